@@ -1,8 +1,8 @@
 use anyhow::{Context, Result};
-use clap::Parser;
-use nargo_add::{nargo_toml, utils};
-use reqwest::Client;
-use serde::Deserialize;
+use clap::{CommandFactory, Parser};
+use nargo_add::output::ErrorKind;
+use nargo_add::registry_client::{FetchError, IndexedDependency, RegistryClient};
+use nargo_add::{completions, config, info, nargo_toml, output, utils};
 use std::fs;
 use std::path::Path;
 use toml_edit::{DocumentMut, InlineTable, Item, Table};
@@ -13,7 +13,12 @@ use toml_edit::{DocumentMut, InlineTable, Item, Table};
 #[command(version)]
 struct Args {
     /// Package name to add (e.g., rocq-of-noir)
-    package_name: String,
+    #[arg(required_unless_present = "from", conflicts_with = "from")]
+    package_name: Option<String>,
+
+    /// Add every package listed in a file, one `name` or `name@version` per line
+    #[arg(long, value_name = "FILE")]
+    from: Option<std::path::PathBuf>,
 
     /// Registry API URL (optional, defaults to NOIR_REGISTRY_URL env var or http://localhost:8080/api)
     #[arg(long)]
@@ -24,141 +29,95 @@ struct Args {
     manifest_path: Option<std::path::PathBuf>,
 
     /// Skip running `nargo check` after adding the dependency
-    #[arg(long)]
+    #[arg(long, conflicts_with = "fetch")]
     no_fetch: bool,
-}
 
-#[derive(Deserialize)]
-struct PackageInfo {
-    name: String,
-    github_repository_url: String,
-    latest_version: Option<String>,
-}
+    /// Run `nargo check` even if `skip_fetch` is set in the config
+    #[arg(long)]
+    fetch: bool,
 
-#[derive(Deserialize)]
-struct GitHubTag {
-    name: String,
-}
+    /// Allow pre-release tags (e.g. 1.0.0-rc.1) when picking the latest tag from GitHub
+    #[arg(long)]
+    pre: bool,
 
-/// Extracts the "{owner}/{repo}" slug from a GitHub URL.
-/// Handles both https://github.com/owner/repo and https://github.com/owner/repo/tree/...
-fn github_slug_from_url(url: &str) -> Option<String> {
-    let url = url.trim_end_matches('/');
-    let stripped = url.strip_prefix("https://github.com/")?;
-    // Take only the first two path segments (owner/repo)
-    let mut parts = stripped.splitn(3, '/');
-    let owner = parts.next()?;
-    let repo = parts.next()?;
-    Some(format!("{}/{}", owner, repo))
-}
+    /// Remove the dependency again if `nargo check` fails because of it
+    #[arg(long)]
+    revert_on_failure: bool,
 
-/// Fetches the latest tag name from the GitHub API for a given repo URL.
-/// Returns None if the repo has no tags or the request fails (non-fatal).
-async fn fetch_latest_github_tag(client: &Client, github_url: &str) -> Option<String> {
-    let slug = github_slug_from_url(github_url)?;
-    let api_url = format!("https://api.github.com/repos/{}/tags", slug);
-
-    let response = client
-        .get(&api_url)
-        .header("User-Agent", "nargo-add")
-        .header("Accept", "application/vnd.github+json")
-        .timeout(std::time::Duration::from_secs(10))
-        .send()
-        .await
-        .ok()?;
-
-    if !response.status().is_success() {
-        return None;
-    }
+    /// Timeout in seconds for registry requests (defaults to NOIR_REGISTRY_TIMEOUT env var)
+    #[arg(long)]
+    timeout: Option<u64>,
+
+    /// Ignore cached registry responses and refresh them (see NOIR_REGISTRY_CACHE_TTL)
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Check the registry is up before doing anything else, and stop early if not
+    #[arg(long)]
+    check_registry: bool,
+
+    /// Suppress informational output (warnings and errors are still shown)
+    #[arg(long, short)]
+    quiet: bool,
 
-    let tags: Vec<GitHubTag> = response.json().await.ok()?;
-    tags.into_iter().next().map(|t| t.name)
+    /// Report registry lookup failures as a JSON object on stderr (implies --quiet)
+    #[arg(long)]
+    json: bool,
 }
 
-/// Fetches package information from the registry with retry logic
-async fn fetch_package_info(registry_url: &str, package_name: &str) -> Result<PackageInfo> {
-    let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .context("Failed to create HTTP client")?;
-
-    let url = format!(
-        "{}/packages/{}",
-        registry_url.trim_end_matches('/'),
-        package_name
-    );
+/// How the dependency is pinned in Nargo.toml
+enum Pin {
+    Tag(String),
+    Rev(String),
+}
 
-    // Retry logic: 3 attempts with exponential backoff
-    let mut last_error: Option<anyhow::Error> = None;
-    for attempt in 0..3 {
-        let response = match client.get(&url).send().await {
-            Ok(resp) => resp,
-            Err(e) => {
-                let err = anyhow::anyhow!("Network error: {}", e);
-                last_error = Some(err);
-                if attempt < 2 {
-                    let delay = std::time::Duration::from_millis(100 * (1 << attempt));
-                    tokio::time::sleep(delay).await;
-                    continue;
-                }
-                return Err(last_error
-                    .unwrap()
-                    .context(format!("Failed to connect to registry at {}", url)));
-            }
-        };
+/// Warns when the package being added depends on a different tag of a
+/// repository the manifest already depends on directly. Both versions would
+/// have to resolve, which usually only fails later at `nargo check`.
+fn warn_on_version_conflicts(
+    manifest_path: &Path,
+    package_name: &str,
+    dependencies: &[IndexedDependency],
+) -> Result<()> {
+    let doc = nargo_toml::read_manifest(manifest_path)?
+        .parse::<DocumentMut>()
+        .context("Failed to parse Nargo.toml")?;
+    let Some(existing) = doc.get("dependencies").and_then(|d| d.as_table_like()) else {
+        return Ok(());
+    };
 
-        match response.status() {
-            status if status.is_success() => match response.json::<PackageInfo>().await {
-                Ok(package) => return Ok(package),
-                Err(e) => {
-                    return Err(anyhow::anyhow!(
-                        "Failed to parse package response from registry: {}. \
-                            The registry may be returning an unexpected format.",
-                        e
-                    ));
-                }
-            },
-            status if status == 404 => {
-                return Err(anyhow::anyhow!(
-                    "Package '{}' not found in registry.\n\
-                    Registry URL: {}\n\
-                    Tip: Check the package name and ensure the registry is up to date.",
-                    package_name,
-                    registry_url
-                ));
-            }
-            status if status == 503 || status == 502 => {
-                last_error = Some(anyhow::anyhow!("Registry server error: {}", status));
-                if attempt < 2 {
-                    let delay = std::time::Duration::from_millis(500 * (1 << attempt));
-                    eprintln!(
-                        "Registry temporarily unavailable, retrying in {:.1}s...",
-                        delay.as_secs_f64()
-                    );
-                    tokio::time::sleep(delay).await;
-                    continue;
-                } else {
-                    return Err(last_error
-                        .unwrap()
-                        .context("Registry server is unavailable"));
-                }
+    for dep in dependencies {
+        let (Some(git_url), Some(tag)) = (&dep.git_url, &dep.tag) else {
+            continue;
+        };
+        for (key, spec) in existing.iter() {
+            let same_repo = spec
+                .get("git")
+                .and_then(|v| v.as_str())
+                .is_some_and(|git| utils::normalize_git_url(git) == *git_url);
+            let directory = spec
+                .get("directory")
+                .and_then(|v| v.as_str())
+                .map(|d| d.trim_matches('/'))
+                .filter(|d| !d.is_empty());
+            if !same_repo || directory != dep.directory.as_deref() {
+                continue;
             }
-            status => {
-                let error_text = response.text().await.unwrap_or_default();
-                return Err(anyhow::anyhow!(
-                    "Registry returned error {}: {}\n\
-                    Registry URL: {}",
-                    status,
-                    error_text,
-                    registry_url
-                ));
+            if let Some(existing_tag) = spec.get("tag").and_then(|v| v.as_str())
+                && existing_tag != tag
+            {
+                eprintln!(
+                    "Warning: '{}' depends on '{}' at tag {}, but Nargo.toml has '{}' at tag {}.",
+                    package_name, dep.name, tag, key, existing_tag
+                );
+                eprintln!(
+                    "   Both versions would be needed, which `nargo check` may reject. \
+                     Consider aligning them."
+                );
             }
         }
     }
-
-    Err(last_error
-        .unwrap_or_else(|| anyhow::anyhow!("Failed to fetch package after 3 attempts"))
-        .context("Registry request failed"))
+    Ok(())
 }
 
 /// Runs `nargo check` in the project directory to fetch and validate the new dependency.
@@ -195,22 +154,67 @@ fn run_nargo_fetch(manifest_path: &Path) -> Result<bool> {
     }
 }
 
+/// Called after `nargo check` failed with the new dependency in place. Re-runs
+/// the check (`check`, normally `run_nargo_fetch`) without it: if that passes,
+/// the new dependency was the cause and stays removed; if it still fails, the
+/// project was already broken and the dependency is put back. Returns whether
+/// it was removed.
+fn revert_if_caused_by_new_dep(
+    manifest_path: &Path,
+    package_name: &str,
+    check: impl FnOnce(&Path) -> Result<bool>,
+) -> Result<bool> {
+    let with_dep = nargo_toml::read_manifest(manifest_path)?;
+    let dep_key = sanitize_dep_key(package_name);
+    if !nargo_toml::remove_dependency(manifest_path, &dep_key)? {
+        return Ok(false);
+    }
+
+    match check(manifest_path) {
+        Ok(_) => {
+            eprintln!(
+                "   `nargo check` passes without '{}', so it was removed from {} again.",
+                package_name,
+                manifest_path.display()
+            );
+            Ok(true)
+        }
+        Err(_) => {
+            fs::write(manifest_path, with_dep)
+                .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+            eprintln!(
+                "   `nargo check` also fails without '{}', so the error predates it; keeping it.",
+                package_name
+            );
+            eprintln!("   Run `nargo check` manually to see the full error.");
+            Ok(false)
+        }
+    }
+}
+
 /// Nargo requires dependency keys to use underscores, not hyphens.
 fn sanitize_dep_key(name: &str) -> String {
     name.replace('-', "_")
 }
 
+/// Canonical form used to spot an existing entry regardless of how it was spelled
+/// (`My-Lib`, `my_lib` and `my-lib` all refer to the same dependency).
+fn normalize_dep_key(name: &str) -> String {
+    sanitize_dep_key(name).to_lowercase()
+}
+
 /// Adds a dependency to Nargo.toml.
 /// `tag` is required by nargo ≥1.0.0-beta.16 for git dependencies.
+/// `directory` points nargo at a package inside a monorepo.
 fn add_dependency_to_nargo_toml(
     manifest_path: &Path,
     package_name: &str,
     github_url: &str,
-    tag: Option<&str>,
+    pin: Option<Pin>,
+    directory: Option<&str>,
 ) -> Result<()> {
     // Read the file
-    let content = fs::read_to_string(manifest_path)
-        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    let content = nargo_toml::read_manifest(manifest_path)?;
 
     // Parse TOML using toml_edit for better formatting control
     let mut doc = content
@@ -227,17 +231,35 @@ fn add_dependency_to_nargo_toml(
         .as_table_mut()
         .context("Failed to access dependencies section")?;
 
-    // Check if dependency already exists (check both hyphenated and underscored forms)
-    if deps.contains_key(&dep_key) || deps.contains_key(package_name) {
-        anyhow::bail!("Dependency '{}' already exists in Nargo.toml", package_name);
+    // Check if dependency already exists under any hyphen/underscore/case spelling
+    let normalized = normalize_dep_key(package_name);
+    if let Some((existing, _)) = deps
+        .iter()
+        .find(|(key, _)| normalize_dep_key(key) == normalized)
+    {
+        anyhow::bail!(
+            "Dependency '{}' already exists in Nargo.toml (as '{}')",
+            package_name,
+            existing
+        );
     }
 
-    // Build the inline table: { git = "...", tag = "..." }
-    // nargo ≥1.0.0-beta.16 requires `tag` for git deps.
+    // Build the inline table: { git = "...", tag = "...", directory = "..." }
+    // nargo ≥1.0.0-beta.16 requires `tag` for git deps; packages published
+    // with `--rev` are pinned with `rev` instead.
     let mut dep_table = InlineTable::new();
     dep_table.insert("git", toml_edit::Value::from(github_url));
-    if let Some(t) = tag {
-        dep_table.insert("tag", toml_edit::Value::from(t));
+    match pin {
+        Some(Pin::Tag(t)) => {
+            dep_table.insert("tag", toml_edit::Value::from(t));
+        }
+        Some(Pin::Rev(r)) => {
+            dep_table.insert("rev", toml_edit::Value::from(r));
+        }
+        None => {}
+    }
+    if let Some(d) = directory {
+        dep_table.insert("directory", toml_edit::Value::from(d));
     }
 
     deps.insert(
@@ -252,34 +274,85 @@ fn add_dependency_to_nargo_toml(
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
-
-    // Get registry URL
-    let registry_url = utils::get_registry_url(args.registry);
+/// One line of a `--from` file: `name`, `name@version`, either optionally
+/// followed by `?` to mark it optional
+struct DepLine {
+    line: usize,
+    spec: String,
+    name: String,
+    version: Option<String>,
+    optional: bool,
+}
 
-    // Find Nargo.toml
-    let current_dir = std::env::current_dir().context("Failed to get current directory")?;
-    let manifest_path = match args.manifest_path {
-        Some(path) => {
-            if !path.exists() {
-                anyhow::bail!("Nargo.toml not found at: {}", path.display());
-            }
-            path
+/// Parses a `--from` file, skipping blank lines and `#` comments
+fn parse_deps_file(content: &str) -> Result<Vec<DepLine>> {
+    let mut deps = Vec::new();
+    for (i, raw) in content.lines().enumerate() {
+        let spec = raw.trim();
+        if spec.is_empty() || spec.starts_with('#') {
+            continue;
         }
-        None => nargo_toml::find_nargo_toml(&current_dir)?,
-    };
+        let (entry, optional) = match spec.strip_suffix('?') {
+            Some(entry) => (entry.trim_end(), true),
+            None => (spec, false),
+        };
+        let (name, version) = match entry.split_once('@') {
+            Some((name, version)) => (name.trim(), Some(version.trim().to_string())),
+            None => (entry, None),
+        };
+        if name.is_empty() || version.as_deref() == Some("") {
+            anyhow::bail!(
+                "line {}: expected `name` or `name@version`, got '{}'",
+                i + 1,
+                spec
+            );
+        }
+        deps.push(DepLine {
+            line: i + 1,
+            spec: spec.to_string(),
+            name: name.to_string(),
+            version,
+            optional,
+        });
+    }
+    Ok(deps)
+}
 
-    eprintln!(
-        "Fetching package '{}' from registry...",
-        args.package_name
-    );
-    eprintln!("   Registry: {}", registry_url);
+/// Settings shared by every package added in one run
+struct AddContext<'a> {
+    registry: RegistryClient,
+    manifest_path: &'a Path,
+    json: bool,
+    /// Whether `--pre` lets pre-release tags count as the latest
+    allow_pre: bool,
+}
+
+/// Looks a package up and writes it to Nargo.toml. `version` overrides the
+/// registry's latest version. Returns whether the dependency got a version,
+/// since `nargo check` can only fetch pinned git dependencies.
+async fn add_package(
+    ctx: &AddContext<'_>,
+    package_name: &str,
+    version: Option<&str>,
+) -> Result<bool> {
+    info!("Fetching package '{}' from registry...", package_name);
+    info!("   Registry: {}", ctx.registry.registry_url());
 
     // Fetch package info
-    let package_info = match fetch_package_info(&registry_url, &args.package_name).await {
+    let package_info = match ctx.registry.get_package(package_name).await {
         Ok(info) => info,
+        Err(e) if ctx.json => {
+            output::print_json_error(&e.message, e.kind, package_name, &e.suggestions);
+            return Err(e.into());
+        }
+        Err(e) if e.kind == ErrorKind::NotFound => {
+            // The registry answered, so server troubleshooting doesn't apply
+            eprintln!("Error: {}", e);
+            if let Some(closest) = e.suggestions.first() {
+                eprintln!("\nTo add the closest match: nargo add {}", closest);
+            }
+            return Err(e.into());
+        }
         Err(e) => {
             eprintln!("Error: {}", e);
             eprintln!("\nTroubleshooting:");
@@ -287,101 +360,365 @@ async fn main() -> Result<()> {
             eprintln!("   - Verify the package name is correct");
             eprintln!(
                 "   - Try: curl {}/packages/{}",
-                registry_url, args.package_name
+                ctx.registry.registry_url(),
+                package_name
             );
-            return Err(e);
+            return Err(e.into());
         }
     };
 
-    eprintln!("Found package: {}", package_info.name);
-    eprintln!("   Repository: {}", package_info.github_repository_url);
+    info!("Found package: {}", package_info.name);
+    info!("   Repository: {}", package_info.github_repository_url);
+    if let Some(directory) = &package_info.directory {
+        info!("   Directory: {}", directory);
+    }
+    if package_info.deprecated {
+        match &package_info.deprecation_message {
+            Some(message) => eprintln!("Warning: '{}' is deprecated: {}", package_name, message),
+            None => eprintln!("Warning: '{}' is deprecated.", package_name),
+        }
+    }
+
+    if let Some(dependencies) = ctx.registry.dependencies(package_name).await
+        && let Err(e) = warn_on_version_conflicts(ctx.manifest_path, package_name, &dependencies)
+    {
+        eprintln!("Warning: Could not check for version conflicts: {}", e);
+    }
 
-    // Resolve the version to use: registry value → GitHub tag → none
-    let resolved_version: Option<String> = if package_info.latest_version.is_some() {
-        let v = package_info.latest_version.clone();
-        eprintln!("   Latest version: {}", v.as_deref().unwrap());
-        v
+    // Resolve the version to use: requested → registry value → GitHub tag → none
+    let pin = if let Some(v) = version {
+        info!("   Requested version: {}", v);
+        Some(Pin::Tag(v.to_string()))
+    } else if let Some(v) = package_info.latest_version.clone() {
+        if package_info.latest_is_rev() {
+            info!("   Latest version: commit {}", v);
+            Some(Pin::Rev(v))
+        } else {
+            info!("   Latest version: {}", v);
+            Some(Pin::Tag(v))
+        }
     } else {
-        eprintln!("   Checking GitHub for latest tag...");
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(15))
-            .build()
-            .unwrap_or_default();
-        match fetch_latest_github_tag(&client, &package_info.github_repository_url).await {
+        info!("   Checking GitHub for latest tag...");
+        let latest = ctx
+            .registry
+            .latest_tag(
+                package_name,
+                &package_info.github_repository_url,
+                ctx.allow_pre,
+            )
+            .await;
+        match latest {
             Some(tag) => {
-                eprintln!("   Latest tag: {} (from GitHub)", tag);
-                Some(tag)
+                info!("   Latest tag: {} (from GitHub)", tag);
+                Some(Pin::Tag(tag))
             }
             None => {
                 eprintln!("   No version tag found,dependency will be added without a tag.");
-                eprintln!("      Add a `tag` manually in Nargo.toml once the author publishes a release.");
+                if !ctx.allow_pre {
+                    eprintln!("      Pass --pre to also consider pre-release tags.");
+                }
+                eprintln!(
+                    "      Add a `tag` manually in Nargo.toml once the author publishes a release."
+                );
                 None
             }
         }
     };
+    let pinned = pin.is_some();
+    let tag = match &pin {
+        Some(Pin::Tag(tag)) => Some(tag.clone()),
+        _ => None,
+    };
 
     // Add to Nargo.toml
-    match add_dependency_to_nargo_toml(
-        &manifest_path,
-        &args.package_name,
+    if let Err(e) = add_dependency_to_nargo_toml(
+        ctx.manifest_path,
+        package_name,
         &package_info.github_repository_url,
-        resolved_version.as_deref(),
+        pin,
+        package_info.directory.as_deref(),
     ) {
-        Ok(_) => {
-            eprintln!(
-                "Added '{}' to {}",
-                args.package_name,
-                manifest_path.display()
-            );
+        eprintln!("Failed to add dependency: {}", e);
+        return Err(e);
+    }
+    info!(
+        "Added '{}' to {}",
+        package_name,
+        ctx.manifest_path.display()
+    );
 
-            // Validate the TOML was written correctly
-            if let Err(e) = nargo_toml::validate_nargo_toml(&manifest_path) {
-                eprintln!("Warning: Could not validate Nargo.toml: {}", e);
-                eprintln!("   Please check the file manually");
-            }
+    // Validate the TOML was written correctly
+    if let Err(e) = nargo_toml::validate_nargo_toml(ctx.manifest_path) {
+        eprintln!("Warning: Could not validate Nargo.toml: {}", e);
+        eprintln!("   Please check the file manually");
+    }
+
+    // Record the download,fire-and-forget, non-fatal
+    ctx.registry
+        .record_download(package_name, tag.as_deref())
+        .await;
+
+    Ok(pinned)
+}
+
+/// Fetches and validates the new dependencies via `nargo check`.
+/// `--revert-on-failure` only applies to a single added package.
+fn check_added(args: &Args, manifest_path: &Path, added: &[String]) -> Result<()> {
+    let skip_fetch = if args.fetch {
+        false
+    } else if args.no_fetch {
+        true
+    } else {
+        config::Config::load()
+            .map(|cfg| cfg.skip_fetch())
+            .unwrap_or(false)
+    };
+    if skip_fetch {
+        return Ok(());
+    }
 
-            // Record the download,fire-and-forget, non-fatal
-            let download_url = format!(
-                "{}/packages/{}/download",
-                registry_url.trim_end_matches('/'),
-                args.package_name
+    info!("Fetching dependency with `nargo check`...");
+    match run_nargo_fetch(manifest_path) {
+        Ok(true) => {
+            info!("Dependency fetched and validated successfully!");
+        }
+        Ok(false) => {
+            eprintln!("nargo not found in PATH,skipping fetch.");
+            eprintln!(
+                "   Run `nargo check` manually to pull the dependency, or install nargo first."
             );
-            let ping_client = Client::builder()
-                .timeout(std::time::Duration::from_secs(5))
-                .build()
-                .unwrap_or_default();
-            let _ = ping_client.post(&download_url).send().await;
+        }
+        Err(e) if args.revert_on_failure && added.len() == 1 => {
+            eprintln!("nargo check failed: {}", e);
+            if revert_if_caused_by_new_dep(manifest_path, &added[0], run_nargo_fetch)? {
+                anyhow::bail!("'{}' was not added", added[0]);
+            }
         }
         Err(e) => {
-            eprintln!("Failed to add dependency: {}", e);
-            return Err(e);
+            eprintln!("nargo check failed: {}", e);
+            eprintln!("   The dependency was added to Nargo.toml but could not be fetched.");
+            eprintln!("   This may be caused by other unresolved dependencies in your project.");
+            eprintln!("   Run `nargo check` manually to see the full error, or");
+            eprintln!("   run `nargo remove {}` to undo.", added.join(" "));
         }
     }
+    Ok(())
+}
 
-    // Fetch and validate the dependency via `nargo check`
-    // Skip if no tag is available,nargo ≥1.0.0-beta.16 requires `tag` for git deps,
-    // so `nargo check` would fail anyway without one.
-    if !args.no_fetch && resolved_version.is_some() {
-        eprintln!("Fetching dependency with `nargo check`...");
-        match run_nargo_fetch(&manifest_path) {
-            Ok(true) => {
-                eprintln!("Dependency fetched and validated successfully!");
-            }
-            Ok(false) => {
-                eprintln!("nargo not found in PATH,skipping fetch.");
-                eprintln!(
-                    "   Run `nargo check` manually to pull the dependency, or install nargo first."
-                );
-            }
+/// `--from`: adds every package listed in the file, then prints a per-line
+/// summary. Fails if any line not marked optional failed.
+async fn add_from_file(args: &Args, ctx: &AddContext<'_>, path: &Path) -> Result<()> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let deps = parse_deps_file(&content).with_context(|| format!("In {}", path.display()))?;
+    if deps.is_empty() {
+        anyhow::bail!("No packages listed in {}", path.display());
+    }
+
+    let mut results = Vec::new();
+    let mut pinned = Vec::new();
+    for dep in &deps {
+        let result = add_package(ctx, &dep.name, dep.version.as_deref()).await;
+        if let Ok(true) = result {
+            pinned.push(dep.name.clone());
+        }
+        results.push(result);
+    }
+
+    // Skip the check when nothing fetchable was added, as for a single package
+    if !pinned.is_empty() {
+        check_added(args, ctx.manifest_path, &pinned)?;
+    }
+
+    println!("\nSummary for {}:", path.display());
+    let mut required_failures = 0;
+    for (dep, result) in deps.iter().zip(&results) {
+        match result {
+            Ok(_) => println!("   line {}: {} added", dep.line, dep.spec),
             Err(e) => {
-                eprintln!("nargo check failed: {}", e);
-                eprintln!("   The dependency was added to Nargo.toml but could not be fetched.");
-                eprintln!("   This may be caused by other unresolved dependencies in your project.");
-                eprintln!("   Run `nargo check` manually to see the full error, or");
-                eprintln!("   run `nargo remove {}` to undo.", args.package_name);
+                let reason = e.to_string();
+                let reason = reason.lines().next().unwrap_or_default();
+                if dep.optional {
+                    println!(
+                        "   line {}: {} failed (optional): {}",
+                        dep.line, dep.spec, reason
+                    );
+                } else {
+                    required_failures += 1;
+                    println!("   line {}: {} failed: {}", dep.line, dep.spec, reason);
+                }
             }
         }
     }
 
+    if required_failures > 0 {
+        anyhow::bail!(
+            "{} required package(s) from {} could not be added",
+            required_failures,
+            path.display()
+        );
+    }
     Ok(())
 }
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    completions::handle_with_package_names(Args::command()).await;
+    let args = Args::parse();
+    output::set_quiet(args.quiet || args.json);
+
+    // Find Nargo.toml
+    let current_dir = std::env::current_dir().context("Failed to get current directory")?;
+    let manifest_path = match &args.manifest_path {
+        Some(path) => {
+            if !path.exists() {
+                anyhow::bail!("Nargo.toml not found at: {}", path.display());
+            }
+            path.clone()
+        }
+        None => nargo_toml::find_nargo_toml(&current_dir)?,
+    };
+
+    // The project may pin its registry, so this needs the manifest first
+    let (registry_url, _) =
+        utils::get_project_registry_url(args.registry.clone(), Some(&manifest_path));
+    let registry_timeout = utils::get_registry_timeout(args.timeout);
+
+    let ctx = AddContext {
+        registry: RegistryClient::new(&registry_url, registry_timeout).no_cache(args.no_cache),
+        manifest_path: &manifest_path,
+        json: args.json,
+        allow_pre: args.pre,
+    };
+    if args.check_registry {
+        ctx.registry.check_health().await?;
+    }
+
+    if let Some(path) = &args.from {
+        return add_from_file(&args, &ctx, path).await;
+    }
+
+    let package_name = args
+        .package_name
+        .as_deref()
+        .context("A package name or --from <file> is required")?;
+    let pinned = match add_package(&ctx, package_name, None).await {
+        Ok(pinned) => pinned,
+        // The JSON error, or the not-found report with its suggestions, is the whole report
+        Err(e)
+            if e.downcast_ref::<FetchError>()
+                .is_some_and(|f| args.json || f.kind == ErrorKind::NotFound) =>
+        {
+            std::process::exit(1)
+        }
+        Err(e) => return Err(e),
+    };
+
+    // Skip if no tag is available,nargo ≥1.0.0-beta.16 requires `tag` for git deps,
+    // so `nargo check` would fail anyway without one.
+    if pinned {
+        check_added(&args, &manifest_path, &[package_name.to_string()])?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(content: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), content).unwrap();
+        file
+    }
+
+    const GIT: &str = "https://github.com/owner/my-lib";
+
+    #[test]
+    fn dep_keys_normalize_case_and_separators() {
+        for spelling in ["my-lib", "my_lib", "My-Lib", "MY_LIB", "my-Lib"] {
+            assert_eq!(normalize_dep_key(spelling), "my_lib", "{}", spelling);
+        }
+        assert_ne!(normalize_dep_key("my-lib2"), normalize_dep_key("my-lib"));
+    }
+
+    #[test]
+    fn existing_dependency_is_found_under_any_spelling() {
+        for (existing, requested) in [
+            ("my_lib", "my-lib"),
+            ("My_Lib", "my-lib"),
+            ("my-lib", "MY_LIB"),
+            ("\"my-lib\"", "my_lib"),
+        ] {
+            let file = manifest(&format!(
+                "[package]\nname = \"app\"\n\n[dependencies]\n{} = {{ git = \"{}\", tag = \"v1\" }}\n",
+                existing, GIT
+            ));
+            let err = add_dependency_to_nargo_toml(file.path(), requested, GIT, None, None)
+                .expect_err("dependency already present");
+            assert!(
+                err.to_string().contains("already exists"),
+                "{} vs {}: {}",
+                existing,
+                requested,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn new_dependency_is_added_under_an_underscored_key() {
+        let file =
+            manifest("[package]\nname = \"app\"\n\n[dependencies]\nother = { git = \"x\" }\n");
+        add_dependency_to_nargo_toml(
+            file.path(),
+            "my-lib",
+            GIT,
+            Some(Pin::Tag("v1.0.0".to_string())),
+            None,
+        )
+        .unwrap();
+
+        let doc = fs::read_to_string(file.path())
+            .unwrap()
+            .parse::<DocumentMut>()
+            .unwrap();
+        let dep = doc["dependencies"]["my_lib"].as_inline_table().unwrap();
+        assert_eq!(dep.get("git").and_then(|v| v.as_str()), Some(GIT));
+        assert_eq!(dep.get("tag").and_then(|v| v.as_str()), Some("v1.0.0"));
+        assert!(doc["dependencies"].get("other").is_some());
+    }
+
+    const WITH_DEP: &str = "[package]\nname = \"app\"\n\n[dependencies]\n# pinned\nother = { git = \"x\" }\nmy_lib = { git = \"https://github.com/owner/my-lib\", tag = \"v1\" }\n";
+
+    #[test]
+    fn new_dependency_stays_removed_when_the_check_passes_without_it() {
+        let file = manifest(WITH_DEP);
+        let removed = revert_if_caused_by_new_dep(file.path(), "my-lib", |path| {
+            let during = fs::read_to_string(path).unwrap();
+            assert!(!during.contains("my_lib"), "checked with the dep still in");
+            assert!(during.contains("other"));
+            Ok(true)
+        })
+        .unwrap();
+
+        assert!(removed);
+        let after = fs::read_to_string(file.path()).unwrap();
+        assert!(!after.contains("my_lib"));
+        assert!(after.contains("other = { git = \"x\" }"));
+    }
+
+    #[test]
+    fn new_dependency_is_restored_when_the_check_fails_without_it() {
+        let file = manifest(WITH_DEP);
+        let removed = revert_if_caused_by_new_dep(file.path(), "my-lib", |_| {
+            Err(anyhow::anyhow!("error in src/main.nr"))
+        })
+        .unwrap();
+
+        assert!(!removed);
+        // Put back byte for byte, comments and order included
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), WITH_DEP);
+    }
+}
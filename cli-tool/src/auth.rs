@@ -1,52 +1,22 @@
-use anyhow::{Context, Result};
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Serialize)]
-pub struct GitHubAuthRequest {
-    pub github_token: String,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct GitHubAuthResponse {
-    pub success: bool,
-    pub api_key: Option<String>,
-    pub message: String,
-    #[allow(dead_code)]
-    pub github_username: Option<String>,
-}
-
-/// Authenticates with GitHub. Returns Some(api_key) on new-user creation,
-/// None if the user already existed (backend only issues a raw token once).
-pub async fn authenticate_github(
-    registry_url: &str,
-    github_token: &str,
-) -> Result<Option<String>> {
-    let client = Client::new();
-    let auth_url = format!("{}/auth/github", registry_url.trim_end_matches('/'));
-
-    let response = client
-        .post(&auth_url)
-        .json(&GitHubAuthRequest {
-            github_token: github_token.to_string(),
-        })
-        .send()
-        .await
-        .context("Failed to connect to registry")?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        anyhow::bail!("Authentication failed: {}", error_text);
+/// Reads the token of an authenticated GitHub CLI session via `gh auth token`.
+/// Returns None if `gh` isn't installed or isn't logged in.
+pub fn github_token_from_gh_cli() -> Option<String> {
+    let output = std::process::Command::new("gh")
+        .args(["auth", "token"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
     }
 
-    let auth_response: GitHubAuthResponse = response
-        .json()
-        .await
-        .context("Failed to parse authentication response")?;
-
-    if !auth_response.success {
-        anyhow::bail!("Authentication failed: {}", auth_response.message);
-    }
+    let token = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    if token.is_empty() { None } else { Some(token) }
+}
 
-    Ok(auth_response.api_key)
+/// Resolves a GitHub token from the --github-token flag, then the GITHUB_TOKEN
+/// env var, then the GitHub CLI as a last resort.
+pub fn resolve_github_token(flag: Option<String>) -> Option<String> {
+    flag.or_else(|| std::env::var("GITHUB_TOKEN").ok())
+        .or_else(github_token_from_gh_cli)
 }
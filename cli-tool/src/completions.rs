@@ -0,0 +1,117 @@
+//! Shell completion scripts for the CLI binaries.
+//!
+//! Every binary answers a hidden `completions <shell>` command (bash, zsh,
+//! fish, elvish or powershell) by printing its script. Binaries that take a
+//! registry package name also answer `completions names`, which prints the
+//! registry's package names; the bash and fish scripts call it to complete
+//! that argument.
+
+use crate::registry_client::RegistryClient;
+use crate::utils;
+use clap::Command;
+use clap_complete::Shell;
+use std::io::Write;
+use std::time::Duration;
+
+/// Completion runs while the user waits on <TAB>, so give up quickly
+const NAMES_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The argument after `completions`, if the binary was invoked as exactly
+/// `<bin> completions <arg>`. Anything else is left for the binary to parse,
+/// so a package literally named "completions" still works as one.
+fn completions_arg() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    match (args.next(), args.next(), args.next()) {
+        (Some(command), Some(arg), None) if command == "completions" => Some(arg),
+        _ => None,
+    }
+}
+
+/// Handles `<bin> completions <shell>`, exiting once the script is printed
+pub fn handle(cmd: Command) {
+    if let Some(shell) = completions_arg().and_then(|arg| arg.parse::<Shell>().ok()) {
+        print_script(cmd, shell, None);
+        std::process::exit(0);
+    }
+}
+
+/// Like [`handle`], for binaries whose first argument is a registry package:
+/// the script completes it via `<bin> completions names`, handled here too.
+pub async fn handle_with_package_names(cmd: Command) {
+    let Some(arg) = completions_arg() else {
+        return;
+    };
+    if arg == "names" {
+        print_package_names().await;
+        std::process::exit(0);
+    }
+    if let Ok(shell) = arg.parse::<Shell>() {
+        let bin = cmd.get_name().to_string();
+        print_script(cmd, shell, Some(&bin));
+        std::process::exit(0);
+    }
+}
+
+/// Writes the completion script for `cmd` to stdout. `names_from` is the
+/// binary whose first positional argument (or, for the `nargo` wrapper, the
+/// argument after `add`/`open`) is completed with registry package names.
+pub fn print_script(mut cmd: Command, shell: Shell, names_from: Option<&str>) {
+    let bin = cmd.get_name().to_string();
+    let mut stdout = std::io::stdout();
+    clap_complete::generate(shell, &mut cmd, &bin, &mut stdout);
+
+    let Some(names_from) = names_from else {
+        return;
+    };
+    let names = format!("{} completions names 2>/dev/null", names_from);
+    let snippet = match (shell, bin.as_str()) {
+        (Shell::Bash, "nargo") => format!(
+            r#"
+_nargo_registry_names() {{
+    if [[ ${{COMP_CWORD}} -eq 2 && ( "${{COMP_WORDS[1]}}" == add || "${{COMP_WORDS[1]}}" == open ) && "${{COMP_WORDS[2]}}" != -* ]]; then
+        COMPREPLY=( $(compgen -W "$({names})" -- "${{COMP_WORDS[2]}}") )
+        return 0
+    fi
+    _nargo "$@"
+}}
+complete -F _nargo_registry_names -o bashdefault -o default nargo
+"#
+        ),
+        (Shell::Bash, _) => format!(
+            r#"
+_{fn_name}_registry_names() {{
+    if [[ ${{COMP_CWORD}} -eq 1 && "${{COMP_WORDS[1]}}" != -* ]]; then
+        COMPREPLY=( $(compgen -W "$({names})" -- "${{COMP_WORDS[1]}}") )
+        return 0
+    fi
+    _{bin} "$@"
+}}
+complete -F _{fn_name}_registry_names -o bashdefault -o default {bin}
+"#,
+            fn_name = bin.replace('-', "_"),
+        ),
+        (Shell::Fish, "nargo") => format!(
+            "complete -c nargo -n '__fish_seen_subcommand_from add open' -f -a '({})'\n",
+            names
+        ),
+        (Shell::Fish, _) => format!(
+            "complete -c {} -n 'test (count (commandline -opc)) -eq 1' -f -a '({})'\n",
+            bin, names
+        ),
+        _ => return,
+    };
+    let _ = stdout.write_all(snippet.as_bytes());
+}
+
+/// Prints every package name in the registry, one per line. Failures print
+/// nothing: a completion script has nowhere to show an error.
+async fn print_package_names() {
+    let (registry_url, _) = utils::get_registry_url(None);
+    let registry = RegistryClient::new(&registry_url, Some(NAMES_TIMEOUT));
+    let Ok(names) = registry.package_names().await else {
+        return;
+    };
+    for name in names {
+        println!("{}", name);
+    }
+}
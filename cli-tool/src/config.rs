@@ -1,15 +1,47 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
 use std::fs;
 use std::path::PathBuf;
+
+/// Keychain service and account the API key is stored under
+const KEYRING_SERVICE: &str = "noir-registry";
+const KEYRING_USER: &str = "api_key";
+
+/// Where the API key was saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStorage {
+    /// The OS keychain (macOS Keychain, Windows Credential Manager, Secret Service)
+    Keyring,
+    /// The `api_key` field of this file
+    File,
+}
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Config {
+    /// Only written when no keychain was available
     pub api_key: Option<String>,
+    /// Where `set_api_key` put the key; configs without it keep it in `api_key`
+    pub storage: Option<KeyStorage>,
     pub registry_url: Option<String>,
+    /// Make `nargo add --no-fetch` the default (`--fetch` overrides it)
+    pub skip_fetch: Option<bool>,
+    /// Check once a day for a newer CLI release (on unless set to false)
+    pub update_check: Option<bool>,
+    /// Seconds to reuse registry responses for (off when unset or 0)
+    pub cache_ttl: Option<u64>,
+    /// Key read from the keychain, fetched on first use
+    #[serde(skip)]
+    keyring_key: OnceCell<Option<String>>,
+}
+
+fn keyring_entry() -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
 }
 impl Config {
     /// Get the path to the config file
-    fn config_path() -> Result<PathBuf> {
+    pub fn config_path() -> Result<PathBuf> {
         let config_dir = dirs::config_dir()
             .or_else(|| dirs::home_dir().map(|h| h.join(".config")))
             .context("Could not find config directory")?;
@@ -28,9 +60,7 @@ impl Config {
 
         let content = fs::read_to_string(&path).context("Failed to read config file")?;
 
-        toml::from_str(&content)
-            .context("Failed to parse config file")
-            .map_err(Into::into)
+        toml::from_str(&content).context("Failed to parse config file")
     }
     /// Save config to file
     pub fn save(&self) -> Result<()> {
@@ -42,14 +72,47 @@ impl Config {
         Ok(())
     }
 
-    /// Get API key from config
+    /// Get API key from the keychain or the config file, wherever it was saved
     pub fn get_api_key(&self) -> Option<&str> {
-        self.api_key.as_deref()
+        match self.storage {
+            Some(KeyStorage::Keyring) => self
+                .keyring_key
+                .get_or_init(|| keyring_entry().and_then(|e| e.get_password()).ok())
+                .as_deref(),
+            _ => self.api_key.as_deref(),
+        }
     }
 
-    /// Set API key in config
-    pub fn set_api_key(&mut self, api_key: String) {
-        self.api_key = Some(api_key);
+    /// Set API key, in the OS keychain when one is available and otherwise in
+    /// the config file. Returns where it went; `save` records that.
+    pub fn set_api_key(&mut self, api_key: String) -> KeyStorage {
+        match keyring_entry().and_then(|e| e.set_password(&api_key)) {
+            Ok(()) => {
+                self.api_key = None;
+                self.storage = Some(KeyStorage::Keyring);
+                self.keyring_key = OnceCell::from(Some(api_key));
+            }
+            Err(_) => {
+                self.api_key = Some(api_key);
+                self.storage = Some(KeyStorage::File);
+            }
+        }
+        self.key_storage()
+    }
+
+    /// Where the API key is kept
+    pub fn key_storage(&self) -> KeyStorage {
+        self.storage.unwrap_or(KeyStorage::File)
+    }
+
+    /// Whether `nargo add` should skip `nargo check` by default
+    pub fn skip_fetch(&self) -> bool {
+        self.skip_fetch.unwrap_or(false)
+    }
+
+    /// Whether to check for a newer CLI release
+    pub fn update_check(&self) -> bool {
+        self.update_check.unwrap_or(true)
     }
 
     /// Set registry URL in config
@@ -57,3 +120,33 @@ impl Config {
         self.registry_url = Some(registry_url);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configs_without_storage_read_the_key_from_the_file() {
+        let cfg: Config = toml::from_str("api_key = \"nr_plain\"\n").unwrap();
+        assert_eq!(cfg.key_storage(), KeyStorage::File);
+        assert_eq!(cfg.get_api_key(), Some("nr_plain"));
+    }
+
+    #[test]
+    fn keychain_keys_are_not_written_to_the_file() {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+        let mut cfg = Config {
+            api_key: Some("nr_old".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            cfg.set_api_key("nr_secret".to_string()),
+            KeyStorage::Keyring
+        );
+        assert_eq!(cfg.get_api_key(), Some("nr_secret"));
+
+        let written = toml::to_string_pretty(&cfg).unwrap();
+        assert!(written.contains("storage = \"keyring\""));
+        assert!(!written.contains("nr_"));
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::Result;
+use clap::{CommandFactory, Parser, Subcommand};
+use nargo_add::{completions, config, nargo_toml, response_cache, utils};
+
+#[derive(Parser)]
+#[command(name = "nargo-config")]
+#[command(about = "Inspect the Noir registry CLI configuration (use: nargo config <command>)")]
+#[command(version)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Registry API URL, to see how a flag would override the other sources
+    #[arg(long, global = true)]
+    registry: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the settings commands will actually use, and where each comes from
+    Effective,
+}
+
+/// Shows only the first few characters of a secret
+fn redact(secret: &str) -> String {
+    let prefix: String = secret.chars().take(8).collect();
+    format!("{}... (redacted)", prefix)
+}
+
+fn effective(registry: Option<String>) -> Result<()> {
+    // Show the project's registry setting when run inside a Noir project
+    let manifest_path = std::env::current_dir()
+        .ok()
+        .and_then(|dir| nargo_toml::find_nargo_toml(&dir).ok());
+    let (registry_url, source) =
+        utils::get_project_registry_url(registry, manifest_path.as_deref());
+    println!("registry_url: {} (from {})", registry_url, source);
+
+    match utils::get_registry_timeout(None) {
+        Some(timeout) => println!(
+            "timeout: {}s (from NOIR_REGISTRY_TIMEOUT env var)",
+            timeout.as_secs()
+        ),
+        None => println!("timeout: per-command default"),
+    }
+
+    match response_cache::ttl_setting() {
+        Some((ttl, source)) => println!("cache_ttl: {}s (from {})", ttl.as_secs(), source),
+        None => println!("cache_ttl: off"),
+    }
+
+    let config_path = config::Config::config_path()?;
+    match config::Config::load() {
+        Ok(cfg) => {
+            println!("config_file: {}", config_path.display());
+            match cfg.get_api_key() {
+                Some(key) => println!(
+                    "api_key: {} (in {})",
+                    redact(key),
+                    match cfg.key_storage() {
+                        config::KeyStorage::Keyring => "OS keychain",
+                        config::KeyStorage::File => "config file",
+                    }
+                ),
+                None => println!("api_key: not set (run 'nargo login')"),
+            }
+            println!("skip_fetch: {}", cfg.skip_fetch());
+            println!("update_check: {}", cfg.update_check());
+        }
+        Err(e) => {
+            println!("config_file: {} (unreadable: {})", config_path.display(), e);
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    completions::handle(Args::command());
+    let args = Args::parse();
+
+    match args.command {
+        Command::Effective => effective(args.registry),
+    }
+}
@@ -0,0 +1,284 @@
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser};
+use nargo_add::nargo_toml::{self, ManifestDependency};
+use nargo_add::registry_client::RegistryClient;
+use nargo_add::{auth, completions, github, http, info, utils};
+use toml_edit::DocumentMut;
+
+#[derive(Parser)]
+#[command(name = "nargo-diff")]
+#[command(
+    about = "Compare the Nargo.toml of two versions of a package (use: nargo diff <package> <from> <to>)"
+)]
+#[command(version)]
+struct Args {
+    /// Package name in the registry
+    package_name: String,
+
+    /// Older version (a tag of the package's repository)
+    from: String,
+
+    /// Newer version (a tag of the package's repository)
+    to: String,
+
+    /// Registry API URL (optional, defaults to NOIR_REGISTRY_URL env var)
+    #[arg(long)]
+    registry: Option<String>,
+
+    /// Timeout in seconds for registry requests (defaults to NOIR_REGISTRY_TIMEOUT env var)
+    #[arg(long)]
+    timeout: Option<u64>,
+
+    /// Ignore cached registry responses and refresh them (see NOIR_REGISTRY_CACHE_TTL)
+    #[arg(long)]
+    no_cache: bool,
+
+    /// GitHub token for fetching the manifests (defaults to GITHUB_TOKEN, then
+    /// `gh auth token`); without one GitHub allows 60 lookups an hour
+    #[arg(long)]
+    github_token: Option<String>,
+}
+
+/// How one `[package]` field or dependency differs between the versions
+#[derive(Debug, PartialEq)]
+enum Change {
+    Added {
+        key: String,
+        value: String,
+    },
+    Removed {
+        key: String,
+        value: String,
+    },
+    Changed {
+        key: String,
+        from: String,
+        to: String,
+    },
+}
+
+/// `[package]` fields of a manifest, in order, values as written
+fn package_fields(content: &str) -> Result<Vec<(String, String)>> {
+    let doc = content
+        .parse::<DocumentMut>()
+        .context("Failed to parse Nargo.toml")?;
+    let Some(package) = doc.get("package").and_then(|p| p.as_table_like()) else {
+        return Ok(Vec::new());
+    };
+    Ok(package
+        .iter()
+        .filter_map(|(key, item)| {
+            let value = item.as_value()?.to_string();
+            Some((key.to_string(), value.trim().to_string()))
+        })
+        .collect())
+}
+
+/// A dependency's source, e.g. `git https://..., tag v1.0.0`
+fn describe(dep: &ManifestDependency) -> String {
+    [
+        ("git", &dep.git),
+        ("tag", &dep.tag),
+        ("rev", &dep.rev),
+        ("path", &dep.path),
+    ]
+    .iter()
+    .filter_map(|(field, value)| value.as_ref().map(|v| format!("{} {}", field, v)))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+fn dependency_entries(content: &str) -> Result<Vec<(String, String)>> {
+    Ok(nargo_toml::parse_dependencies(content)?
+        .iter()
+        .map(|dep| (dep.name.clone(), describe(dep)))
+        .collect())
+}
+
+/// Compares two lists of entries by key: removals and changes in the old
+/// order, then additions in the new order
+fn diff_entries(old: &[(String, String)], new: &[(String, String)]) -> Vec<Change> {
+    let find = |entries: &[(String, String)], key: &str| {
+        entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    };
+    let mut changes: Vec<Change> = old
+        .iter()
+        .filter_map(|(key, value)| match find(new, key) {
+            None => Some(Change::Removed {
+                key: key.clone(),
+                value: value.clone(),
+            }),
+            Some(to) if to != *value => Some(Change::Changed {
+                key: key.clone(),
+                from: value.clone(),
+                to,
+            }),
+            Some(_) => None,
+        })
+        .collect();
+    changes.extend(
+        new.iter()
+            .filter(|(key, _)| find(old, key).is_none())
+            .map(|(key, value)| Change::Added {
+                key: key.clone(),
+                value: value.clone(),
+            }),
+    );
+    changes
+}
+
+fn print_section(title: &str, changes: &[Change]) {
+    if changes.is_empty() {
+        return;
+    }
+    println!("[{}]", title);
+    for change in changes {
+        match change {
+            Change::Added { key, value } => println!("  + {} = {}", key, value),
+            Change::Removed { key, value } => println!("  - {} = {}", key, value),
+            Change::Changed { key, from, to } => println!("  ~ {}: {} -> {}", key, from, to),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    completions::handle(Args::command());
+    let args = Args::parse();
+    let (registry_url, _) = utils::get_registry_url(args.registry.clone());
+
+    let registry = RegistryClient::new(&registry_url, utils::get_registry_timeout(args.timeout))
+        .no_cache(args.no_cache);
+    let package = registry.get_package(&args.package_name).await?;
+    let slug = github::github_slug_from_url(&package.github_repository_url).with_context(|| {
+        format!(
+            "'{}' is not hosted on GitHub ({})",
+            package.name, package.github_repository_url
+        )
+    })?;
+
+    let token = auth::resolve_github_token(args.github_token.clone());
+    let client = http::build_client();
+    info!(
+        "Comparing {} {} -> {} ({})",
+        package.name, args.from, args.to, slug
+    );
+    let mut manifests = Vec::new();
+    for version in [&args.from, &args.to] {
+        let manifest = github::fetch_nargo_toml(
+            &client,
+            &slug,
+            package.directory.as_deref(),
+            version,
+            token.as_deref(),
+        )
+        .await
+        .with_context(|| format!("Failed to fetch Nargo.toml of {}", version))?;
+        if manifest.is_none() {
+            eprintln!(
+                "Note: {} has no Nargo.toml (or no such tag); comparing against an empty manifest",
+                version
+            );
+        }
+        manifests.push(manifest.unwrap_or_default());
+    }
+    if manifests.iter().all(String::is_empty) {
+        anyhow::bail!(
+            "Neither {} nor {} has a Nargo.toml; check the tag names",
+            args.from,
+            args.to
+        );
+    }
+
+    let package_changes = diff_entries(
+        &package_fields(&manifests[0])?,
+        &package_fields(&manifests[1])?,
+    );
+    let dependency_changes = diff_entries(
+        &dependency_entries(&manifests[0])?,
+        &dependency_entries(&manifests[1])?,
+    );
+    if package_changes.is_empty() && dependency_changes.is_empty() {
+        println!("No differences in [package] or [dependencies].");
+        return Ok(());
+    }
+    print_section("package", &package_changes);
+    print_section("dependencies", &dependency_changes);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "[package]\nname = \"lib\"\ntype = \"lib\"\ncompiler_version = \">=0.36.0\"\n\n\
+        [dependencies]\n\
+        bignum = { git = \"https://github.com/noir-lang/noir-bignum\", tag = \"v0.4.0\" }\n\
+        old_dep = { git = \"https://github.com/owner/old\", tag = \"v1\" }\n\
+        local = { path = \"../local\" }\n";
+
+    const NEW: &str = "[package]\nname = \"lib\"\ntype = \"lib\"\ncompiler_version = \">=1.0.0\"\nlicense = \"MIT\"\n\n\
+        [dependencies]\n\
+        bignum = { git = \"https://github.com/noir-lang/noir-bignum\", tag = \"v0.5.0\" }\n\
+        local = { path = \"../local\" }\n\
+        poseidon = { git = \"https://github.com/noir-lang/poseidon\", tag = \"v0.1.0\" }\n";
+
+    #[test]
+    fn package_fields_are_compared_by_value() {
+        let changes = diff_entries(&package_fields(OLD).unwrap(), &package_fields(NEW).unwrap());
+        assert_eq!(
+            changes,
+            [
+                Change::Changed {
+                    key: "compiler_version".to_string(),
+                    from: "\">=0.36.0\"".to_string(),
+                    to: "\">=1.0.0\"".to_string(),
+                },
+                Change::Added {
+                    key: "license".to_string(),
+                    value: "\"MIT\"".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn dependencies_are_added_removed_or_changed() {
+        let changes = diff_entries(
+            &dependency_entries(OLD).unwrap(),
+            &dependency_entries(NEW).unwrap(),
+        );
+        assert_eq!(
+            changes,
+            [
+                Change::Changed {
+                    key: "bignum".to_string(),
+                    from: "git https://github.com/noir-lang/noir-bignum, tag v0.4.0".to_string(),
+                    to: "git https://github.com/noir-lang/noir-bignum, tag v0.5.0".to_string(),
+                },
+                Change::Removed {
+                    key: "old_dep".to_string(),
+                    value: "git https://github.com/owner/old, tag v1".to_string(),
+                },
+                Change::Added {
+                    key: "poseidon".to_string(),
+                    value: "git https://github.com/noir-lang/poseidon, tag v0.1.0".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_missing_manifest_compares_as_empty() {
+        assert!(package_fields("").unwrap().is_empty());
+        let changes = diff_entries(
+            &dependency_entries("").unwrap(),
+            &dependency_entries(OLD).unwrap(),
+        );
+        assert_eq!(changes.len(), 3);
+        assert!(changes.iter().all(|c| matches!(c, Change::Added { .. })));
+    }
+}
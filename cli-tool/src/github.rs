@@ -0,0 +1,193 @@
+//! GitHub API lookups shared by the CLI binaries.
+
+use reqwest::{Client, RequestBuilder, StatusCode};
+use semver::Version;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// GitHub lookups are a side check, so don't let one hang a command
+const GITHUB_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+pub struct GitHubTag {
+    pub name: String,
+}
+
+/// Extracts the "{owner}/{repo}" slug from a GitHub URL.
+/// Handles both https://github.com/owner/repo and https://github.com/owner/repo/tree/...
+pub fn github_slug_from_url(url: &str) -> Option<String> {
+    let url = url.trim_end_matches('/');
+    let stripped = url.strip_prefix("https://github.com/")?;
+    // Take only the first two path segments (owner/repo)
+    let mut parts = stripped.splitn(3, '/');
+    let owner = parts.next()?;
+    let repo = parts.next()?;
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+    Some(format!("{}/{}", owner, repo))
+}
+
+/// GET request against the GitHub API accepting `accept`, with the token
+/// when there is one
+fn request(client: &Client, path: &str, accept: &str, token: Option<&str>) -> RequestBuilder {
+    let request = client
+        .get(format!("https://api.github.com/{}", path))
+        .header("Accept", accept)
+        .timeout(GITHUB_TIMEOUT);
+    match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+/// GET against the GitHub API, with the token when there is one
+async fn get(
+    client: &Client,
+    path: &str,
+    token: Option<&str>,
+) -> reqwest::Result<reqwest::Response> {
+    request(client, path, "application/vnd.github+json", token)
+        .send()
+        .await
+}
+
+/// Picks the newest tag by semver precedence. Tags that aren't semver
+/// (a leading `v` is allowed) are ignored, as are pre-releases unless
+/// `allow_pre` is set.
+pub fn latest_semver_tag<'a>(
+    tags: impl IntoIterator<Item = &'a str>,
+    allow_pre: bool,
+) -> Option<&'a str> {
+    tags.into_iter()
+        .filter_map(|tag| {
+            let version = Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()?;
+            (allow_pre || version.pre.is_empty()).then_some((version, tag))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, tag)| tag)
+}
+
+/// Fetches the latest release tag from the GitHub API for a given repo URL,
+/// chosen by [`latest_semver_tag`]. Returns None if the repo has no such tag
+/// or the request fails (non-fatal).
+pub async fn fetch_latest_github_tag(
+    client: &Client,
+    github_url: &str,
+    allow_pre: bool,
+) -> Option<String> {
+    let slug = github_slug_from_url(github_url)?;
+    // GitHub lists tags by name, not by version, so fetch a full page to compare
+    let response = get(client, &format!("repos/{}/tags?per_page=100", slug), None)
+        .await
+        .ok()?;
+
+    if !response.status().is_success() {
+        return None;
+    }
+
+    let tags: Vec<GitHubTag> = response.json().await.ok()?;
+    latest_semver_tag(tags.iter().map(|t| t.name.as_str()), allow_pre).map(str::to_string)
+}
+
+/// Whether something on GitHub can still be fetched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reachability {
+    Reachable,
+    /// GitHub answered 404: deleted, renamed away, or private
+    Missing,
+    /// GitHub couldn't be asked (network error, rate limit, ...); carries why
+    Unknown(String),
+}
+
+async fn reachability(client: &Client, path: &str, token: Option<&str>) -> Reachability {
+    match get(client, path, token).await {
+        Ok(response) if response.status().is_success() => Reachability::Reachable,
+        Ok(response) if response.status() == StatusCode::NOT_FOUND => Reachability::Missing,
+        Ok(response) => Reachability::Unknown(format!("GitHub returned {}", response.status())),
+        Err(e) => Reachability::Unknown(e.to_string()),
+    }
+}
+
+/// Checks that the repository "{owner}/{repo}" still exists
+pub async fn check_repo(client: &Client, slug: &str, token: Option<&str>) -> Reachability {
+    reachability(client, &format!("repos/{}", slug), token).await
+}
+
+/// Checks that `tag` still exists in the repository "{owner}/{repo}"
+pub async fn check_tag(
+    client: &Client,
+    slug: &str,
+    tag: &str,
+    token: Option<&str>,
+) -> Reachability {
+    reachability(
+        client,
+        &format!("repos/{}/git/ref/tags/{}", slug, tag),
+        token,
+    )
+    .await
+}
+
+/// Fetches the Nargo.toml of the repository "{owner}/{repo}" at `tag`, from
+/// `directory` for a package inside a monorepo. Ok(None) if that version has
+/// no Nargo.toml there (or the tag doesn't exist).
+pub async fn fetch_nargo_toml(
+    client: &Client,
+    slug: &str,
+    directory: Option<&str>,
+    tag: &str,
+    token: Option<&str>,
+) -> anyhow::Result<Option<String>> {
+    let path = match directory {
+        Some(directory) => format!("{}/Nargo.toml", directory.trim_matches('/')),
+        None => "Nargo.toml".to_string(),
+    };
+    let response = request(
+        client,
+        &format!("repos/{}/contents/{}", slug, path),
+        "application/vnd.github.raw",
+        token,
+    )
+    .query(&[("ref", tag)])
+    .send()
+    .await?;
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(None),
+        status if status.is_success() => Ok(Some(response.text().await?)),
+        status => anyhow::bail!("GitHub returned {} for {} at {}", status, path, tag),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slug_is_taken_from_repo_and_tree_urls() {
+        assert_eq!(
+            github_slug_from_url("https://github.com/noir-lang/noir-bignum").as_deref(),
+            Some("noir-lang/noir-bignum")
+        );
+        assert_eq!(
+            github_slug_from_url("https://github.com/noir-lang/noir-bignum.git/").as_deref(),
+            Some("noir-lang/noir-bignum")
+        );
+        assert_eq!(
+            github_slug_from_url("https://github.com/owner/mono/tree/main/libs/a").as_deref(),
+            Some("owner/mono")
+        );
+        assert_eq!(github_slug_from_url("https://gitlab.com/owner/repo"), None);
+    }
+
+    #[test]
+    fn latest_tag_is_the_highest_stable_semver() {
+        let tags = ["nightly", "v0.9.0", "v0.10.0", "v1.0.0-rc.1", "0.10.1-beta"];
+        assert_eq!(latest_semver_tag(tags, false), Some("v0.10.0"));
+        assert_eq!(latest_semver_tag(tags, true), Some("v1.0.0-rc.1"));
+    }
+
+    #[test]
+    fn no_latest_tag_without_a_matching_semver_tag() {
+        assert_eq!(latest_semver_tag(["nightly", "release-2"], true), None);
+        assert_eq!(latest_semver_tag(["v1.0.0-rc.1"], false), None);
+    }
+}
@@ -0,0 +1,27 @@
+//! Shared HTTP client for the CLI binaries.
+
+use reqwest::Client;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Sent on every request to the registry and GitHub
+pub const USER_AGENT: &str = concat!("nargo-add/", env!("CARGO_PKG_VERSION"));
+
+/// Applies to every request unless it sets its own timeout
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Returns the process-wide HTTP client. Clones share one connection pool, so
+/// repeated calls reuse connections instead of opening new ones. Commands
+/// that take `--timeout` override [`DEFAULT_TIMEOUT`] per request.
+pub fn build_client() -> Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT
+        .get_or_init(|| {
+            Client::builder()
+                .user_agent(USER_AGENT)
+                .timeout(DEFAULT_TIMEOUT)
+                .build()
+                .expect("Failed to create HTTP client")
+        })
+        .clone()
+}
@@ -1,4 +1,11 @@
 pub mod auth;
+pub mod completions;
 pub mod config;
+pub mod github;
+pub mod http;
 pub mod nargo_toml;
+pub mod output;
+pub mod registry_client;
+pub mod response_cache;
+pub mod update_check;
 pub mod utils;
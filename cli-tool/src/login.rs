@@ -1,53 +1,71 @@
 use anyhow::Result;
-use clap::Parser;
-use nargo_add::{auth, config, utils};
+use clap::{CommandFactory, Parser};
+use nargo_add::registry_client::RegistryClient;
+use nargo_add::{auth, completions, config, info, output, utils};
 
 #[derive(Parser)]
 #[command(name = "nargo-login")]
 #[command(about = "Login to the Noir registry (use: nargo login)")]
 #[command(version)]
 struct Args {
-    /// GitHub token for authentication (optional, can use env var GITHUB_TOKEN)
+    /// GitHub token for authentication (optional, falls back to GITHUB_TOKEN, then `gh auth token`)
     #[arg(long)]
     github_token: Option<String>,
 
     /// Registry API URL (optional, defaults to NOIR_REGISTRY_URL env var)
     #[arg(long)]
     registry: Option<String>,
+
+    /// Timeout in seconds for registry requests (defaults to NOIR_REGISTRY_TIMEOUT env var)
+    #[arg(long)]
+    timeout: Option<u64>,
+
+    /// Suppress informational output (warnings and errors are still shown)
+    #[arg(long, short)]
+    quiet: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    completions::handle(Args::command());
     let args = Args::parse();
+    output::set_quiet(args.quiet);
 
-    let registry_url = utils::get_registry_url(args.registry);
+    let (registry_url, _) = utils::get_registry_url(args.registry);
+    let registry = RegistryClient::new(&registry_url, utils::get_registry_timeout(args.timeout));
 
-    // Get GitHub token (from arg or env var)
-    let github_token = args.github_token
-        .or_else(|| std::env::var("GITHUB_TOKEN").ok())
-        .ok_or_else(|| {
-            anyhow::anyhow!(
-                "GitHub token required. Provide --github-token <token> or set GITHUB_TOKEN env var.\n\
+    // Get GitHub token (from arg, env var, or the GitHub CLI)
+    let github_token = auth::resolve_github_token(args.github_token).ok_or_else(|| {
+        anyhow::anyhow!(
+            "GitHub token required. Provide --github-token <token>, set GITHUB_TOKEN env var, \
+                or run 'gh auth login'.\n\
                 Create a token at: https://github.com/settings/tokens (with 'repo' scope)"
-            )
-        })?;
+        )
+    })?;
 
-    eprintln!("Authenticating with GitHub...");
-    let maybe_key = auth::authenticate_github(&registry_url, &github_token).await?;
+    info!("Authenticating with GitHub...");
+    let maybe_key = registry.authenticate_github(&github_token).await?;
 
     match maybe_key {
         Some(api_key) => {
             let mut cfg = config::Config::load()?;
-            cfg.set_api_key(api_key);
+            let storage = cfg.set_api_key(api_key);
             cfg.set_registry_url(registry_url);
             cfg.save()?;
 
-            eprintln!("Account created. Credentials saved.");
-            eprintln!("You can now use 'nargo publish' without authentication.");
+            match storage {
+                config::KeyStorage::Keyring => {
+                    info!("Account created. API key saved to the OS keychain.")
+                }
+                config::KeyStorage::File => info!("Account created. Credentials saved."),
+            }
+            info!("You can now use 'nargo publish' without authentication.");
         }
         None => {
-            eprintln!("You already have an account. Your existing tokens are still active.");
-            eprintln!("Run 'nargo token list' to see them, or 'nargo token create <name>' to make a new one.");
+            info!("You already have an account. Your existing tokens are still active.");
+            info!(
+                "Run 'nargo token list' to see them, or 'nargo token create <name>' to make a new one."
+            );
         }
     }
 
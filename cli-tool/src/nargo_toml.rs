@@ -20,10 +20,39 @@ pub fn find_nargo_toml(start_dir: &Path) -> Result<PathBuf> {
     }
 }
 
+/// Largest Nargo.toml we'll read; real manifests are a few KB at most
+const MAX_MANIFEST_SIZE: u64 = 1024 * 1024;
+
+/// Reads a manifest into a string, refusing oversized or non-UTF-8 files with a
+/// clear error instead of an opaque IO/parse failure. Catches `--manifest-path`
+/// pointed at the wrong file early.
+pub fn read_manifest(manifest_path: &Path) -> Result<String> {
+    let size = fs::metadata(manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?
+        .len();
+    if size > MAX_MANIFEST_SIZE {
+        anyhow::bail!(
+            "{} is {} bytes, which is too large for a Nargo.toml (limit is {} bytes). \
+             Check that --manifest-path points at the right file.",
+            manifest_path.display(),
+            size,
+            MAX_MANIFEST_SIZE
+        );
+    }
+
+    let bytes = fs::read(manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    String::from_utf8(bytes).map_err(|_| {
+        anyhow::anyhow!(
+            "{} is not a UTF-8 text file. Check that --manifest-path points at a Nargo.toml.",
+            manifest_path.display()
+        )
+    })
+}
+
 /// Reads package name from Nargo.toml
 pub fn read_package_name(manifest_path: &Path) -> Result<String> {
-    let content = fs::read_to_string(manifest_path)
-        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    let content = read_manifest(manifest_path)?;
 
     let doc = content
         .parse::<DocumentMut>()
@@ -44,8 +73,7 @@ pub fn read_package_name(manifest_path: &Path) -> Result<String> {
 
 /// Validates that the Nargo.toml file is valid TOML
 pub fn validate_nargo_toml(manifest_path: &Path) -> Result<()> {
-    let content = fs::read_to_string(manifest_path)
-        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    let content = read_manifest(manifest_path)?;
 
     content
         .parse::<DocumentMut>()
@@ -57,8 +85,7 @@ pub fn validate_nargo_toml(manifest_path: &Path) -> Result<()> {
 /// Removes a dependency from Nargo.toml (used for rollback).
 /// Returns Ok(true) if removed, Ok(false) if the dependency was not present.
 pub fn remove_dependency(manifest_path: &Path, package_name: &str) -> Result<bool> {
-    let content = fs::read_to_string(manifest_path)
-        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    let content = read_manifest(manifest_path)?;
 
     let mut doc = content
         .parse::<DocumentMut>()
@@ -80,3 +107,222 @@ pub fn remove_dependency(manifest_path: &Path, package_name: &str) -> Result<boo
 
     Ok(true)
 }
+
+/// One `[dependencies]` entry of a Nargo.toml
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestDependency {
+    /// Key the dependency is listed under
+    pub name: String,
+    pub git: Option<String>,
+    pub tag: Option<String>,
+    pub rev: Option<String>,
+    pub path: Option<String>,
+}
+
+/// Reads the `[dependencies]` of a Nargo.toml, in the order they're listed.
+/// A manifest without the section has none.
+pub fn read_dependencies(manifest_path: &Path) -> Result<Vec<ManifestDependency>> {
+    parse_dependencies(&read_manifest(manifest_path)?)
+}
+
+/// The `[dependencies]` of Nargo.toml content, like [`read_dependencies`]
+pub fn parse_dependencies(content: &str) -> Result<Vec<ManifestDependency>> {
+    let doc = content
+        .parse::<DocumentMut>()
+        .context("Failed to parse Nargo.toml")?;
+
+    let Some(deps) = doc.get("dependencies").and_then(|d| d.as_table_like()) else {
+        return Ok(Vec::new());
+    };
+
+    let field = |spec: &toml_edit::Item, key: &str| {
+        spec.get(key).and_then(|v| v.as_str()).map(str::to_string)
+    };
+    Ok(deps
+        .iter()
+        .map(|(name, spec)| ManifestDependency {
+            name: name.to_string(),
+            git: field(spec, "git"),
+            tag: field(spec, "tag"),
+            rev: field(spec, "rev"),
+            path: field(spec, "path"),
+        })
+        .collect())
+}
+
+/// Sets the `tag` of a dependency, keeping the rest of its entry and the
+/// file's formatting. Returns Ok(false) if the dependency is not present.
+pub fn set_dependency_tag(manifest_path: &Path, name: &str, tag: &str) -> Result<bool> {
+    let content = read_manifest(manifest_path)?;
+
+    let mut doc = content
+        .parse::<DocumentMut>()
+        .context("Failed to parse Nargo.toml")?;
+
+    let Some(spec) = doc
+        .get_mut("dependencies")
+        .and_then(|d| d.as_table_like_mut())
+        .and_then(|deps| deps.get_mut(name))
+    else {
+        return Ok(false);
+    };
+
+    if let Some(existing) = spec.get_mut("tag").and_then(|t| t.as_value_mut()) {
+        let decor = existing.decor().clone();
+        *existing = tag.into();
+        *existing.decor_mut() = decor;
+    } else if let Some(inline) = spec.as_inline_table_mut() {
+        // In `{ git = "..." }` the space before `}` belongs to the last
+        // value; keep it at the end of the entry
+        let trailing = inline.iter_mut().last().and_then(|(_, last)| {
+            let suffix = last.decor().suffix().cloned();
+            last.decor_mut().set_suffix("");
+            suffix
+        });
+        inline.insert("tag", tag.into());
+        if let (Some(trailing), Some(new)) = (trailing, inline.get_mut("tag")) {
+            new.decor_mut().set_suffix(trailing);
+        }
+    } else if let Some(table) = spec.as_table_like_mut() {
+        table.insert("tag", toml_edit::value(tag));
+    } else {
+        return Ok(false);
+    }
+
+    fs::write(manifest_path, doc.to_string())
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+
+    Ok(true)
+}
+
+/// Project file that can name the registry instead of Nargo.toml
+pub const PROJECT_REGISTRY_FILE: &str = ".noir-registry.toml";
+
+/// Registry URL a project pins for everyone working on it: `url` in the
+/// `[registry]` table of Nargo.toml, or else `url` in a `.noir-registry.toml`
+/// next to it. None when neither sets one.
+pub fn read_project_registry(manifest_path: &Path) -> Result<Option<String>> {
+    let content = read_manifest(manifest_path)?;
+    let doc = content
+        .parse::<DocumentMut>()
+        .context("Failed to parse Nargo.toml")?;
+    if let Some(url) = doc
+        .get("registry")
+        .and_then(|r| r.get("url"))
+        .and_then(|u| u.as_str())
+    {
+        return Ok(Some(url.to_string()));
+    }
+
+    let sibling = manifest_path.with_file_name(PROJECT_REGISTRY_FILE);
+    if !sibling.exists() {
+        return Ok(None);
+    }
+    let doc = fs::read_to_string(&sibling)
+        .with_context(|| format!("Failed to read {}", sibling.display()))?
+        .parse::<DocumentMut>()
+        .with_context(|| format!("Failed to parse {}", sibling.display()))?;
+    Ok(doc.get("url").and_then(|u| u.as_str()).map(str::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn dependencies_are_read_in_order() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "[package]\nname = \"app\"\n\n[dependencies]\n\
+             bignum = {{ git = \"https://github.com/noir-lang/noir-bignum\", tag = \"v0.5.0\" }}\n\
+             local = {{ path = \"../local\" }}\n"
+        )
+        .unwrap();
+
+        let deps = read_dependencies(file.path()).unwrap();
+        assert_eq!(
+            deps,
+            [
+                ManifestDependency {
+                    name: "bignum".to_string(),
+                    git: Some("https://github.com/noir-lang/noir-bignum".to_string()),
+                    tag: Some("v0.5.0".to_string()),
+                    rev: None,
+                    path: None,
+                },
+                ManifestDependency {
+                    name: "local".to_string(),
+                    git: None,
+                    tag: None,
+                    rev: None,
+                    path: Some("../local".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn dependency_tag_is_replaced_in_place() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "[package]\nname = \"app\"\n\n[dependencies]\n\
+             # pinned by nargo add\n\
+             bignum = {{ git = \"https://github.com/noir-lang/noir-bignum\", tag = \"v0.5.0\" }}\n\
+             untagged = {{ git = \"https://github.com/owner/untagged\" }}\n\n\
+             [dependencies.table]\ngit = \"https://github.com/owner/table\"\ntag = \"v1\" # old\n"
+        )
+        .unwrap();
+
+        assert!(set_dependency_tag(file.path(), "bignum", "v0.6.0").unwrap());
+        assert!(set_dependency_tag(file.path(), "untagged", "v2.0.0").unwrap());
+        assert!(set_dependency_tag(file.path(), "table", "v1.1").unwrap());
+        assert!(!set_dependency_tag(file.path(), "missing", "v1").unwrap());
+
+        assert_eq!(
+            fs::read_to_string(file.path()).unwrap(),
+            "[package]\nname = \"app\"\n\n[dependencies]\n\
+             # pinned by nargo add\n\
+             bignum = { git = \"https://github.com/noir-lang/noir-bignum\", tag = \"v0.6.0\" }\n\
+             untagged = { git = \"https://github.com/owner/untagged\", tag = \"v2.0.0\" }\n\n\
+             [dependencies.table]\ngit = \"https://github.com/owner/table\"\ntag = \"v1.1\" # old\n"
+        );
+    }
+
+    #[test]
+    fn manifest_without_dependencies_has_none() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "[package]\nname = \"app\"\n").unwrap();
+        assert!(read_dependencies(file.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn project_registry_comes_from_nargo_toml_before_the_sibling_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Nargo.toml");
+        fs::write(&manifest, "[package]\nname = \"app\"\n").unwrap();
+        assert_eq!(read_project_registry(&manifest).unwrap(), None);
+
+        fs::write(
+            dir.path().join(PROJECT_REGISTRY_FILE),
+            "url = \"https://sibling.example.com/api\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            read_project_registry(&manifest).unwrap().as_deref(),
+            Some("https://sibling.example.com/api")
+        );
+
+        fs::write(
+            &manifest,
+            "[package]\nname = \"app\"\n\n[registry]\nurl = \"https://manifest.example.com/api\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            read_project_registry(&manifest).unwrap().as_deref(),
+            Some("https://manifest.example.com/api")
+        );
+    }
+}
@@ -1,20 +1,85 @@
+use clap_complete::Shell;
+use nargo_add::{completions, update_check};
 use std::env;
+use std::io::IsTerminal;
 use std::path::PathBuf;
 use std::process::Command;
 
+/// Subcommands handled by our own binaries: (subcommand, binary, summary).
+/// Everything else is passed through to the real nargo.
+const COMMANDS: &[(&str, &str, &str)] = &[
+    (
+        "add",
+        "nargo-add",
+        "Add a package from the registry to Nargo.toml",
+    ),
+    (
+        "remove",
+        "nargo-remove",
+        "Remove a dependency from Nargo.toml",
+    ),
+    (
+        "prune",
+        "nargo-prune",
+        "Remove dependencies whose GitHub repo or tag is gone",
+    ),
+    (
+        "update",
+        "nargo-update",
+        "Bump registry dependencies to their latest tag",
+    ),
+    (
+        "diff",
+        "nargo-diff",
+        "Compare the Nargo.toml of two versions of a package",
+    ),
+    (
+        "publish",
+        "nargo-publish",
+        "Publish this package to the registry",
+    ),
+    ("login", "nargo-login", "Log in to the registry with GitHub"),
+    ("token", "nargo-token", "Manage registry API tokens"),
+    (
+        "config",
+        "nargo-config",
+        "Show or edit the CLI configuration",
+    ),
+    ("search", "nargo-search", "Search the registry"),
+    (
+        "open",
+        "nargo-open",
+        "Open a package's repository in the browser",
+    ),
+    ("registry", "nargo-registry", "Back up or seed a registry"),
+];
+
+/// `nargo` as seen by `nargo completions <shell>`: just the registry
+/// subcommands, since the real nargo's own commands aren't known here
+fn registry_commands() -> clap::Command {
+    COMMANDS.iter().fold(
+        clap::Command::new("nargo").about("Noir's package manager, with registry commands"),
+        |cmd, (name, _, about)| cmd.subcommand(clap::Command::new(*name).about(*about)),
+    )
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     // Handle commands that we delegate to our binaries
     if args.len() > 1 {
         let command = &args[1];
-        let binary_name = match command.as_str() {
-            "add" => "nargo-add",
-            "remove" => "nargo-remove",
-            "publish" => "nargo-publish",
-            "login" => "nargo-login",
-            "token" => "nargo-token",
-            _ => {
+        if command == "completions"
+            && let Some(shell) = args.get(2).and_then(|s| s.parse::<Shell>().ok())
+            && args.len() == 3
+        {
+            completions::print_script(registry_commands(), shell, Some("nargo-add"));
+            return;
+        }
+
+        let binary_name = match COMMANDS.iter().find(|(name, _, _)| name == command) {
+            Some((_, binary, _)) => *binary,
+            None => {
                 // Not one of our commands, pass through to real nargo
                 let real_nargo = find_real_nargo().unwrap_or_else(|| {
                     eprintln!("Error: Could not find nargo binary in PATH");
@@ -48,6 +113,13 @@ fn main() {
             std::process::exit(1);
         });
 
+        // Only when someone is watching: scripts and CI shouldn't see the notice
+        if std::io::stderr().is_terminal()
+            && let Ok(runtime) = tokio::runtime::Runtime::new()
+        {
+            runtime.block_on(update_check::notify_if_outdated());
+        }
+
         let mut cmd = Command::new(&binary_path);
         if args.len() > 2 {
             cmd.args(&args[2..]);
@@ -153,11 +225,7 @@ fn find_real_nargo() -> Option<String> {
         format!("{}/.cargo/bin/nargo", home),
     ];
 
-    for path in common_paths {
-        if std::path::Path::new(&path).exists() {
-            return Some(path);
-        }
-    }
-
-    None
+    common_paths
+        .into_iter()
+        .find(|path| std::path::Path::new(path).exists())
 }
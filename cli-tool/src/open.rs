@@ -0,0 +1,112 @@
+use anyhow::Result;
+use clap::{CommandFactory, Parser};
+use nargo_add::registry_client::RegistryClient;
+use nargo_add::{completions, info, output, utils};
+
+#[derive(Parser)]
+#[command(name = "nargo-open")]
+#[command(about = "Open a package's repository in the browser (use: nargo open <package>)")]
+#[command(version)]
+struct Args {
+    /// Package name (e.g., rocq-of-noir)
+    package_name: String,
+
+    /// Registry API URL (optional, defaults to NOIR_REGISTRY_URL env var)
+    #[arg(long)]
+    registry: Option<String>,
+
+    /// Open the package's homepage instead of its repository, if it has one
+    #[arg(long)]
+    homepage: bool,
+
+    /// Print the URL instead of opening a browser
+    #[arg(long)]
+    print: bool,
+
+    /// Timeout in seconds for registry requests (defaults to NOIR_REGISTRY_TIMEOUT env var)
+    #[arg(long)]
+    timeout: Option<u64>,
+
+    /// Ignore cached registry responses and refresh them (see NOIR_REGISTRY_CACHE_TTL)
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Suppress informational output (warnings and errors are still shown)
+    #[arg(long, short)]
+    quiet: bool,
+}
+
+/// Hands the URL to the platform's opener. Returns false when there is no
+/// browser to open it in (e.g. a headless machine), the opener failed, or the
+/// URL isn't http(s). The URL comes from the registry, so it is never put on
+/// a shell command line: `webbrowser` passes it to the opener as one argument.
+fn open_in_browser(url: &str) -> bool {
+    if !is_web_url(url) {
+        return false;
+    }
+    if cfg!(all(unix, not(target_os = "macos")))
+        && std::env::var_os("DISPLAY").is_none()
+        && std::env::var_os("WAYLAND_DISPLAY").is_none()
+    {
+        return false;
+    }
+    webbrowser::open(url).is_ok()
+}
+
+fn is_web_url(url: &str) -> bool {
+    url::Url::parse(url).is_ok_and(|u| matches!(u.scheme(), "http" | "https"))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    completions::handle_with_package_names(Args::command()).await;
+    let args = Args::parse();
+    output::set_quiet(args.quiet);
+    let (registry_url, _) = utils::get_registry_url(args.registry.clone());
+
+    let registry = RegistryClient::new(&registry_url, utils::get_registry_timeout(args.timeout))
+        .no_cache(args.no_cache);
+    let package = registry.get_package(&args.package_name).await?;
+    let url = match package.homepage {
+        Some(homepage) if args.homepage => homepage,
+        _ => {
+            if args.homepage {
+                info!(
+                    "'{}' has no homepage, using its repository",
+                    args.package_name
+                );
+            }
+            package.github_repository_url
+        }
+    };
+
+    if args.print {
+        println!("{}", url);
+    } else if open_in_browser(&url) {
+        info!("Opened {}", url);
+    } else {
+        info!("Couldn't open a browser, here is the URL:");
+        println!("{}", url);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_http_urls_are_opened() {
+        assert!(is_web_url("https://github.com/noir-lang/noir-bignum"));
+        assert!(is_web_url("http://example.com/?a=1&b=2"));
+        for url in [
+            "file:///etc/passwd",
+            "javascript:alert(1)",
+            "calc.exe",
+            "-h",
+            "https://example.com\" & calc.exe",
+        ] {
+            assert!(!is_web_url(url), "{:?}", url);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Shared console output for the CLI binaries.
+//!
+//! Informational progress goes through [`info!`](crate::info) so that `--quiet`
+//! can silence it in one place. Warnings and errors keep using `eprintln!`
+//! directly and are always shown.
+//!
+//! With `--json`, failures are reported as a single JSON object on stderr
+//! (see [`print_json_error`]) so scripts can tell error kinds apart.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Suppress informational output for the rest of the process
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Whether informational output is currently suppressed
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Category of a failure in `--json` error output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The registry has no such package
+    NotFound,
+    /// The registry couldn't be reached
+    Network,
+    /// The registry answered with an error status
+    Server,
+    /// The registry's response couldn't be understood
+    Parse,
+}
+
+/// Writes `{ "error": ..., "kind": ..., "package": ..., "suggestions": [...] }`
+/// as one line to stderr. `suggestions` is empty unless the registry offered
+/// similarly named packages.
+pub fn print_json_error(error: &str, kind: ErrorKind, package: &str, suggestions: &[String]) {
+    eprintln!(
+        "{}",
+        serde_json::json!({
+            "error": error,
+            "kind": kind,
+            "package": package,
+            "suggestions": suggestions,
+        })
+    );
+}
+
+/// Prints an informational line to stderr unless `--quiet` is active
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
@@ -0,0 +1,147 @@
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser};
+use nargo_add::github::{self, Reachability};
+use nargo_add::{auth, completions, http, info, nargo_toml, output};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "nargo-prune")]
+#[command(
+    about = "Find dependencies whose GitHub repo or tag is gone, and remove them with --fix (use: nargo prune)"
+)]
+#[command(version)]
+struct Args {
+    /// Path to Nargo.toml (optional, will search from current directory)
+    #[arg(long)]
+    manifest_path: Option<PathBuf>,
+
+    /// Remove the dead dependencies from Nargo.toml instead of only reporting them
+    #[arg(long)]
+    fix: bool,
+
+    /// GitHub token for the checks (defaults to GITHUB_TOKEN, then `gh auth token`);
+    /// without one GitHub allows 60 lookups an hour
+    #[arg(long)]
+    github_token: Option<String>,
+
+    /// Suppress informational output (warnings and errors are still shown)
+    #[arg(long, short)]
+    quiet: bool,
+}
+
+/// Why a dependency can no longer be fetched
+enum Dead {
+    Repo,
+    Tag(String),
+}
+
+/// Checks one git dependency. None when it's fine or couldn't be checked.
+async fn check_dependency(
+    client: &reqwest::Client,
+    dep: &nargo_toml::ManifestDependency,
+    token: Option<&str>,
+) -> Option<Dead> {
+    let git = dep.git.as_deref()?;
+    let Some(slug) = github::github_slug_from_url(git) else {
+        info!("   {} - not on GitHub, skipped", dep.name);
+        return None;
+    };
+
+    match github::check_repo(client, &slug, token).await {
+        Reachability::Reachable => {}
+        Reachability::Missing => return Some(Dead::Repo),
+        Reachability::Unknown(why) => {
+            eprintln!("Warning: could not check {} ({}): {}", dep.name, slug, why);
+            return None;
+        }
+    }
+
+    let Some(tag) = dep.tag.as_deref() else {
+        info!("   {} - ok ({}, no tag)", dep.name, slug);
+        return None;
+    };
+    match github::check_tag(client, &slug, tag, token).await {
+        Reachability::Reachable => {
+            info!("   {} - ok ({} {})", dep.name, slug, tag);
+            None
+        }
+        Reachability::Missing => Some(Dead::Tag(tag.to_string())),
+        Reachability::Unknown(why) => {
+            eprintln!(
+                "Warning: could not check tag {} of {} ({}): {}",
+                tag, dep.name, slug, why
+            );
+            None
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    completions::handle(Args::command());
+    let args = Args::parse();
+    output::set_quiet(args.quiet);
+
+    let manifest_path = match args.manifest_path {
+        Some(path) => path,
+        None => {
+            let current_dir = std::env::current_dir().context("Failed to get current directory")?;
+            nargo_toml::find_nargo_toml(&current_dir)?
+        }
+    };
+
+    let dependencies = nargo_toml::read_dependencies(&manifest_path)?;
+    let git_deps: Vec<_> = dependencies.iter().filter(|d| d.git.is_some()).collect();
+    if git_deps.is_empty() {
+        info!("No git dependencies in {}", manifest_path.display());
+        return Ok(());
+    }
+
+    let token = auth::resolve_github_token(args.github_token);
+    let client = http::build_client();
+    info!(
+        "Checking {} git dependencies in {}...",
+        git_deps.len(),
+        manifest_path.display()
+    );
+
+    let mut dead = Vec::new();
+    for dep in git_deps {
+        match check_dependency(&client, dep, token.as_deref()).await {
+            Some(Dead::Repo) => {
+                eprintln!(
+                    "   {} - repository not found: {} (deleted, renamed or private)",
+                    dep.name,
+                    dep.git.as_deref().unwrap_or_default()
+                );
+                dead.push(dep.name.as_str());
+            }
+            Some(Dead::Tag(tag)) => {
+                eprintln!("   {} - tag {} no longer exists", dep.name, tag);
+                dead.push(dep.name.as_str());
+            }
+            None => {}
+        }
+    }
+
+    if dead.is_empty() {
+        info!("All reachable dependencies are fine.");
+        return Ok(());
+    }
+
+    if !args.fix {
+        eprintln!(
+            "{} dead dependencies: {}. Run 'nargo prune --fix' to remove them.",
+            dead.len(),
+            dead.join(", ")
+        );
+        std::process::exit(1);
+    }
+
+    for name in &dead {
+        if nargo_toml::remove_dependency(&manifest_path, name)? {
+            info!("Removed '{}' from {}", name, manifest_path.display());
+        }
+    }
+    Ok(())
+}
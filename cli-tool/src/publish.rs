@@ -1,8 +1,8 @@
 use anyhow::{Context, Result};
-use clap::Parser;
-use nargo_add::{auth, config, nargo_toml, utils};
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use clap::{CommandFactory, Parser};
+use nargo_add::output::ErrorKind;
+use nargo_add::registry_client::{PublishRequest, RegistryClient};
+use nargo_add::{auth, completions, config, info, nargo_toml, output, utils};
 use std::path::PathBuf;
 #[derive(Parser)]
 #[command(name = "nargo-publish")]
@@ -13,10 +13,25 @@ struct Args {
     registry: Option<String>,
     #[arg(long)]
     repo: Option<String>,
+    /// Git remote to read the repository URL from (defaults to origin, then the first remote)
+    #[arg(long)]
+    remote: Option<String>,
+    /// Publish even if the git working tree has uncommitted changes
+    #[arg(long)]
+    allow_dirty: bool,
+    /// Check the registry is up before doing anything else, and stop early if not
+    #[arg(long)]
+    check_registry: bool,
+    /// Confirm publishing a repository that is a fork of another one
+    #[arg(long)]
+    allow_fork: bool,
     #[arg(long)]
     description: Option<String>,
     #[arg(long)]
     package_version: Option<String>,
+    /// Publish a specific commit (SHA) instead of a tag, for untagged packages
+    #[arg(long, conflicts_with = "package_version")]
+    rev: Option<String>,
     #[arg(long)]
     license: Option<String>,
     #[arg(long)]
@@ -28,44 +43,160 @@ struct Args {
     /// Comma-separated keywords (e.g. --keywords crypto,hash,math)
     #[arg(long, value_delimiter = ',')]
     keywords: Option<Vec<String>>,
+    /// Timeout in seconds for registry requests (defaults to NOIR_REGISTRY_TIMEOUT env var)
+    #[arg(long)]
+    timeout: Option<u64>,
+    /// Ignore cached registry responses and refresh them (see NOIR_REGISTRY_CACHE_TTL)
+    #[arg(long)]
+    no_cache: bool,
+    /// Suppress informational output (warnings and errors are still shown)
+    #[arg(long, short)]
+    quiet: bool,
 }
 
-#[derive(Deserialize)]
-struct PublishResponse {
-    success: bool,
-    message: String,
-    #[allow(dead_code)]
-    package_id: Option<i32>,
+/// Whether the registry has a package named `name` published from `git_url`.
+/// None if the registry couldn't be asked.
+async fn is_in_registry(registry: &RegistryClient, name: &str, git_url: &str) -> Option<bool> {
+    match registry.get_package(name).await {
+        Ok(package) => Some(
+            utils::normalize_git_url(&package.github_repository_url)
+                == utils::normalize_git_url(git_url),
+        ),
+        Err(e) if e.kind == ErrorKind::NotFound => Some(false),
+        Err(_) => None,
+    }
 }
 
-#[derive(Serialize)]
-struct PublishRequest {
-    name: String,
-    description: Option<String>,
-    github_repository_url: String,
-    version: Option<String>,
-    license: Option<String>,
-    homepage: Option<String>,
-    keywords: Option<Vec<String>>,
+/// Lists the manifest's dependencies and warns about the ones consumers can't
+/// get from the registry: git dependencies that aren't published there, and
+/// local path dependencies. Informational only; nothing here stops the publish.
+async fn preview_dependencies(
+    manifest_path: &std::path::Path,
+    registry: &RegistryClient,
+) -> Result<()> {
+    let dependencies = nargo_toml::read_dependencies(manifest_path)?;
+    if dependencies.is_empty() {
+        info!("Dependencies: none");
+        return Ok(());
+    }
+
+    let mut unpublished = Vec::new();
+    let mut local = Vec::new();
+    info!("Dependencies:");
+    for dep in &dependencies {
+        match (&dep.git, &dep.path) {
+            (Some(git), _) => {
+                let status = match is_in_registry(registry, &dep.name, git).await {
+                    Some(true) => "in registry",
+                    Some(false) => {
+                        unpublished.push(dep.name.as_str());
+                        "not in registry"
+                    }
+                    None => "registry check failed",
+                };
+                info!(
+                    "   {} ({} {}) - {}",
+                    dep.name,
+                    git,
+                    dep.tag.as_deref().unwrap_or("(no tag)"),
+                    status
+                );
+            }
+            (None, Some(path)) => {
+                local.push(dep.name.as_str());
+                info!("   {} (path {}) - local", dep.name, path);
+            }
+            (None, None) => info!("   {}", dep.name),
+        }
+    }
+
+    if !unpublished.is_empty() {
+        eprintln!(
+            "Warning: {} not published to the registry: {}.",
+            if unpublished.len() == 1 {
+                "this git dependency is"
+            } else {
+                "these git dependencies are"
+            },
+            unpublished.join(", ")
+        );
+        eprintln!(
+            "   Consumers will fetch them straight from git, which may be untagged or move; \
+             consider publishing them too."
+        );
+    }
+    if !local.is_empty() {
+        eprintln!(
+            "Warning: path dependencies won't resolve for consumers: {}.",
+            local.join(", ")
+        );
+    }
+    Ok(())
 }
 
-/// Gets GitHub repository URL from git remote
-fn get_git_remote_url() -> Result<String> {
+/// Runs a git command in the current directory and returns its trimmed stdout,
+/// or `None` if git exited unsuccessfully
+fn run_git(args: &[&str]) -> Result<Option<String>> {
     use std::process::Command;
 
     let output = Command::new("git")
-        .args(&["remote", "get-url", "origin"])
+        .args(args)
         .output()
         .context("Failed to run git command. Make sure git is installed.")?;
 
     if !output.status.success() {
-        anyhow::bail!("Failed to get git remote URL. Is this a git repository?");
+        return Ok(None);
     }
 
-    let url = String::from_utf8(output.stdout)
-        .context("Invalid UTF-8 in git remote URL")?
-        .trim()
-        .to_string();
+    let stdout = String::from_utf8(output.stdout).context("Invalid UTF-8 in git output")?;
+    Ok(Some(stdout.trim().to_string()))
+}
+
+/// Gets GitHub repository URL from git remote.
+///
+/// Uses `remote` if given, otherwise `origin`, otherwise the first configured remote.
+fn get_git_remote_url(remote: Option<&str>) -> Result<String> {
+    match run_git(&["rev-parse", "--is-bare-repository"])?.as_deref() {
+        None => anyhow::bail!("Not a git repository."),
+        Some("true") => anyhow::bail!(
+            "This is a bare git repository. Run publish from a working checkout of the package."
+        ),
+        Some(_) => {}
+    }
+
+    // A detached HEAD still has remotes, but the commit being published may not be on a branch
+    if run_git(&["symbolic-ref", "-q", "HEAD"])?.is_none() {
+        eprintln!("Warning: HEAD is detached. Make sure the commit you're publishing is pushed.");
+    }
+
+    let url = if let Some(remote) = remote {
+        run_git(&["remote", "get-url", remote])?.ok_or_else(|| {
+            let available = run_git(&["remote"]).ok().flatten().unwrap_or_default();
+            let available: Vec<&str> = available.lines().collect();
+            if available.is_empty() {
+                anyhow::anyhow!(
+                    "Remote '{}' not found. This repository has no remotes.",
+                    remote
+                )
+            } else {
+                anyhow::anyhow!(
+                    "Remote '{}' not found. Available remotes: {}",
+                    remote,
+                    available.join(", ")
+                )
+            }
+        })?
+    } else if let Some(url) = run_git(&["remote", "get-url", "origin"])? {
+        url
+    } else {
+        let remotes = run_git(&["remote"])?.unwrap_or_default();
+        let first = remotes.lines().next().ok_or_else(|| {
+            anyhow::anyhow!("This repository has no git remotes. Add one with 'git remote add'.")
+        })?;
+        info!("No 'origin' remote, using '{}'", first);
+        run_git(&["remote", "get-url", first])?
+            .ok_or_else(|| anyhow::anyhow!("Failed to get URL of remote '{}'", first))?
+    };
 
     // Convert SSH URL to HTTPS URL if needed
     let url = if url.starts_with("git@github.com:") {
@@ -81,50 +212,47 @@ fn get_git_remote_url() -> Result<String> {
     Ok(url)
 }
 
-/// Publishes a package to the registry
-async fn publish_package(
-    registry_url: &str,
-    api_key: &str,
-    request: &PublishRequest,
-) -> Result<()> {
-    let client = Client::new();
-    let publish_url = format!("{}/packages/publish", registry_url.trim_end_matches('/'));
-
-    let response = client
-        .post(&publish_url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .json(request)
-        .send()
-        .await
-        .context("Failed to connect to registry")?;
-
-    let status = response.status();
-    let publish_response: PublishResponse = response
-        .json()
-        .await
-        .context("Failed to parse publish response")?;
-
-    if !publish_response.success {
-        anyhow::bail!("Publish failed: {}", publish_response.message);
+/// Fails if the git working tree has uncommitted changes, since the published
+/// tag wouldn't match what's on disk. Outside a git repository there's nothing to check.
+fn ensure_clean_worktree() -> Result<()> {
+    let Some(status) = run_git(&["status", "--porcelain"])? else {
+        return Ok(());
+    };
+    if status.is_empty() {
+        return Ok(());
     }
 
-    if !status.is_success() {
-        anyhow::bail!(
-            "Publish failed with status {}: {}",
-            status,
-            publish_response.message
-        );
+    let files: Vec<&str> = status.lines().collect();
+    let mut listing = files
+        .iter()
+        .take(10)
+        .map(|line| format!("  {}", line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if files.len() > 10 {
+        listing.push_str(&format!("\n  ... and {} more", files.len() - 10));
     }
-
-    Ok(())
+    anyhow::bail!(
+        "{} file(s) in the working tree have uncommitted changes:\n{}\n\
+         Commit or stash them, or pass --allow-dirty to publish anyway.",
+        files.len(),
+        listing
+    );
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    completions::handle(Args::command());
     let args = Args::parse();
+    output::set_quiet(args.quiet);
 
     // Get registry URL
-    let registry_url = utils::get_registry_url(args.registry);
+    let (registry_url, _) = utils::get_registry_url(args.registry);
+    let registry = RegistryClient::new(&registry_url, utils::get_registry_timeout(args.timeout))
+        .no_cache(args.no_cache);
+    if args.check_registry {
+        registry.check_health().await?;
+    }
 
     // Find Nargo.toml
     let current_dir = std::env::current_dir().context("Failed to get current directory")?;
@@ -138,22 +266,28 @@ async fn main() -> Result<()> {
         None => nargo_toml::find_nargo_toml(&current_dir)?,
     };
 
-    eprintln!(
+    info!(
         "Reading package information from {}",
         manifest_path.display()
     );
 
     // Read package name
     let package_name = nargo_toml::read_package_name(&manifest_path)?;
-    eprintln!("Package name: {}", package_name);
+    info!("Package name: {}", package_name);
+
+    if !args.allow_dirty {
+        ensure_clean_worktree()?;
+    }
+
+    preview_dependencies(&manifest_path, &registry).await?;
 
     // Get GitHub repository URL
     let github_repo_url = if let Some(repo) = args.repo {
         repo
     } else {
-        match get_git_remote_url() {
+        match get_git_remote_url(args.remote.as_deref()) {
             Ok(url) => {
-                eprintln!("Detected repository: {}", url);
+                info!("Detected repository: {}", url);
                 url
             }
             Err(e) => {
@@ -167,21 +301,19 @@ async fn main() -> Result<()> {
     // Get API key (from config, or authenticate with GitHub token)
     let api_key = if let Ok(cfg) = config::Config::load() {
         if let Some(stored_api_key) = cfg.get_api_key() {
-            eprintln!("Using stored credentials");
+            info!("Using stored credentials");
             stored_api_key.to_string()
         } else {
             // No stored credentials, need to authenticate
-            let github_token = args.github_token
-                .or_else(|| std::env::var("GITHUB_TOKEN").ok())
-                .ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "Not logged in. Run 'nargo login' first, or provide --github-token <token>.\n\
+            let github_token = auth::resolve_github_token(args.github_token).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Not logged in. Run 'nargo login' first, or provide --github-token <token>.\n\
                         Create a token at: https://github.com/settings/tokens (with 'repo' scope)"
-                    )
-                })?;
+                )
+            })?;
 
-            eprintln!("Authenticating with GitHub...");
-            match auth::authenticate_github(&registry_url, &github_token).await? {
+            info!("Authenticating with GitHub...");
+            match registry.authenticate_github(&github_token).await? {
                 Some(key) => key,
                 None => anyhow::bail!(
                     "Your account already exists but no raw token was returned. \
@@ -192,18 +324,15 @@ async fn main() -> Result<()> {
         }
     } else {
         // Config file error, fall back to token auth
-        let github_token = args
-            .github_token
-            .or_else(|| std::env::var("GITHUB_TOKEN").ok())
-            .ok_or_else(|| {
-                anyhow::anyhow!(
-                    "Not logged in. Run 'nargo login' first, or provide --github-token <token>.\n\
+        let github_token = auth::resolve_github_token(args.github_token).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Not logged in. Run 'nargo login' first, or provide --github-token <token>.\n\
                     Create a token at: https://github.com/settings/tokens (with 'repo' scope)"
-                )
-            })?;
+            )
+        })?;
 
-        eprintln!("Authenticating with GitHub...");
-        match auth::authenticate_github(&registry_url, &github_token).await? {
+        info!("Authenticating with GitHub...");
+        match registry.authenticate_github(&github_token).await? {
             Some(key) => key,
             None => anyhow::bail!(
                 "Your account already exists but no raw token was returned. \
@@ -219,20 +348,22 @@ async fn main() -> Result<()> {
         description: args.description,
         github_repository_url: github_repo_url.clone(),
         version: args.package_version,
+        rev: args.rev,
         license: args.license,
         homepage: args.homepage,
         keywords: args.keywords,
+        allow_fork: args.allow_fork,
     };
 
-    eprintln!("Publishing package to registry...");
-    eprintln!("   Registry: {}", registry_url);
-    eprintln!("   Package: {}", publish_request.name);
-    eprintln!("   Repository: {}", publish_request.github_repository_url);
+    info!("Publishing package to registry...");
+    info!("   Registry: {}", registry_url);
+    info!("   Package: {}", publish_request.name);
+    info!("   Repository: {}", publish_request.github_repository_url);
 
-    match publish_package(&registry_url, &api_key, &publish_request).await {
+    match registry.publish(&api_key, &publish_request).await {
         Ok(_) => {
-            eprintln!("Package '{}' published successfully!", package_name);
-            eprintln!(
+            info!("Package '{}' published successfully!", package_name);
+            info!(
                 "   View at: {}/packages/{}",
                 registry_url.replace("/api", ""),
                 package_name
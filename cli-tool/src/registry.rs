@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser, Subcommand};
+use nargo_add::registry_client::{ImportResult, RegistryClient};
+use nargo_add::{completions, info, output, utils};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(name = "nargo-registry")]
+#[command(about = "Back up or seed a Noir registry (use: nargo registry <command>)")]
+#[command(version)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Registry API URL (optional, defaults to NOIR_REGISTRY_URL env var)
+    #[arg(long, global = true)]
+    registry: Option<String>,
+
+    /// Timeout in seconds for registry requests (defaults to NOIR_REGISTRY_TIMEOUT env var)
+    #[arg(long, global = true)]
+    timeout: Option<u64>,
+
+    /// Suppress informational output (warnings and errors are still shown)
+    #[arg(long, short, global = true)]
+    quiet: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write every package (with its keywords and version history) to a JSON file
+    Export {
+        /// File to write; replaced only once the export has completed
+        #[arg(long, default_value = "registry.json")]
+        out: PathBuf,
+    },
+    /// Load a file written by `export` into a registry (needs its admin key)
+    Import {
+        /// Export file to load
+        #[arg(long)]
+        file: PathBuf,
+
+        /// Registry admin key (defaults to NOIR_REGISTRY_ADMIN_KEY env var)
+        #[arg(long)]
+        admin_key: Option<String>,
+    },
+}
+
+/// Turns one NDJSON line from the registry into an export record. The
+/// database id is dropped: it means nothing to the registry importing it.
+fn export_record(line: &[u8]) -> Result<Option<String>> {
+    let line = std::str::from_utf8(line).context("Registry sent invalid UTF-8")?;
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+    let mut record: serde_json::Value =
+        serde_json::from_str(line).context("Failed to parse package from registry")?;
+    if let Some(fields) = record.as_object_mut() {
+        fields.remove("id");
+    }
+    Ok(Some(serde_json::to_string(&record)?))
+}
+
+/// Appends one export record to the array being written, opening it first
+fn write_record(file: &mut impl Write, line: &[u8], count: &mut usize) -> Result<()> {
+    if let Some(record) = export_record(line)? {
+        let sep = if *count == 0 { "[\n" } else { ",\n" };
+        write!(file, "{}{}", sep, record)?;
+        *count += 1;
+    }
+    Ok(())
+}
+
+/// Streams `GET /packages` as NDJSON into a JSON array, one package per line.
+/// Writes to a temporary file next to `out` so a failed export never leaves
+/// a truncated backup in its place.
+async fn export(registry: &RegistryClient, out: &Path) -> Result<()> {
+    let mut packages = registry.export().await?;
+
+    let mut tmp_name = out.as_os_str().to_os_string();
+    tmp_name.push(".partial");
+    let tmp = PathBuf::from(tmp_name);
+    let mut file = std::io::BufWriter::new(
+        std::fs::File::create(&tmp)
+            .with_context(|| format!("Failed to create {}", tmp.display()))?,
+    );
+
+    let mut count = 0usize;
+    let result: Result<()> = async {
+        while let Some(line) = packages.next_line().await? {
+            write_record(&mut file, &line, &mut count)?;
+        }
+        file.write_all(if count == 0 { b"[]\n" } else { b"\n]\n" })?;
+        file.flush()?;
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    std::fs::rename(&tmp, out).with_context(|| format!("Failed to write {}", out.display()))?;
+
+    info!("Exported {} package(s) to {}", count, out.display());
+    Ok(())
+}
+
+async fn import(registry: &RegistryClient, file: &Path, admin_key: &str) -> Result<()> {
+    let contents = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let records: Vec<serde_json::Value> = serde_json::from_str(&contents)
+        .with_context(|| format!("{} is not a registry export", file.display()))?;
+    if records.is_empty() {
+        info!("{} has no packages, nothing to import", file.display());
+        return Ok(());
+    }
+
+    let results = registry.import(admin_key, &records).await?;
+    let failed: Vec<&ImportResult> = results.iter().filter(|r| !r.success).collect();
+    for r in &failed {
+        eprintln!(
+            "Warning: '{}' was not imported: {}",
+            r.name,
+            r.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    info!(
+        "Imported {} of {} package(s) into {}",
+        results.len() - failed.len(),
+        results.len(),
+        registry.registry_url()
+    );
+    if !failed.is_empty() {
+        anyhow::bail!("{} package(s) failed to import", failed.len());
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    completions::handle(Args::command());
+    let args = Args::parse();
+    output::set_quiet(args.quiet);
+    let (registry_url, _) = utils::get_registry_url(args.registry.clone());
+    let registry = RegistryClient::new(&registry_url, utils::get_registry_timeout(args.timeout));
+
+    match args.command {
+        Command::Export { out } => export(&registry, &out).await,
+        Command::Import { file, admin_key } => {
+            let admin_key = admin_key
+                .or_else(|| std::env::var("NOIR_REGISTRY_ADMIN_KEY").ok())
+                .filter(|k| !k.is_empty())
+                .context("No admin key. Pass --admin-key or set NOIR_REGISTRY_ADMIN_KEY.")?;
+            import(&registry, &file, &admin_key).await
+        }
+    }
+}
@@ -0,0 +1,958 @@
+//! Typed client for the registry API, shared by the CLI binaries.
+//!
+//! Request and response types live here rather than in each command, so a
+//! field added to the registry's responses is picked up once.
+
+use crate::github::{fetch_latest_github_tag, latest_semver_tag};
+use crate::output::ErrorKind;
+use crate::response_cache::ResponseCache;
+use crate::{http, info, utils};
+use anyhow::{Context, Result};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Side calls (dependency lists, download pings) that nothing waits on give
+/// up sooner than the main lookup when no `--timeout` was given
+const SIDE_CALL_TIMEOUT: Duration = Duration::from_secs(5);
+/// A full export is one long streamed response, so without an explicit
+/// timeout allow far more than the client's per-request default
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A package as the registry describes it
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageInfo {
+    pub name: String,
+    pub github_repository_url: String,
+    pub latest_version: Option<String>,
+    #[serde(default)]
+    pub homepage: Option<String>,
+    /// Subdirectory for monorepo packages (absent on older registries)
+    #[serde(default)]
+    pub directory: Option<String>,
+    /// Commit the latest version points to (absent on older registries)
+    #[serde(default)]
+    pub latest_version_commit_sha: Option<String>,
+    /// Set by the owner to steer users to another package (absent on older registries)
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default)]
+    pub deprecation_message: Option<String>,
+}
+
+impl PackageInfo {
+    /// Whether the latest version was published with `--rev`, i.e. it is
+    /// the commit SHA itself rather than a tag
+    pub fn latest_is_rev(&self) -> bool {
+        self.latest_version.is_some() && self.latest_version == self.latest_version_commit_sha
+    }
+}
+
+/// Body of a registry 404; older registries send no suggestions
+#[derive(Deserialize)]
+struct NotFoundResponse {
+    #[serde(default)]
+    suggestions: Vec<String>,
+}
+
+/// A dependency the registry indexed from a package's Nargo.toml
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexedDependency {
+    pub name: String,
+    pub git_url: Option<String>,
+    pub directory: Option<String>,
+    pub tag: Option<String>,
+}
+
+/// A git tag of a package's repository, as the registry relays it from GitHub
+#[derive(Debug, Clone, Deserialize)]
+pub struct RepoTag {
+    pub name: String,
+}
+
+/// One page of search results
+#[derive(Debug, Deserialize)]
+pub struct SearchPage {
+    pub items: Vec<SearchResult>,
+    pub total: u64,
+    pub offset: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchResult {
+    pub name: String,
+    pub description: Option<String>,
+    pub github_stars: i32,
+}
+
+/// What `nargo publish` sends
+#[derive(Debug, Serialize)]
+pub struct PublishRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub github_repository_url: String,
+    pub version: Option<String>,
+    pub rev: Option<String>,
+    pub license: Option<String>,
+    pub homepage: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    /// Confirms the repository is a fork on purpose
+    pub allow_fork: bool,
+}
+
+#[derive(Deserialize)]
+struct PublishResponse {
+    success: bool,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct VersionShaResponse {
+    commit_sha: String,
+}
+
+/// A published version, oldest first in [`RegistryClient::versions`]
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageVersion {
+    pub version: String,
+    pub commit_sha: Option<String>,
+    #[serde(default)]
+    pub published_at: Option<String>,
+    #[serde(default)]
+    pub downloads: i32,
+}
+
+/// An API token on the caller's account; the raw value is never sent back
+#[derive(Debug, Deserialize)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub token_prefix: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateTokenRequest<'a> {
+    name: &'a str,
+}
+
+/// A newly created token, with its raw value (shown only this once)
+#[derive(Debug, Deserialize)]
+pub struct CreatedToken {
+    pub token: ApiToken,
+    pub raw: String,
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+struct ValidateKeyResponse {
+    username: String,
+}
+
+#[derive(Serialize)]
+struct GitHubAuthRequest<'a> {
+    github_token: &'a str,
+}
+
+#[derive(Deserialize)]
+struct GitHubAuthResponse {
+    success: bool,
+    api_key: Option<String>,
+    message: String,
+}
+
+/// Outcome of one record, as returned by the import endpoint
+#[derive(Debug, Deserialize)]
+pub struct ImportResult {
+    pub name: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PackageName {
+    name: String,
+}
+
+/// The registry's packages as streamed by [`RegistryClient::export`], one
+/// NDJSON line at a time
+pub struct ExportStream {
+    response: Response,
+    pending: Vec<u8>,
+    done: bool,
+}
+
+impl ExportStream {
+    /// The next line, newline included; None once the stream has ended
+    pub async fn next_line(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            if let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+                return Ok(Some(self.pending.drain(..=pos).collect()));
+            }
+            if self.done {
+                // A last line without a trailing newline
+                return Ok((!self.pending.is_empty()).then(|| std::mem::take(&mut self.pending)));
+            }
+            match self
+                .response
+                .chunk()
+                .await
+                .context("Connection to registry lost during export")?
+            {
+                Some(chunk) => self.pending.extend_from_slice(&chunk),
+                None => self.done = true,
+            }
+        }
+    }
+}
+
+/// A failed registry lookup, classified so `--json` can report its kind
+#[derive(Debug)]
+pub struct FetchError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Similarly named packages the registry offered on a 404
+    pub suggestions: Vec<String>,
+}
+
+impl FetchError {
+    fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        FetchError {
+            kind,
+            message: message.into(),
+            suggestions: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Talks to one registry. `timeout` is the `--timeout` / `NOIR_REGISTRY_TIMEOUT`
+/// setting; None keeps each call's default. Lookups go through the response
+/// cache when one is configured.
+#[derive(Debug, Clone)]
+pub struct RegistryClient {
+    registry_url: String,
+    timeout: Option<Duration>,
+    client: Client,
+    cache: Option<ResponseCache>,
+}
+
+impl RegistryClient {
+    pub fn new(registry_url: &str, timeout: Option<Duration>) -> Self {
+        RegistryClient {
+            registry_url: registry_url.trim_end_matches('/').to_string(),
+            timeout,
+            client: http::build_client(),
+            cache: ResponseCache::from_settings(),
+        }
+    }
+
+    /// `--no-cache`: fetch everything from the registry, replacing what the
+    /// response cache holds
+    pub fn no_cache(mut self, no_cache: bool) -> Self {
+        if no_cache {
+            self.cache = self.cache.map(ResponseCache::refreshing);
+        }
+        self
+    }
+
+    pub fn registry_url(&self) -> &str {
+        &self.registry_url
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.registry_url, path)
+    }
+
+    /// The server's `/health` endpoint, which sits beside the API rather than
+    /// under it
+    fn health_url(&self) -> String {
+        let base = self
+            .registry_url
+            .strip_suffix("/api")
+            .unwrap_or(&self.registry_url);
+        format!("{}/health", base)
+    }
+
+    /// A cached response for `url`, if caching is on and it hasn't expired
+    fn cached<T: DeserializeOwned>(&self, url: &str) -> Option<T> {
+        serde_json::from_str(&self.cache.as_ref()?.load(url)?).ok()
+    }
+
+    /// Parses a successful response, keeping its body in the cache
+    async fn read_json<T: DeserializeOwned>(&self, url: &str, response: Response) -> Result<T> {
+        let body = response.text().await?;
+        let value = serde_json::from_str(&body)?;
+        if let Some(cache) = &self.cache {
+            cache.store(url, &body);
+        }
+        Ok(value)
+    }
+
+    /// Applies the configured timeout, if any, on top of the client's default
+    fn with_timeout(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
+
+    /// Fetches a package, retrying network errors and 502/503 with
+    /// exponential backoff (3 attempts)
+    pub async fn get_package(&self, package_name: &str) -> Result<PackageInfo, FetchError> {
+        let url = self.url(&format!("packages/{}", package_name));
+        if let Some(package) = self.cached(&url) {
+            return Ok(package);
+        }
+
+        for attempt in 0..3 {
+            let response = match self.with_timeout(self.client.get(&url)).send().await {
+                Ok(resp) => resp,
+                Err(e) => {
+                    if attempt < 2 {
+                        let delay = Duration::from_millis(100 * (1 << attempt));
+                        tokio::time::sleep(delay).await;
+                        continue;
+                    }
+                    return Err(FetchError::new(
+                        ErrorKind::Network,
+                        format!(
+                            "Failed to connect to registry at {}: Network error: {}",
+                            url, e
+                        ),
+                    ));
+                }
+            };
+
+            utils::check_api_version(&response);
+            match response.status() {
+                status if status.is_success() => match self.read_json(&url, response).await {
+                    Ok(package) => return Ok(package),
+                    Err(e) => {
+                        return Err(FetchError::new(
+                            ErrorKind::Parse,
+                            format!(
+                                "Failed to parse package response from registry: {}. \
+                                The registry may be returning an unexpected format.",
+                                e
+                            ),
+                        ));
+                    }
+                },
+                StatusCode::NOT_FOUND => {
+                    let suggestions = response
+                        .json::<NotFoundResponse>()
+                        .await
+                        .map(|r| r.suggestions)
+                        .unwrap_or_default();
+                    return Err(FetchError {
+                        suggestions: suggestions.clone(),
+                        ..FetchError::new(
+                            ErrorKind::NotFound,
+                            utils::package_not_found_message(
+                                package_name,
+                                &self.registry_url,
+                                &suggestions,
+                            ),
+                        )
+                    });
+                }
+                status
+                    if status == StatusCode::SERVICE_UNAVAILABLE
+                        || status == StatusCode::BAD_GATEWAY =>
+                {
+                    if attempt < 2 {
+                        let delay = Duration::from_millis(500 * (1 << attempt));
+                        // Progress, not an error: `--json` output must stay a single object
+                        info!(
+                            "Registry temporarily unavailable, retrying in {:.1}s...",
+                            delay.as_secs_f64()
+                        );
+                        tokio::time::sleep(delay).await;
+                        continue;
+                    }
+                    return Err(FetchError::new(
+                        ErrorKind::Server,
+                        format!(
+                            "Registry server is unavailable: Registry server error: {}",
+                            status
+                        ),
+                    ));
+                }
+                status => {
+                    let error_text = response.text().await.unwrap_or_default();
+                    return Err(FetchError::new(
+                        ErrorKind::Server,
+                        format!(
+                            "Registry returned error {}: {}\n\
+                            Registry URL: {}",
+                            status, error_text, self.registry_url
+                        ),
+                    ));
+                }
+            }
+        }
+
+        Err(FetchError::new(
+            ErrorKind::Network,
+            "Registry request failed: Failed to fetch package after 3 attempts",
+        ))
+    }
+
+    /// Pings the registry's health check, retrying network errors and
+    /// 502/503 like [`Self::get_package`]. Errs if it still isn't healthy.
+    pub async fn check_health(&self) -> Result<()> {
+        let url = self.health_url();
+        let mut last_error = String::new();
+        for attempt in 0..3 {
+            if attempt > 0 {
+                tokio::time::sleep(Duration::from_millis(500 * (1 << (attempt - 1)))).await;
+            }
+            match self.with_timeout(self.client.get(&url)).send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response)
+                    if response.status() == StatusCode::SERVICE_UNAVAILABLE
+                        || response.status() == StatusCode::BAD_GATEWAY =>
+                {
+                    last_error = format!("health check returned {}", response.status());
+                }
+                Ok(response) => {
+                    last_error = format!("health check returned {}", response.status());
+                    break;
+                }
+                Err(e) => last_error = e.to_string(),
+            }
+        }
+        anyhow::bail!(
+            "Registry at {} is unavailable ({}). Try again later.",
+            self.registry_url,
+            last_error
+        )
+    }
+
+    /// Fetches the dependencies the registry indexed for a package. None if the
+    /// registry doesn't know them (e.g. an older registry); this is advisory only.
+    pub async fn dependencies(&self, package_name: &str) -> Option<Vec<IndexedDependency>> {
+        let url = self.url(&format!("packages/{}/dependencies", package_name));
+        if let Some(cached) = self.cached(&url) {
+            return Some(cached);
+        }
+        let response = self
+            .client
+            .get(&url)
+            .timeout(self.timeout.unwrap_or(SIDE_CALL_TIMEOUT))
+            .send()
+            .await
+            .ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        self.read_json(&url, response).await.ok()
+    }
+
+    /// Tags of the package's repository, fetched by the registry so the
+    /// caller doesn't spend its own GitHub quota. None if the registry can't
+    /// provide them (e.g. an older registry, or GitHub is unreachable).
+    pub async fn tags(&self, package_name: &str) -> Option<Vec<RepoTag>> {
+        let url = self.url(&format!("packages/{}/tags", package_name));
+        if let Some(cached) = self.cached(&url) {
+            return Some(cached);
+        }
+        let response = self
+            .client
+            .get(&url)
+            .timeout(self.timeout.unwrap_or(SIDE_CALL_TIMEOUT))
+            .send()
+            .await
+            .ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        self.read_json(&url, response).await.ok()
+    }
+
+    /// Newest semver tag of the package's repository (see
+    /// [`latest_semver_tag`]). The registry relays GitHub's tags with its own
+    /// token; GitHub is only asked directly when it can't.
+    pub async fn latest_tag(
+        &self,
+        package_name: &str,
+        github_url: &str,
+        allow_pre: bool,
+    ) -> Option<String> {
+        match self.tags(package_name).await {
+            Some(tags) => latest_semver_tag(tags.iter().map(|t| t.name.as_str()), allow_pre)
+                .map(str::to_string),
+            None => fetch_latest_github_tag(&self.client, github_url, allow_pre).await,
+        }
+    }
+
+    /// One page of search results. `sort` is passed through as given.
+    pub async fn search(
+        &self,
+        query: &str,
+        offset: u64,
+        limit: u64,
+        sort: Option<&str>,
+        exact: bool,
+    ) -> Result<SearchPage> {
+        let url = self.url("search");
+        let mut params = vec![
+            ("q", query.to_string()),
+            ("offset", offset.to_string()),
+            ("limit", limit.to_string()),
+        ];
+        if let Some(sort) = sort {
+            params.push(("sort", sort.to_string()));
+        }
+        if exact {
+            params.push(("exact", "true".to_string()));
+        }
+        // The full URL, query included, is what the cache is keyed on
+        let request_url = Url::parse_with_params(&url, &params)
+            .with_context(|| format!("Invalid registry URL: {}", url))?;
+        if let Some(page) = self.cached(request_url.as_str()) {
+            return Ok(page);
+        }
+
+        let response = self
+            .with_timeout(self.client.get(request_url.clone()))
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to registry at {}", url))?;
+
+        utils::check_api_version(&response);
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Search failed ({}): {}", status, body);
+        }
+
+        self.read_json(request_url.as_str(), response)
+            .await
+            .context("Failed to parse search response")
+    }
+
+    /// Publishes a package under the account `api_key` belongs to
+    pub async fn publish(&self, api_key: &str, request: &PublishRequest) -> Result<()> {
+        let post = self
+            .client
+            .post(self.url("packages/publish"))
+            .header("Authorization", format!("Bearer {}", api_key))
+            .json(request);
+        let response = self
+            .with_timeout(post)
+            .send()
+            .await
+            .context("Failed to connect to registry")?;
+
+        // Check the status before parsing: error pages from a proxy (e.g. a 502)
+        // aren't JSON, and their raw body is more useful than a parse error.
+        utils::check_api_version(&response);
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let body = body.trim();
+            if body.is_empty() {
+                anyhow::bail!("Publish failed with status {}", status);
+            }
+            anyhow::bail!("Publish failed with status {}: {}", status, body);
+        }
+
+        let publish_response: PublishResponse = response
+            .json()
+            .await
+            .context("Failed to parse publish response")?;
+
+        if !publish_response.success {
+            anyhow::bail!("Publish failed: {}", publish_response.message);
+        }
+
+        Ok(())
+    }
+
+    /// Counts a download of the package, and of `version` when the dependency
+    /// was pinned to a tag. Fire-and-forget: failures are ignored.
+    pub async fn record_download(&self, package_name: &str, version: Option<&str>) {
+        let mut request = self
+            .client
+            .post(self.url(&format!("packages/{}/download", package_name)));
+        if let Some(version) = version {
+            request = request.query(&[("version", version)]);
+        }
+        let _ = request
+            .timeout(self.timeout.unwrap_or(SIDE_CALL_TIMEOUT))
+            .send()
+            .await;
+    }
+
+    /// Commit a published version resolves to. None if the registry doesn't
+    /// know the package or version.
+    pub async fn version_sha(&self, package_name: &str, version: &str) -> Result<Option<String>> {
+        let url = self.url(&format!(
+            "packages/{}/versions/{}/sha",
+            package_name, version
+        ));
+        if let Some(sha) = self.cached::<VersionShaResponse>(&url) {
+            return Ok(Some(sha.commit_sha));
+        }
+        let response = self
+            .with_timeout(self.client.get(&url))
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to registry at {}", url))?;
+
+        utils::check_api_version(&response);
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Registry returned error {}: {}", status, body);
+        }
+        let sha: VersionShaResponse = self
+            .read_json(&url, response)
+            .await
+            .context("Failed to parse version response")?;
+        Ok(Some(sha.commit_sha))
+    }
+
+    /// Every published version of a package, oldest first. None if the
+    /// registry doesn't know the package.
+    pub async fn versions(&self, package_name: &str) -> Result<Option<Vec<PackageVersion>>> {
+        let url = self.url(&format!("packages/{}/versions", package_name));
+        if let Some(versions) = self.cached(&url) {
+            return Ok(Some(versions));
+        }
+        let response = self
+            .with_timeout(self.client.get(&url))
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to registry at {}", url))?;
+
+        utils::check_api_version(&response);
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Registry returned error {}: {}", status, body);
+        }
+        self.read_json(&url, response)
+            .await
+            .context("Failed to parse versions response")
+            .map(Some)
+    }
+
+    /// Names of every package in the registry
+    pub async fn package_names(&self) -> Result<Vec<String>> {
+        let url = self.url("packages");
+        let packages: Vec<PackageName> = match self.cached(&url) {
+            Some(packages) => packages,
+            None => {
+                let response = self
+                    .with_timeout(self.client.get(&url))
+                    .send()
+                    .await
+                    .with_context(|| format!("Failed to connect to registry at {}", url))?
+                    .error_for_status()?;
+                self.read_json(&url, response)
+                    .await
+                    .context("Failed to parse package list")?
+            }
+        };
+        Ok(packages.into_iter().map(|p| p.name).collect())
+    }
+
+    /// Streams every package (with its keywords and version history) as
+    /// NDJSON, for backups. Never cached.
+    pub async fn export(&self) -> Result<ExportStream> {
+        let url = self.url("packages");
+        let response = self
+            .client
+            .get(&url)
+            .header(reqwest::header::ACCEPT, "application/x-ndjson")
+            .timeout(self.timeout.unwrap_or(EXPORT_TIMEOUT))
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to registry at {}", url))?;
+
+        utils::check_api_version(&response);
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Registry returned error {}: {}", status, body);
+        }
+        Ok(ExportStream {
+            response,
+            pending: Vec::new(),
+            done: false,
+        })
+    }
+
+    /// Loads exported packages into the registry, authorized by its admin key
+    pub async fn import(
+        &self,
+        admin_key: &str,
+        records: &[serde_json::Value],
+    ) -> Result<Vec<ImportResult>> {
+        let url = self.url("admin/packages/import");
+        let post = self
+            .client
+            .post(&url)
+            .header("X-Admin-Key", admin_key)
+            .json(records);
+        let response = self
+            .with_timeout(post)
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to registry at {}", url))?;
+
+        utils::check_api_version(&response);
+        let status = response.status();
+        if status == StatusCode::UNAUTHORIZED {
+            anyhow::bail!("Registry rejected the admin key (or has no ADMIN_API_KEY configured)");
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Registry returned error {}: {}", status, body);
+        }
+        response
+            .json()
+            .await
+            .context("Failed to parse import response from registry")
+    }
+
+    /// Signs in with a GitHub token. Returns Some(api_key) on new-user
+    /// creation, None if the user already existed (the registry only issues
+    /// a raw token once).
+    pub async fn authenticate_github(&self, github_token: &str) -> Result<Option<String>> {
+        let url = self.url("auth/github");
+        let request = GitHubAuthRequest { github_token };
+
+        // Retry logic: 3 attempts with exponential backoff on network errors and 5xx.
+        // 4xx (e.g. a bad token) fails straight away with the server's message.
+        let mut attempt = 0;
+        let response = loop {
+            let delay = Duration::from_millis(500 * (1 << attempt));
+            let post = self.client.post(&url).json(&request);
+            match self.with_timeout(post).send().await {
+                Ok(resp) if resp.status().is_server_error() && attempt < 2 => {
+                    eprintln!(
+                        "Registry returned {}, retrying in {:.1}s...",
+                        resp.status(),
+                        delay.as_secs_f64()
+                    );
+                }
+                Ok(resp) => break resp,
+                Err(e) if attempt < 2 => {
+                    eprintln!(
+                        "Network error ({}), retrying in {:.1}s...",
+                        e,
+                        delay.as_secs_f64()
+                    );
+                }
+                Err(e) => return Err(e).context("Failed to connect to registry"),
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        };
+
+        utils::check_api_version(&response);
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Authentication failed: {}", error_text);
+        }
+
+        let auth_response: GitHubAuthResponse = response
+            .json()
+            .await
+            .context("Failed to parse authentication response")?;
+
+        if !auth_response.success {
+            anyhow::bail!("Authentication failed: {}", auth_response.message);
+        }
+
+        Ok(auth_response.api_key)
+    }
+
+    /// The account `api_key` belongs to. Errs if the key is unknown or revoked.
+    pub async fn validate_key(&self, api_key: &str) -> Result<String> {
+        let get = self
+            .client
+            .get(self.url("auth/validate"))
+            .bearer_auth(api_key);
+        let response = self
+            .with_timeout(get)
+            .send()
+            .await
+            .context("Failed to connect to registry")?;
+
+        utils::check_api_version(&response);
+        match response.status() {
+            status if status.is_success() => {
+                let valid: ValidateKeyResponse = response
+                    .json()
+                    .await
+                    .context("Failed to parse validate response")?;
+                Ok(valid.username)
+            }
+            StatusCode::UNAUTHORIZED => anyhow::bail!(
+                "Token is not valid (unknown or revoked). Run 'nargo login' or 'nargo token create' for a new one."
+            ),
+            StatusCode::NOT_FOUND => {
+                anyhow::bail!(
+                    "This registry doesn't support token validation (GET /api/auth/validate)."
+                )
+            }
+            other => {
+                let body = response.text().await.unwrap_or_default();
+                anyhow::bail!("Verify failed ({}): {}", other, body)
+            }
+        }
+    }
+
+    /// Tokens on the account `api_key` belongs to, revoked ones included
+    pub async fn tokens(&self, api_key: &str) -> Result<Vec<ApiToken>> {
+        let get = self.client.get(self.url("tokens")).bearer_auth(api_key);
+        let response = self
+            .with_timeout(get)
+            .send()
+            .await
+            .context("Failed to connect to registry")?;
+
+        utils::check_api_version(&response);
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("List tokens failed ({}): {}", status, body);
+        }
+        response
+            .json()
+            .await
+            .context("Failed to parse tokens response")
+    }
+
+    /// Creates a named token on the account `api_key` belongs to
+    pub async fn create_token(&self, api_key: &str, name: &str) -> Result<CreatedToken> {
+        let post = self
+            .client
+            .post(self.url("tokens"))
+            .bearer_auth(api_key)
+            .json(&CreateTokenRequest { name });
+        let response = self
+            .with_timeout(post)
+            .send()
+            .await
+            .context("Failed to connect to registry")?;
+
+        utils::check_api_version(&response);
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Create token failed ({}): {}", status, body);
+        }
+        response
+            .json()
+            .await
+            .context("Failed to parse create response")
+    }
+
+    /// Revokes a token by id. False if there is no such active token on the
+    /// account `api_key` belongs to.
+    pub async fn revoke_token(&self, api_key: &str, id: i32) -> Result<bool> {
+        let delete = self
+            .client
+            .delete(self.url(&format!("tokens/{}", id)))
+            .bearer_auth(api_key);
+        let response = self
+            .with_timeout(delete)
+            .send()
+            .await
+            .context("Failed to connect to registry")?;
+
+        utils::check_api_version(&response);
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            other => {
+                let body = response.text().await.unwrap_or_default();
+                anyhow::bail!("Revoke failed ({}): {}", other, body)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn older_registries_without_optional_fields_still_parse() {
+        let package: PackageInfo = serde_json::from_str(
+            r#"{"name": "bignum", "github_repository_url": "https://github.com/noir-lang/noir-bignum", "latest_version": "v0.5.0"}"#,
+        )
+        .unwrap();
+        assert_eq!(package.directory, None);
+        assert_eq!(package.homepage, None);
+        assert!(!package.latest_is_rev());
+        assert!(!package.deprecated);
+    }
+
+    #[test]
+    fn versions_parse_as_the_registry_sends_them() {
+        let versions: Vec<PackageVersion> = serde_json::from_str(
+            r#"[{"version": "v0.1.0", "commit_sha": null, "published_at": "2025-01-02T03:04:05Z", "downloads": 7},
+                {"version": "v0.2.0", "commit_sha": "abc123"}]"#,
+        )
+        .unwrap();
+        assert_eq!(versions[0].downloads, 7);
+        assert_eq!(versions[1].commit_sha.as_deref(), Some("abc123"));
+        assert_eq!(versions[1].published_at, None);
+    }
+
+    #[test]
+    fn version_equal_to_its_commit_is_a_rev() {
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        let package = PackageInfo {
+            name: "untagged".to_string(),
+            github_repository_url: "https://github.com/owner/untagged".to_string(),
+            latest_version: Some(sha.to_string()),
+            homepage: None,
+            directory: None,
+            latest_version_commit_sha: Some(sha.to_string()),
+            deprecated: false,
+            deprecation_message: None,
+        };
+        assert!(package.latest_is_rev());
+    }
+
+    #[test]
+    fn trailing_slash_is_dropped_from_the_registry_url() {
+        let registry = RegistryClient::new("https://registry.example.com/api/", None);
+        assert_eq!(
+            registry.url("packages/bignum"),
+            "https://registry.example.com/api/packages/bignum"
+        );
+    }
+
+    #[test]
+    fn health_check_sits_beside_the_api() {
+        let registry = RegistryClient::new("https://registry.example.com/api", None);
+        assert_eq!(registry.health_url(), "https://registry.example.com/health");
+        let registry = RegistryClient::new("http://localhost:8080", None);
+        assert_eq!(registry.health_url(), "http://localhost:8080/health");
+    }
+}
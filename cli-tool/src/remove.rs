@@ -1,6 +1,6 @@
 use anyhow::{Context, Result};
-use clap::Parser;
-use nargo_add::nargo_toml;
+use clap::{CommandFactory, Parser};
+use nargo_add::{completions, info, nargo_toml, output};
 use std::fs;
 use std::path::{Path, PathBuf};
 use toml_edit::DocumentMut;
@@ -22,16 +22,25 @@ struct Args {
     /// Also delete cached source files from ~/nargo
     #[arg(long)]
     clean: bool,
+
+    /// Delete the [dependencies] table if removing leaves it empty
+    #[arg(long)]
+    prune_empty: bool,
+
+    /// Suppress informational output (warnings and errors are still shown)
+    #[arg(long, short)]
+    quiet: bool,
 }
 
 /// Removes a dependency from Nargo.toml.
 /// Returns Ok(Some(git_url)) if the dependency was found and removed, Ok(None) if it wasn't present.
+/// With `prune_empty`, a `[dependencies]` table left empty is dropped from the file.
 fn remove_dependency_from_nargo_toml(
     manifest_path: &Path,
     package_name: &str,
+    prune_empty: bool,
 ) -> Result<Option<String>> {
-    let content = fs::read_to_string(manifest_path)
-        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    let content = nargo_toml::read_manifest(manifest_path)?;
 
     let mut doc = content
         .parse::<DocumentMut>()
@@ -66,6 +75,9 @@ fn remove_dependency_from_nargo_toml(
 
     // Remove the dependency
     deps.remove(package_name);
+    if prune_empty && deps.is_empty() {
+        doc.remove("dependencies");
+    }
 
     // Write back
     fs::write(manifest_path, doc.to_string())
@@ -106,19 +118,21 @@ fn clean_cached_source(git_url: &str) -> Result<bool> {
     };
 
     if !cache_dir.exists() {
-        eprintln!("   No cached files found at {}", cache_dir.display());
+        info!("   No cached files found at {}", cache_dir.display());
         return Ok(false);
     }
 
     fs::remove_dir_all(&cache_dir)
         .with_context(|| format!("Failed to delete cache at {}", cache_dir.display()))?;
 
-    eprintln!("   Deleted cached source: {}", cache_dir.display());
+    info!("   Deleted cached source: {}", cache_dir.display());
     Ok(true)
 }
 
 fn main() -> Result<()> {
+    completions::handle(Args::command());
     let args = Args::parse();
+    output::set_quiet(args.quiet);
 
     // Find Nargo.toml
     let current_dir = std::env::current_dir().context("Failed to get current directory")?;
@@ -137,13 +151,17 @@ fn main() -> Result<()> {
     let mut errors = Vec::new();
 
     for package_name in &args.package_names {
-        match remove_dependency_from_nargo_toml(&manifest_path, package_name) {
+        match remove_dependency_from_nargo_toml(&manifest_path, package_name, args.prune_empty) {
             Ok(Some(git_url)) => {
-                eprintln!("Removed '{}' from {}", package_name, manifest_path.display());
-                if args.clean {
-                    if let Err(e) = clean_cached_source(&git_url) {
-                        eprintln!("   Failed to clean cache for '{}': {}", package_name, e);
-                    }
+                info!(
+                    "Removed '{}' from {}",
+                    package_name,
+                    manifest_path.display()
+                );
+                if args.clean
+                    && let Err(e) = clean_cached_source(&git_url)
+                {
+                    eprintln!("   Failed to clean cache for '{}': {}", package_name, e);
                 }
                 removed.push(package_name.as_str());
             }
@@ -163,18 +181,22 @@ fn main() -> Result<()> {
     }
 
     // Validate the TOML is still well-formed after all removals
-    if !removed.is_empty() {
-        if let Err(e) = nargo_toml::validate_nargo_toml(&manifest_path) {
-            eprintln!("Warning: Could not validate Nargo.toml after removal: {}", e);
-            eprintln!("   Please check the file manually");
-        }
+    if !removed.is_empty()
+        && let Err(e) = nargo_toml::validate_nargo_toml(&manifest_path)
+    {
+        eprintln!("Warning: Could not validate Nargo.toml after removal: {}", e);
+        eprintln!("   Please check the file manually");
     }
 
     // Print summary when operating on multiple packages
     if args.package_names.len() > 1 {
-        eprintln!();
-        eprintln!("Summary: {} removed, {} not found, {} errors",
-            removed.len(), not_found.len(), errors.len());
+        info!();
+        info!(
+            "Summary: {} removed, {} not found, {} errors",
+            removed.len(),
+            not_found.len(),
+            errors.len()
+        );
     }
 
     if !errors.is_empty() {
@@ -190,3 +212,84 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOLE_DEPENDENCY: &str = "\
+[package]
+name = \"app\"
+type = \"bin\"
+
+[dependencies]
+my_lib = { git = \"https://github.com/owner/my-lib\", tag = \"v1.0.0\" }
+";
+
+    fn manifest(content: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), content).unwrap();
+        file
+    }
+
+    fn parsed(file: &tempfile::NamedTempFile) -> DocumentMut {
+        fs::read_to_string(file.path())
+            .unwrap()
+            .parse::<DocumentMut>()
+            .unwrap()
+    }
+
+    #[test]
+    fn removing_the_sole_dependency_with_prune_empty_drops_the_table() {
+        let file = manifest(SOLE_DEPENDENCY);
+        let git_url = remove_dependency_from_nargo_toml(file.path(), "my_lib", true).unwrap();
+        assert_eq!(git_url.as_deref(), Some("https://github.com/owner/my-lib"));
+
+        let doc = parsed(&file);
+        assert!(doc.get("dependencies").is_none());
+        assert_eq!(doc["package"]["name"].as_str(), Some("app"));
+        assert!(
+            !fs::read_to_string(file.path())
+                .unwrap()
+                .contains("[dependencies]")
+        );
+    }
+
+    #[test]
+    fn removing_the_sole_dependency_keeps_the_empty_table_by_default() {
+        let file = manifest(SOLE_DEPENDENCY);
+        remove_dependency_from_nargo_toml(file.path(), "my_lib", false)
+            .unwrap()
+            .expect("dependency was present");
+
+        let doc = parsed(&file);
+        let deps = doc["dependencies"].as_table().expect("table kept");
+        assert!(deps.is_empty());
+    }
+
+    #[test]
+    fn prune_empty_leaves_other_dependencies_alone() {
+        let file = manifest(&format!(
+            "{}other = {{ git = \"https://github.com/owner/other\" }}\n",
+            SOLE_DEPENDENCY
+        ));
+        remove_dependency_from_nargo_toml(file.path(), "my_lib", true)
+            .unwrap()
+            .expect("dependency was present");
+
+        let doc = parsed(&file);
+        let deps = doc["dependencies"].as_table().unwrap();
+        assert!(deps.contains_key("other"));
+        assert!(!deps.contains_key("my_lib"));
+    }
+
+    #[test]
+    fn missing_dependency_leaves_the_manifest_untouched() {
+        let file = manifest(SOLE_DEPENDENCY);
+        assert_eq!(
+            remove_dependency_from_nargo_toml(file.path(), "absent", true).unwrap(),
+            None
+        );
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), SOLE_DEPENDENCY);
+    }
+}
@@ -0,0 +1,146 @@
+//! Opt-in on-disk cache of the registry's GET responses.
+//!
+//! Entries live in `cache/` next to the config file, one per URL, and are
+//! served for `NOIR_REGISTRY_CACHE_TTL` seconds (or `cache_ttl` in the
+//! config). With neither set nothing is cached.
+
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CACHE_TTL_ENV: &str = "NOIR_REGISTRY_CACHE_TTL";
+
+/// One cached response, saved as `<hash of url>.json`
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// Checked on load, so a hash collision is a miss rather than a wrong answer
+    url: String,
+    /// Seconds since the Unix epoch
+    stored_at: u64,
+    body: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+    /// Ignore stored entries (`--no-cache`); fresh responses still replace them
+    refresh: bool,
+}
+
+/// The cache TTL and where it was set; None when caching is off. The env
+/// var wins over the config file, and 0 turns caching off.
+pub fn ttl_setting() -> Option<(Duration, &'static str)> {
+    let from_env = std::env::var(CACHE_TTL_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let (secs, source) = match from_env {
+        Some(secs) => (secs, "NOIR_REGISTRY_CACHE_TTL env var"),
+        None => (Config::load().ok()?.cache_ttl?, "config file"),
+    };
+    (secs > 0).then(|| (Duration::from_secs(secs), source))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// FNV-1a, which unlike std's hasher is stable across Rust releases
+fn url_hash(url: &str) -> u64 {
+    url.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        ResponseCache {
+            dir,
+            ttl,
+            refresh: false,
+        }
+    }
+
+    /// The cache in the config directory, if a TTL is configured
+    pub fn from_settings() -> Option<Self> {
+        let (ttl, _) = ttl_setting()?;
+        let dir = Config::config_path().ok()?.with_file_name("cache");
+        Some(Self::new(dir, ttl))
+    }
+
+    /// Stops stored entries from being served, so every lookup goes to the
+    /// registry and overwrites what was cached
+    pub fn refreshing(self) -> Self {
+        ResponseCache {
+            refresh: true,
+            ..self
+        }
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", url_hash(url)))
+    }
+
+    /// The body stored for `url`, if it is younger than the TTL
+    pub fn load(&self, url: &str) -> Option<String> {
+        if self.refresh {
+            return None;
+        }
+        let content = fs::read_to_string(self.path(url)).ok()?;
+        let entry: Entry = serde_json::from_str(&content).ok()?;
+        let fresh = now_secs().saturating_sub(entry.stored_at) < self.ttl.as_secs();
+        (entry.url == url && fresh).then_some(entry.body)
+    }
+
+    /// Saves a response body. Failures are ignored: the cache is only a shortcut.
+    pub fn store(&self, url: &str, body: &str) {
+        let entry = Entry {
+            url: url.to_string(),
+            stored_at: now_secs(),
+            body: body.to_string(),
+        };
+        if let Ok(json) = serde_json::to_string(&entry)
+            && fs::create_dir_all(&self.dir).is_ok()
+        {
+            let _ = fs::write(self.path(url), json);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://registry.example/api/packages/bignum";
+
+    #[test]
+    fn stored_responses_are_served_until_they_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ResponseCache::new(dir.path().join("cache"), Duration::from_secs(60));
+        assert_eq!(cache.load(URL), None);
+
+        cache.store(URL, "{\"name\":\"bignum\"}");
+        assert_eq!(cache.load(URL).as_deref(), Some("{\"name\":\"bignum\"}"));
+        assert_eq!(cache.load(&format!("{}/tags", URL)), None);
+
+        let expired = ResponseCache::new(dir.path().join("cache"), Duration::ZERO);
+        assert_eq!(expired.load(URL), None);
+    }
+
+    #[test]
+    fn refreshing_skips_stored_entries_and_replaces_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ResponseCache::new(dir.path().to_path_buf(), Duration::from_secs(60));
+        cache.store(URL, "old");
+
+        let refreshing = cache.clone().refreshing();
+        assert_eq!(refreshing.load(URL), None);
+        refreshing.store(URL, "new");
+        assert_eq!(cache.load(URL).as_deref(), Some("new"));
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser};
+use nargo_add::registry_client::{RegistryClient, SearchPage};
+use nargo_add::{completions, utils};
+
+#[derive(Parser)]
+#[command(name = "nargo-search")]
+#[command(about = "Search the Noir registry (use: nargo search <query>)")]
+#[command(version)]
+struct Args {
+    /// Text to search for in names, owners, descriptions and keywords
+    query: String,
+
+    /// Registry API URL (optional, defaults to NOIR_REGISTRY_URL env var)
+    #[arg(long)]
+    registry: Option<String>,
+
+    /// Page of results to show, starting at 1
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    page: u64,
+
+    /// Results per page (the registry caps this)
+    #[arg(long, default_value_t = 25, value_parser = clap::value_parser!(u64).range(1..))]
+    limit: u64,
+
+    /// Result order: relevance (default), stars, downloads or name
+    #[arg(long)]
+    sort: Option<String>,
+
+    /// Only show the package named exactly QUERY (ignoring case and `-`/`_`),
+    /// exiting with 1 if there is none; handy for checking a name is taken
+    #[arg(long)]
+    exact: bool,
+
+    /// Timeout in seconds for registry requests (defaults to NOIR_REGISTRY_TIMEOUT env var)
+    #[arg(long)]
+    timeout: Option<u64>,
+
+    /// Ignore cached registry responses and refresh them (see NOIR_REGISTRY_CACHE_TTL)
+    #[arg(long)]
+    no_cache: bool,
+}
+
+/// Width of the NAME column; STARS takes 6 more plus separators
+const NAME_WIDTH: usize = 30;
+
+/// Terminal width from `COLUMNS`, falling back to 80
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|&w| w > 0)
+        .unwrap_or(80)
+}
+
+/// Cuts `s` to at most `max` characters, marking the cut with "..."
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        let kept: String = s.chars().take(max.saturating_sub(3)).collect();
+        format!("{}...", kept)
+    }
+}
+
+/// Offset of the first result on `page`; Err when it doesn't fit in a u64
+fn page_offset(page: u64, limit: u64) -> Result<u64> {
+    (page - 1)
+        .checked_mul(limit)
+        .with_context(|| format!("--page {} with --limit {} is out of range", page, limit))
+}
+
+/// What to suggest for the rest of the results. The registry may return
+/// fewer than `limit` per page (it caps the page size), and then `--page N+1`
+/// would skip the results in between.
+fn more_hint(args: &Args, page: &SearchPage) -> String {
+    let returned = page.items.len() as u64;
+    if returned < args.limit {
+        format!(
+            "the registry returns at most {} per page, so page with --limit {}",
+            returned, returned
+        )
+    } else {
+        format!("use --page {} for more", args.page + 1)
+    }
+}
+
+fn print_page(args: &Args, page: &SearchPage) {
+    if page.total == 0 {
+        println!("No packages found for '{}'.", args.query);
+        return;
+    }
+    if page.items.is_empty() {
+        println!(
+            "No results on page {} ({} in total; try a lower --page).",
+            args.page, page.total
+        );
+        return;
+    }
+
+    let description_width = terminal_width().saturating_sub(NAME_WIDTH + 6 + 2).max(10);
+    println!("{:<w$} {:>6} DESCRIPTION", "NAME", "STARS", w = NAME_WIDTH);
+    for p in &page.items {
+        println!(
+            "{:<w$} {:>6} {}",
+            truncate(&p.name, NAME_WIDTH),
+            p.github_stars,
+            truncate(
+                p.description.as_deref().unwrap_or("-").trim(),
+                description_width
+            ),
+            w = NAME_WIDTH,
+        );
+    }
+
+    let first = page.offset + 1;
+    let last = page.offset + page.items.len() as u64;
+    if last < page.total {
+        println!(
+            "\nShowing {}-{} of {}; {}",
+            first,
+            last,
+            page.total,
+            more_hint(args, page)
+        );
+    } else {
+        println!("\nShowing {}-{} of {}", first, last, page.total);
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    completions::handle(Args::command());
+    let args = Args::parse();
+    let (registry_url, _) = utils::get_registry_url(args.registry.clone());
+
+    let registry = RegistryClient::new(&registry_url, utils::get_registry_timeout(args.timeout))
+        .no_cache(args.no_cache);
+    let offset = page_offset(args.page, args.limit)?;
+    let page = registry
+        .search(
+            &args.query,
+            offset,
+            args.limit,
+            args.sort.as_deref(),
+            args.exact,
+        )
+        .await?;
+    print_page(&args, &page);
+    if args.exact && page.total == 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(page: u64, limit: u64) -> Args {
+        Args::parse_from([
+            "nargo-search",
+            "bignum",
+            "--page",
+            &page.to_string(),
+            "--limit",
+            &limit.to_string(),
+        ])
+    }
+
+    fn results(offset: u64, count: usize, total: u64) -> SearchPage {
+        serde_json::from_value(serde_json::json!({
+            "items": (0..count).map(|i| serde_json::json!({
+                "name": format!("lib{}", i),
+                "github_stars": 0,
+            })).collect::<Vec<_>>(),
+            "total": total,
+            "offset": offset,
+            "limit": count,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn huge_pages_are_an_error_not_an_overflow() {
+        assert_eq!(page_offset(3, 25).unwrap(), 50);
+        assert!(page_offset(u64::MAX, 2).is_err());
+    }
+
+    #[test]
+    fn a_capped_page_size_is_pointed_out() {
+        assert_eq!(
+            more_hint(&args(1, 25), &results(0, 25, 100)),
+            "use --page 2 for more"
+        );
+        assert_eq!(
+            more_hint(&args(1, 5000), &results(0, 1000, 3000)),
+            "the registry returns at most 1000 per page, so page with --limit 1000"
+        );
+    }
+}
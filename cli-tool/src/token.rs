@@ -1,8 +1,7 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use nargo_add::{config, utils};
-use reqwest::{Client, StatusCode};
-use serde::{Deserialize, Serialize};
+use clap::{CommandFactory, Parser, Subcommand};
+use nargo_add::registry_client::RegistryClient;
+use nargo_add::{completions, config, utils};
 
 #[derive(Parser)]
 #[command(name = "nargo-token")]
@@ -15,6 +14,10 @@ struct Args {
     /// Registry API URL (optional, defaults to NOIR_REGISTRY_URL env var)
     #[arg(long, global = true)]
     registry: Option<String>,
+
+    /// Timeout in seconds for registry requests (defaults to NOIR_REGISTRY_TIMEOUT env var)
+    #[arg(long, global = true)]
+    timeout: Option<u64>,
 }
 
 #[derive(Subcommand)]
@@ -34,29 +37,8 @@ enum Command {
         /// Numeric token id (see `nargo token list`)
         id: i32,
     },
-}
-
-#[derive(Debug, Deserialize)]
-struct ApiToken {
-    id: i32,
-    name: String,
-    token_prefix: String,
-    created_at: String,
-    last_used_at: Option<String>,
-    revoked_at: Option<String>,
-}
-
-#[derive(Debug, Serialize)]
-struct CreateTokenRequest {
-    name: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct CreateTokenResponse {
-    #[allow(dead_code)]
-    token: ApiToken,
-    raw: String,
-    message: String,
+    /// Check that the stored token is still valid (exits non-zero if not)
+    Verify,
 }
 
 fn load_api_key() -> Result<String> {
@@ -66,27 +48,8 @@ fn load_api_key() -> Result<String> {
         .context("Not logged in. Run 'nargo login' first, or set an API key via the CLI.")
 }
 
-async fn list(registry_url: &str, api_key: &str) -> Result<()> {
-    let client = Client::new();
-    let url = format!("{}/tokens", registry_url.trim_end_matches('/'));
-
-    let response = client
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .send()
-        .await
-        .context("Failed to connect to registry")?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let body = response.text().await.unwrap_or_default();
-        anyhow::bail!("List tokens failed ({}): {}", status, body);
-    }
-
-    let tokens: Vec<ApiToken> = response
-        .json()
-        .await
-        .context("Failed to parse tokens response")?;
+async fn list(registry: &RegistryClient, api_key: &str) -> Result<()> {
+    let tokens = registry.tokens(api_key).await?;
 
     if tokens.is_empty() {
         println!("No tokens on this account.");
@@ -111,26 +74,8 @@ async fn list(registry_url: &str, api_key: &str) -> Result<()> {
     Ok(())
 }
 
-async fn create(registry_url: &str, api_key: &str, name: String, save: bool) -> Result<()> {
-    let client = Client::new();
-    let url = format!("{}/tokens", registry_url.trim_end_matches('/'));
-
-    let response = client
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .json(&CreateTokenRequest { name: name.clone() })
-        .send()
-        .await
-        .context("Failed to connect to registry")?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let body = response.text().await.unwrap_or_default();
-        anyhow::bail!("Create token failed ({}): {}", status, body);
-    }
-
-    let created: CreateTokenResponse =
-        response.json().await.context("Failed to parse create response")?;
+async fn create(registry: &RegistryClient, api_key: &str, name: String, save: bool) -> Result<()> {
+    let created = registry.create_token(api_key, &name).await?;
 
     println!("Token '{}' created.", name);
     println!("{}", created.message);
@@ -148,30 +93,18 @@ async fn create(registry_url: &str, api_key: &str, name: String, save: bool) ->
     Ok(())
 }
 
-async fn revoke(registry_url: &str, api_key: &str, id: i32) -> Result<()> {
-    let client = Client::new();
-    let url = format!("{}/tokens/{}", registry_url.trim_end_matches('/'), id);
-
-    let response = client
-        .delete(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .send()
-        .await
-        .context("Failed to connect to registry")?;
-
-    match response.status() {
-        StatusCode::NO_CONTENT => {
-            println!("Token {} revoked.", id);
-            Ok(())
-        }
-        StatusCode::NOT_FOUND => {
-            anyhow::bail!("Token {} not found (or not yours, or already revoked).", id)
-        }
-        other => {
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("Revoke failed ({}): {}", other, body)
-        }
+async fn revoke(registry: &RegistryClient, api_key: &str, id: i32) -> Result<()> {
+    if !registry.revoke_token(api_key, id).await? {
+        anyhow::bail!("Token {} not found (or not yours, or already revoked).", id);
     }
+    println!("Token {} revoked.", id);
+    Ok(())
+}
+
+async fn verify(registry: &RegistryClient, api_key: &str) -> Result<()> {
+    let username = registry.validate_key(api_key).await?;
+    println!("Token is valid for {}.", username);
+    Ok(())
 }
 
 fn truncate(s: &str, max: usize) -> String {
@@ -184,13 +117,16 @@ fn truncate(s: &str, max: usize) -> String {
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    completions::handle(Args::command());
     let args = Args::parse();
-    let registry_url = utils::get_registry_url(args.registry);
+    let (registry_url, _) = utils::get_registry_url(args.registry);
+    let registry = RegistryClient::new(&registry_url, utils::get_registry_timeout(args.timeout));
     let api_key = load_api_key()?;
 
     match args.command {
-        Command::List => list(&registry_url, &api_key).await,
-        Command::Create { name, save } => create(&registry_url, &api_key, name, save).await,
-        Command::Revoke { id } => revoke(&registry_url, &api_key, id).await,
+        Command::List => list(&registry, &api_key).await,
+        Command::Create { name, save } => create(&registry, &api_key, name, save).await,
+        Command::Revoke { id } => revoke(&registry, &api_key, id).await,
+        Command::Verify => verify(&registry, &api_key).await,
     }
 }
@@ -0,0 +1,246 @@
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser};
+use nargo_add::nargo_toml::ManifestDependency;
+use nargo_add::output::ErrorKind;
+use nargo_add::registry_client::{PackageInfo, RegistryClient};
+use nargo_add::{completions, info, nargo_toml, output, utils};
+use semver::Version;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "nargo-update")]
+#[command(
+    about = "Bump registry dependencies in Nargo.toml to their latest tag (use: nargo update)"
+)]
+#[command(version)]
+struct Args {
+    /// Only update this dependency
+    #[arg(long, short)]
+    package: Option<String>,
+
+    /// Show what would change without writing Nargo.toml
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Allow pre-release tags (e.g. 1.0.0-rc.1) when picking the latest tag from GitHub
+    #[arg(long)]
+    pre: bool,
+
+    /// Registry API URL (optional; defaults to NOIR_REGISTRY_URL, then the project's
+    /// [registry] setting, then the config file, then https://noir-registry.fly.dev/api)
+    #[arg(long)]
+    registry: Option<String>,
+
+    /// Path to Nargo.toml (optional, will search from current directory)
+    #[arg(long)]
+    manifest_path: Option<PathBuf>,
+
+    /// Timeout in seconds for registry requests (defaults to NOIR_REGISTRY_TIMEOUT env var)
+    #[arg(long)]
+    timeout: Option<u64>,
+
+    /// Ignore cached registry responses and refresh them (see NOIR_REGISTRY_CACHE_TTL)
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Suppress informational output (warnings and errors are still shown)
+    #[arg(long, short)]
+    quiet: bool,
+}
+
+/// `nargo add` writes `my-lib` as `my_lib`, so both spellings are tried
+fn registry_names(dep_key: &str) -> Vec<String> {
+    let mut names = vec![dep_key.to_string()];
+    let hyphenated = dep_key.replace('_', "-");
+    if hyphenated != dep_key {
+        names.push(hyphenated);
+    }
+    names
+}
+
+/// The registry package a git dependency was added from: one listed under the
+/// dependency's key whose repository is the one the dependency points at.
+/// Ok(None) if the registry doesn't know it.
+async fn find_registry_package(
+    registry: &RegistryClient,
+    dep: &ManifestDependency,
+    git: &str,
+) -> Result<Option<PackageInfo>> {
+    for name in registry_names(&dep.name) {
+        match registry.get_package(&name).await {
+            Ok(package)
+                if utils::normalize_git_url(&package.github_repository_url)
+                    == utils::normalize_git_url(git) =>
+            {
+                return Ok(Some(package));
+            }
+            Ok(_) => {}
+            Err(e) if e.kind == ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(None)
+}
+
+/// Whether `latest` should replace `current`. When both are semver it has to
+/// be newer, so a tag set by hand (e.g. a pre-release) is never downgraded;
+/// otherwise any different tag counts.
+fn is_update(latest: &str, current: Option<&str>) -> bool {
+    let Some(current) = current else {
+        return true;
+    };
+    let parse = |tag: &str| Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok();
+    match (parse(latest), parse(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => latest != current,
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    completions::handle(Args::command());
+    let args = Args::parse();
+    output::set_quiet(args.quiet);
+
+    let manifest_path = match args.manifest_path {
+        Some(path) => path,
+        None => {
+            let current_dir = std::env::current_dir().context("Failed to get current directory")?;
+            nargo_toml::find_nargo_toml(&current_dir)?
+        }
+    };
+
+    let dependencies = nargo_toml::read_dependencies(&manifest_path)?;
+    let selected: Vec<_> = match &args.package {
+        Some(name) => {
+            let key = name.replace('-', "_").to_lowercase();
+            let dep = dependencies
+                .iter()
+                .find(|d| d.name.replace('-', "_").to_lowercase() == key)
+                .with_context(|| {
+                    format!(
+                        "'{}' is not a dependency in {}",
+                        name,
+                        manifest_path.display()
+                    )
+                })?;
+            vec![dep]
+        }
+        None => dependencies.iter().collect(),
+    };
+
+    let (registry_url, _) = utils::get_project_registry_url(args.registry, Some(&manifest_path));
+    let registry = RegistryClient::new(&registry_url, utils::get_registry_timeout(args.timeout))
+        .no_cache(args.no_cache);
+    info!(
+        "Checking {} dependencies in {} against {}...",
+        selected.len(),
+        manifest_path.display(),
+        registry.registry_url()
+    );
+
+    let mut updated = 0;
+    let mut failed = 0;
+    for dep in selected {
+        let Some(git) = dep.git.as_deref() else {
+            info!("   {} - not a git dependency, skipped", dep.name);
+            continue;
+        };
+        if let Some(rev) = &dep.rev {
+            info!("   {} - pinned to commit {}, skipped", dep.name, rev);
+            continue;
+        }
+        let package = match find_registry_package(&registry, dep, git).await {
+            Ok(Some(package)) => package,
+            Ok(None) => {
+                info!("   {} - not in the registry, skipped", dep.name);
+                continue;
+            }
+            Err(e) => {
+                eprintln!("Warning: could not look up {}: {}", dep.name, e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        // Same order as `nargo add`: the registry's version, else the newest tag
+        let latest = match package.latest_version.clone() {
+            Some(version) if !package.latest_is_rev() => Some(version),
+            _ => {
+                registry
+                    .latest_tag(&package.name, &package.github_repository_url, args.pre)
+                    .await
+            }
+        };
+        let Some(latest) = latest else {
+            info!("   {} - no version tag found, left as is", dep.name);
+            continue;
+        };
+        let current = dep.tag.as_deref();
+        if !is_update(&latest, current) {
+            info!(
+                "   {} - up to date ({})",
+                dep.name,
+                current.unwrap_or_default()
+            );
+            continue;
+        }
+
+        info!(
+            "   {} {} -> {}",
+            dep.name,
+            current.unwrap_or("(no tag)"),
+            latest
+        );
+        if !args.dry_run {
+            nargo_toml::set_dependency_tag(&manifest_path, &dep.name, &latest)?;
+        }
+        updated += 1;
+    }
+
+    if updated == 0 {
+        info!("All registry dependencies are up to date.");
+    } else if args.dry_run {
+        info!(
+            "{} dependencies would be updated. Run without --dry-run to write {}.",
+            updated,
+            manifest_path.display()
+        );
+    } else {
+        nargo_toml::validate_nargo_toml(&manifest_path)?;
+        info!(
+            "Updated {} dependencies in {}",
+            updated,
+            manifest_path.display()
+        );
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} dependencies could not be checked", failed);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn underscored_keys_also_look_up_the_hyphenated_name() {
+        assert_eq!(registry_names("my_lib"), ["my_lib", "my-lib"]);
+        assert_eq!(registry_names("bignum"), ["bignum"]);
+    }
+
+    #[test]
+    fn only_newer_semver_tags_replace_the_current_one() {
+        assert!(is_update("v0.6.0", Some("v0.5.0")));
+        assert!(is_update("v0.6.0", Some("0.5.0")));
+        assert!(is_update("v0.6.0", None));
+        assert!(!is_update("v0.6.0", Some("v0.6.0")));
+        // A hand-picked newer pre-release isn't downgraded
+        assert!(!is_update("v0.6.0", Some("v0.7.0-rc.1")));
+        // Tags that aren't semver can't be ordered; any change counts
+        assert!(is_update("release-2", Some("release-1")));
+        assert!(!is_update("release-1", Some("release-1")));
+    }
+}
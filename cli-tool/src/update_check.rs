@@ -0,0 +1,141 @@
+//! Once-a-day check for a newer release of the CLI.
+//!
+//! The answer is cached next to the config file, so most invocations don't
+//! touch the network. Set `update_check = false` in the config to turn it off.
+
+use crate::config::Config;
+use crate::github::{github_slug_from_url, latest_semver_tag};
+use crate::http;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long a check is good for
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Nothing waits on the answer, so give up quickly
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// When the last check ran, saved in `update_check.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CheckCache {
+    /// Seconds since the Unix epoch
+    checked_at: u64,
+}
+
+#[derive(Deserialize)]
+struct GitHubRelease {
+    tag_name: String,
+}
+
+fn cache_path() -> Option<PathBuf> {
+    Some(
+        Config::config_path()
+            .ok()?
+            .with_file_name("update_check.json"),
+    )
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Whether a check last run at `checked_at` has expired by `now`
+fn is_due(checked_at: u64, now: u64) -> bool {
+    now.saturating_sub(checked_at) >= CHECK_INTERVAL.as_secs()
+}
+
+/// The newest release among `tags` if it is newer than `current`
+fn newer_release<'a>(current: &str, tags: impl IntoIterator<Item = &'a str>) -> Option<Version> {
+    let current = Version::parse(current).ok()?;
+    let tag = latest_semver_tag(tags, !current.pre.is_empty())?;
+    let latest = Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()?;
+    (latest > current).then_some(latest)
+}
+
+/// Latest release tags of the CLI's GitHub repository
+async fn fetch_release_tags() -> Option<Vec<String>> {
+    let slug = github_slug_from_url(env!("CARGO_PKG_REPOSITORY"))?;
+    let response = http::build_client()
+        .get(format!("https://api.github.com/repos/{}/releases", slug))
+        .header("Accept", "application/vnd.github+json")
+        .timeout(CHECK_TIMEOUT)
+        .send()
+        .await
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    let releases: Vec<GitHubRelease> = response.json().await.ok()?;
+    Some(releases.into_iter().map(|r| r.tag_name).collect())
+}
+
+/// Prints a one-line notice to stderr when a newer CLI has been released.
+/// Runs at most once a day, unless disabled in the config; any failure is
+/// silently ignored.
+pub async fn notify_if_outdated() {
+    if !Config::load().is_ok_and(|cfg| cfg.update_check()) {
+        return;
+    }
+    let Some(path) = cache_path() else {
+        return;
+    };
+    let cache: CheckCache = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let now = now_secs();
+    if !is_due(cache.checked_at, now) {
+        return;
+    }
+
+    // Record the attempt first, so being offline doesn't mean retrying every run
+    if let Ok(content) = serde_json::to_string(&CheckCache { checked_at: now }) {
+        let _ = fs::write(&path, content);
+    }
+
+    let Some(tags) = fetch_release_tags().await else {
+        return;
+    };
+    if let Some(latest) = newer_release(env!("CARGO_PKG_VERSION"), tags.iter().map(String::as_str))
+    {
+        eprintln!(
+            "A newer nargo-add is available: {} (you have {}). Update with: cargo install nargo-add",
+            latest,
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_at_most_once_a_day() {
+        let day = CHECK_INTERVAL.as_secs();
+        assert!(is_due(0, 1_000_000));
+        assert!(!is_due(1_000_000, 1_000_000 + day - 1));
+        assert!(is_due(1_000_000, 1_000_000 + day));
+        // A clock that went backwards doesn't trigger a check
+        assert!(!is_due(1_000_000, 999_000));
+    }
+
+    #[test]
+    fn only_newer_stable_releases_are_reported() {
+        let tags = ["v0.3.0-rc.1", "v0.2.1", "nightly"];
+        assert_eq!(newer_release("0.2.0", tags), Version::parse("0.2.1").ok());
+        assert_eq!(newer_release("0.2.1", tags), None);
+        assert_eq!(newer_release("0.3.0", tags), None);
+        // Pre-release users hear about newer pre-releases
+        assert_eq!(
+            newer_release("0.3.0-beta.1", tags),
+            Version::parse("0.3.0-rc.1").ok()
+        );
+    }
+}
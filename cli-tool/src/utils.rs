@@ -1,6 +1,123 @@
-/// Gets the registry URL from args, env var, or default
-pub fn get_registry_url(args_registry: Option<String>) -> String {
-    args_registry
-        .or_else(|| std::env::var("NOIR_REGISTRY_URL").ok())
-        .unwrap_or_else(|| "https://noir-registry.fly.dev/api".to_string())
+/// Registry used when no flag, env var or config entry names one
+pub const DEFAULT_REGISTRY_URL: &str = "https://noir-registry.fly.dev/api";
+
+/// Where the effective registry URL came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrySource {
+    Flag,
+    Env,
+    /// `[registry]` in Nargo.toml, or `.noir-registry.toml` next to it
+    Project,
+    Config,
+    Default,
+}
+
+impl std::fmt::Display for RegistrySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RegistrySource::Flag => "--registry flag",
+            RegistrySource::Env => "NOIR_REGISTRY_URL env var",
+            RegistrySource::Project => "project (Nargo.toml or .noir-registry.toml)",
+            RegistrySource::Config => "config file",
+            RegistrySource::Default => "default",
+        })
+    }
+}
+
+/// Gets the registry URL from args, env var, config file, or default, in that
+/// order, along with which of them it came from
+pub fn get_registry_url(args_registry: Option<String>) -> (String, RegistrySource) {
+    get_project_registry_url(args_registry, None)
+}
+
+/// Like [`get_registry_url`], but a registry pinned by the project whose
+/// manifest is given ranks between the env var and the config file. An
+/// unreadable project setting is warned about and skipped.
+pub fn get_project_registry_url(
+    args_registry: Option<String>,
+    manifest_path: Option<&std::path::Path>,
+) -> (String, RegistrySource) {
+    if let Some(url) = args_registry {
+        return (url, RegistrySource::Flag);
+    }
+    if let Ok(url) = std::env::var("NOIR_REGISTRY_URL") {
+        return (url, RegistrySource::Env);
+    }
+    if let Some(manifest_path) = manifest_path {
+        match crate::nargo_toml::read_project_registry(manifest_path) {
+            Ok(Some(url)) => return (url, RegistrySource::Project),
+            Ok(None) => {}
+            Err(e) => eprintln!("Warning: Ignoring the project's registry setting: {:#}", e),
+        }
+    }
+    if let Some(url) = crate::config::Config::load()
+        .ok()
+        .and_then(|cfg| cfg.registry_url)
+    {
+        return (url, RegistrySource::Config);
+    }
+    (DEFAULT_REGISTRY_URL.to_string(), RegistrySource::Default)
+}
+
+/// Gets the registry request timeout from args or the NOIR_REGISTRY_TIMEOUT env var (seconds).
+/// Returns None when neither is set so each call keeps its own default.
+pub fn get_registry_timeout(args_timeout: Option<u64>) -> Option<std::time::Duration> {
+    args_timeout
+        .or_else(|| {
+            std::env::var("NOIR_REGISTRY_TIMEOUT")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+        })
+        .map(std::time::Duration::from_secs)
+}
+
+/// Registry API major version this CLI was built against
+pub const SUPPORTED_API_VERSION: &str = "1";
+
+/// Warns (once per process) if the registry reports an API major version other
+/// than the one this CLI understands. Registries that predate the header are
+/// assumed compatible.
+pub fn check_api_version(response: &reqwest::Response) {
+    static WARNED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+    let Some(version) = response
+        .headers()
+        .get("X-Registry-Api-Version")
+        .and_then(|v| v.to_str().ok())
+    else {
+        return;
+    };
+    if version != SUPPORTED_API_VERSION && !WARNED.swap(true, std::sync::atomic::Ordering::Relaxed)
+    {
+        eprintln!(
+            "Warning: registry API version {} differs from the version this CLI supports ({}).",
+            version, SUPPORTED_API_VERSION
+        );
+        eprintln!("   Some commands may not work; consider upgrading: cargo install nargo-add");
+    }
+}
+
+/// Same normalization the registry applies to indexed git URLs: lowercase,
+/// without a trailing slash or `.git`
+pub fn normalize_git_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    url.strip_suffix(".git").unwrap_or(url).to_lowercase()
+}
+
+/// Message for a registry 404, with "did you mean" hints when the registry
+/// sent suggestions
+pub fn package_not_found_message(
+    package_name: &str,
+    registry_url: &str,
+    suggestions: &[String],
+) -> String {
+    let tip = if suggestions.is_empty() {
+        "Tip: Check the package name and ensure the registry is up to date.".to_string()
+    } else {
+        format!("Did you mean {}?", suggestions.join(", "))
+    };
+    format!(
+        "Package '{}' not found in registry.\nRegistry URL: {}\n{}",
+        package_name, registry_url, tip
+    )
 }
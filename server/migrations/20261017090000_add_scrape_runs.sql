@@ -0,0 +1,15 @@
+-- Tracks scrape-and-enrich runs triggered through POST /api/admin/scrape.
+-- Operators poll GET /api/admin/scrape/:id to follow progress.
+
+CREATE TABLE IF NOT EXISTS scrape_runs (
+    id                  SERIAL PRIMARY KEY,
+    status              TEXT        NOT NULL DEFAULT 'running'
+                                    CHECK (status IN ('running', 'completed', 'failed')),
+    packages_found      INTEGER     NOT NULL DEFAULT 0,
+    packages_enriched   INTEGER     NOT NULL DEFAULT 0,
+    packages_inserted   INTEGER     NOT NULL DEFAULT 0,
+    packages_failed     INTEGER     NOT NULL DEFAULT 0,
+    error               TEXT,
+    started_at          TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at         TIMESTAMPTZ
+);
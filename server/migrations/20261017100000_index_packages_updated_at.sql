@@ -0,0 +1,2 @@
+-- Supports GET /api/packages?since=... for incremental mirroring
+CREATE INDEX IF NOT EXISTS idx_packages_updated_at ON packages(updated_at);
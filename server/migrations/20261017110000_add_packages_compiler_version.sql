@@ -0,0 +1,2 @@
+-- compiler_version requirement read from each package's Nargo.toml during enrichment
+ALTER TABLE packages ADD COLUMN IF NOT EXISTS compiler_version VARCHAR(100);
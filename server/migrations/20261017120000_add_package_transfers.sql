@@ -0,0 +1,18 @@
+-- Two-step package ownership transfers: the current owner opens a pending
+-- transfer via POST /api/packages/:name/transfer, and it only takes effect once
+-- the recipient accepts it via POST /api/packages/:name/transfer/accept.
+
+CREATE TABLE IF NOT EXISTS package_transfers (
+    id            SERIAL PRIMARY KEY,
+    package_id    INTEGER     NOT NULL REFERENCES packages(id) ON DELETE CASCADE,
+    from_user_id  INTEGER     NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    to_user_id    INTEGER     NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status        TEXT        NOT NULL DEFAULT 'pending'
+                              CHECK (status IN ('pending', 'accepted', 'cancelled')),
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at   TIMESTAMPTZ
+);
+
+-- At most one pending transfer per package
+CREATE UNIQUE INDEX IF NOT EXISTS idx_package_transfers_pending
+    ON package_transfers(package_id) WHERE status = 'pending';
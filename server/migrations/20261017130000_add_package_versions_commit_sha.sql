@@ -0,0 +1,3 @@
+-- Commit SHA each published version's git tag resolved to at publish time, so
+-- consumers can check they fetched the exact code that was published.
+ALTER TABLE package_versions ADD COLUMN IF NOT EXISTS commit_sha TEXT;
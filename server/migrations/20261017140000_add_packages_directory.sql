@@ -0,0 +1,3 @@
+-- Path of the package inside its repository, for libraries that live in a
+-- monorepo subdirectory (github.com/org/repo/tree/<branch>/<path>).
+ALTER TABLE packages ADD COLUMN IF NOT EXISTS directory TEXT;
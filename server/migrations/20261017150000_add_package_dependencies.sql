@@ -0,0 +1,15 @@
+-- Direct dependencies declared in each package's Nargo.toml, indexed during
+-- enrichment so GET /api/packages/:name/dependents can answer the reverse
+-- question. git_url is stored normalized (lowercase, no ".git" or trailing
+-- slash); path dependencies have no git_url.
+
+CREATE TABLE IF NOT EXISTS package_dependencies (
+    package_id       INTEGER NOT NULL REFERENCES packages(id) ON DELETE CASCADE,
+    dependency_name  TEXT    NOT NULL,
+    git_url          TEXT,
+    directory        TEXT,
+    PRIMARY KEY (package_id, dependency_name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_package_dependencies_git_url
+    ON package_dependencies(git_url);
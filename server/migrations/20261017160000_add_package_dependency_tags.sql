@@ -0,0 +1,5 @@
+-- The tag each indexed dependency is pinned to, so clients can spot two
+-- packages requiring different versions of the same dependency. NULL for
+-- untagged and path dependencies, and until the package is next enriched.
+
+ALTER TABLE package_dependencies ADD COLUMN IF NOT EXISTS tag TEXT;
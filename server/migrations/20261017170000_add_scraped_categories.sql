@@ -0,0 +1,17 @@
+-- Categories taken from the awesome-noir README headings during scrapes
+-- (the hand-seeded tables dropped in 20260722090459 were never populated).
+-- Each package sits under one heading, so it has at most one category.
+
+CREATE TABLE IF NOT EXISTS categories (
+    id   SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    slug TEXT UNIQUE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS package_categories (
+    package_id  INTEGER PRIMARY KEY REFERENCES packages(id) ON DELETE CASCADE,
+    category_id INTEGER NOT NULL REFERENCES categories(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_package_categories_category
+    ON package_categories(category_id);
@@ -0,0 +1,9 @@
+-- Curated "featured" packages for the homepage, set by admins. NULL means not
+-- featured; featured packages are listed by ascending rank. Organic listing
+-- and search ordering ignore this column.
+
+ALTER TABLE packages
+    ADD COLUMN IF NOT EXISTS featured_rank INTEGER;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_packages_featured_rank
+    ON packages(featured_rank) WHERE featured_rank IS NOT NULL;
@@ -0,0 +1,7 @@
+-- When the GitHub metadata stored for a package was fetched. A scrape only
+-- overwrites a package when its own fetch is at least as recent, so a slow or
+-- out-of-order run can't regress fresher data. NULL for rows written before
+-- this column existed; any fetch replaces those.
+
+ALTER TABLE packages
+    ADD COLUMN IF NOT EXISTS metadata_fetched_at TIMESTAMPTZ;
@@ -0,0 +1,8 @@
+-- Optional per-user webhook, POSTed to when one of the user's packages is
+-- published. The secret signs each payload (HMAC-SHA256) so the receiver can
+-- check it came from the registry; unlike API tokens it has to be kept in the
+-- clear to sign with.
+
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS webhook_url TEXT,
+    ADD COLUMN IF NOT EXISTS webhook_secret TEXT;
@@ -0,0 +1,7 @@
+-- Owner-set deprecation notice for a whole package (e.g. "use noir-bignum
+-- instead"). Unlike yanking, the package stays installable; clients show the
+-- message as a warning.
+
+ALTER TABLE packages
+    ADD COLUMN IF NOT EXISTS deprecated BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS deprecation_message TEXT;
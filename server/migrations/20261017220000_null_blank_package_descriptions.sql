@@ -0,0 +1,4 @@
+-- Packages store a missing description as NULL; earlier writes kept empty
+-- or whitespace-only ones as they were.
+
+UPDATE packages SET description = NULL WHERE btrim(description) = '';
@@ -0,0 +1,11 @@
+-- Downloads per package per day (UTC), alongside the running total in
+-- packages.total_downloads. Both are bumped in one statement, and the
+-- (package_id, day) key lets concurrent pings for the same day add up
+-- through ON CONFLICT instead of racing to insert the row.
+
+CREATE TABLE IF NOT EXISTS package_daily_downloads (
+    package_id INTEGER NOT NULL REFERENCES packages(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (package_id, day)
+);
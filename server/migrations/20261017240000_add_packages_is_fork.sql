@@ -0,0 +1,6 @@
+-- Whether a package's GitHub repository is a fork, from GitHub's `fork` flag.
+-- Set by the scraper and on publish, where forks need explicit confirmation
+-- (or are refused when PUBLISH_REJECT_FORKS is set).
+
+ALTER TABLE packages
+    ADD COLUMN IF NOT EXISTS is_fork BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,6 @@
+-- package_versions.downloads (from the initial schema) now counts downloads of
+-- each version, from `POST /api/packages/:name/download?version=`. Make it a
+-- plain counter so increments never meet a NULL.
+
+UPDATE package_versions SET downloads = 0 WHERE downloads IS NULL;
+ALTER TABLE package_versions ALTER COLUMN downloads SET NOT NULL;
@@ -0,0 +1,6 @@
+-- Public email from the user's GitHub profile, captured at login so
+-- moderators can reach package owners about abuse or DMCA reports.
+-- NULL when the profile has no public email. Only exposed to admins.
+
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS email TEXT;
@@ -0,0 +1,19 @@
+-- Package names nobody may publish under: reserved for the toolchain, or
+-- refused for trademark or abuse reasons. Stored normalized (lowercase, `-` as
+-- `_`) so every spelling of a name is covered. Admins manage the list through
+-- /api/admin/blocked-names.
+
+CREATE TABLE IF NOT EXISTS blocked_names (
+    name TEXT PRIMARY KEY,
+    reason TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO blocked_names (name, reason) VALUES
+    ('std', 'Reserved for the Noir standard library'),
+    ('core', 'Reserved for the Noir standard library'),
+    ('noir', 'Reserved for the Noir project'),
+    ('noirc', 'Reserved for the Noir compiler'),
+    ('nargo', 'Reserved for the Noir package manager'),
+    ('aztec', 'Reserved for the Aztec project')
+ON CONFLICT (name) DO NOTHING;
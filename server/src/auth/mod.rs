@@ -1,3 +1,5 @@
+use crate::http;
+use crate::webhook::Webhook;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -28,6 +30,19 @@ pub struct GithubUser {
     pub id: i32,
     pub login: String,
     pub avatar_url: String,
+    /// Public profile email; null when the user keeps it private
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+impl GithubUser {
+    /// The public email worth storing, if any
+    fn public_email(&self) -> Option<&str> {
+        self.email
+            .as_deref()
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+    }
 }
 
 /// Generate a random 32-character API token using the OS CSPRNG.
@@ -81,11 +96,10 @@ pub async fn get_or_create_user_from_github(
     pool: &PgPool,
     github_token: &str,
 ) -> Result<(User, Option<String>)> {
-    let client = reqwest::Client::new();
+    let client = http::build_client();
     let github_user: GithubUser = client
         .get("https://api.github.com/user")
         .header("Authorization", format!("Bearer {}", github_token))
-        .header("User-Agent", "noir-registry")
         .header("Accept", "application/vnd.github.v3+json")
         .send()
         .await?
@@ -103,26 +117,60 @@ pub async fn get_or_create_user_from_github(
     .await?;
 
     match existing {
-        Some(r) => Ok((row_to_user(r)?, None)),
+        Some(r) => {
+            let user = row_to_user(r)?;
+            // Kept current, so an email made private again is dropped
+            sqlx::query("UPDATE users SET email = $1 WHERE id = $2")
+                .bind(github_user.public_email())
+                .bind(user.id)
+                .persistent(false)
+                .execute(pool)
+                .await?;
+            Ok((user, None))
+        }
         None => {
-            let user_row = sqlx::query(
-                "INSERT INTO users (github_id, github_username, github_avatar_url)
-                 VALUES ($1, $2, $3)
-                 RETURNING id, github_id, github_username, github_avatar_url, created_at, updated_at",
-            )
-            .bind(github_user.id)
-            .bind(&github_user.login)
-            .bind(&github_user.avatar_url)
-            .persistent(false)
-            .fetch_one(pool)
-            .await?;
-            let user = row_to_user(user_row)?;
+            let user = create_user(pool, &github_user).await?;
             let (_token, raw) = create_token_for_user(pool, user.id, "default").await?;
             Ok((user, Some(raw)))
         }
     }
 }
 
+/// Registers a GitHub account as a new user, with its public email if it has one
+pub async fn create_user(pool: &PgPool, github_user: &GithubUser) -> Result<User> {
+    let row = sqlx::query(
+        "INSERT INTO users (github_id, github_username, github_avatar_url, email)
+         VALUES ($1, $2, $3, $4)
+         RETURNING id, github_id, github_username, github_avatar_url, created_at, updated_at",
+    )
+    .bind(github_user.id)
+    .bind(&github_user.login)
+    .bind(&github_user.avatar_url)
+    .bind(github_user.public_email())
+    .persistent(false)
+    .fetch_one(pool)
+    .await?;
+    Ok(row_to_user(row)?)
+}
+
+/// Look up a registered user by GitHub username (case-insensitive, as GitHub is).
+pub async fn get_user_by_github_username(pool: &PgPool, username: &str) -> Result<Option<User>> {
+    let row = sqlx::query(
+        "SELECT id, github_id, github_username, github_avatar_url, created_at, updated_at
+         FROM users WHERE LOWER(github_username) = LOWER($1)
+         ORDER BY id LIMIT 1",
+    )
+    .bind(username)
+    .persistent(false)
+    .fetch_optional(pool)
+    .await?;
+
+    match row {
+        Some(r) => Ok(Some(row_to_user(r)?)),
+        None => Ok(None),
+    }
+}
+
 /// Validate a raw token by hashing it and looking up an unrevoked matching row.
 /// Returns the owning user, or None if the token is unknown or revoked.
 pub async fn validate_api_key(pool: &PgPool, raw_token: &str) -> Result<Option<User>> {
@@ -204,3 +252,38 @@ pub async fn revoke_token(pool: &PgPool, user_id: i32, token_id: i32) -> Result<
 
     Ok(result.rows_affected() > 0)
 }
+
+/// Sets or clears (`None`) a user's webhook. Setting one generates a fresh
+/// signing secret, which is returned so it can be shown to the user.
+pub async fn set_webhook(pool: &PgPool, user_id: i32, url: Option<&str>) -> Result<Option<String>> {
+    let secret = url.map(|_| generate_api_key());
+    sqlx::query(
+        "UPDATE users SET webhook_url = $2, webhook_secret = $3, updated_at = NOW() WHERE id = $1",
+    )
+    .bind(user_id)
+    .bind(url)
+    .bind(&secret)
+    .persistent(false)
+    .execute(pool)
+    .await?;
+    Ok(secret)
+}
+
+/// A user's webhook, if they configured one
+pub async fn get_webhook(pool: &PgPool, user_id: i32) -> Result<Option<Webhook>> {
+    let row = sqlx::query(
+        "SELECT webhook_url, webhook_secret FROM users
+         WHERE id = $1 AND webhook_url IS NOT NULL AND webhook_secret IS NOT NULL",
+    )
+    .bind(user_id)
+    .persistent(false)
+    .fetch_optional(pool)
+    .await?;
+    row.map(|r| {
+        Ok(Webhook {
+            url: r.try_get("webhook_url")?,
+            secret: r.try_get("webhook_secret")?,
+        })
+    })
+    .transpose()
+}
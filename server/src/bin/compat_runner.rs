@@ -1,6 +1,5 @@
 use anyhow::Result;
 use noir_registry_server::db;
-use noir_registry_server::package_storage::escape_sql_string;
 use sqlx::Row;
 use std::path::PathBuf;
 use std::time::Duration;
@@ -155,25 +154,21 @@ async fn record_result(
         CheckOutcome::Error(s) => ("error", Some(s.as_str())),
     };
 
-    let error_sql = match error_snippet {
-        Some(s) => format!("'{}'", escape_sql_string(s)),
-        None => "NULL".to_string(),
-    };
-
-    let sql = format!(
+    sqlx::query(
         r#"INSERT INTO package_compat_results (package_id, nargo_version, status, error_snippet)
-        VALUES ({}, '{}', '{}', {})
+        VALUES ($1, $2, $3, $4)
         ON CONFLICT (package_id, nargo_version) DO UPDATE SET
             checked_at = NOW(),
             status = EXCLUDED.status,
             error_snippet = EXCLUDED.error_snippet"#,
-        pkg.id,
-        escape_sql_string(nargo_version),
-        status,
-        error_sql,
-    );
-
-    sqlx::raw_sql(&sql).execute(pool).await?;
+    )
+    .bind(pkg.id)
+    .bind(nargo_version)
+    .bind(status)
+    .bind(error_snippet)
+    .persistent(false)
+    .execute(pool)
+    .await?;
     Ok(())
 }
 
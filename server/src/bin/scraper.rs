@@ -1,13 +1,37 @@
 use anyhow::Result;
 use noir_registry_server::db;
-use noir_registry_server::github_metadata::enrich_package;
-use noir_registry_server::models::Package;
+use noir_registry_server::github_metadata::{
+    GithubLimiter, ResponseCache, enrich_package, with_secondary_limit_backoff,
+};
 use noir_registry_server::package_storage::insert_package;
-use regex::Regex;
+use noir_registry_server::scraper::{
+    README_URL, ScrapeCheckpoint, ScrapeFailure, ScrapePhase, failure_summary, fetch_readme,
+    parse_packages_in_section, write_failures,
+};
+use std::path::PathBuf;
+
+/// Where progress is saved unless `--checkpoint` names another file
+const DEFAULT_CHECKPOINT: &str = "scrape-checkpoint.json";
 
 #[tokio::main]
 async fn main() -> Result<()> {
     println!("Starting the Noir package scraper...");
+    // --no-cache skips the on-disk GitHub response cache (GITHUB_CACHE_DIR)
+    let use_cache = !std::env::args().any(|arg| arg == "--no-cache");
+    // --section "Libraries" only takes links under that README heading
+    let section = std::env::args().skip_while(|arg| arg != "--section").nth(1);
+    // --failures-out failures.json also writes the failed packages to a file
+    let failures_out = std::env::args()
+        .skip_while(|arg| arg != "--failures-out")
+        .nth(1);
+    // --resume skips the packages a previous run's checkpoint marks done
+    let resume = std::env::args().any(|arg| arg == "--resume");
+    let checkpoint_path = PathBuf::from(
+        std::env::args()
+            .skip_while(|arg| arg != "--checkpoint")
+            .nth(1)
+            .unwrap_or_else(|| DEFAULT_CHECKPOINT.to_string()),
+    );
     // Load all env variables
     dotenvy::dotenv().ok();
     let github_token = std::env::var("GITHUB_TOKEN").ok();
@@ -24,66 +48,124 @@ async fn main() -> Result<()> {
 
     // Fetch the awesome-noir README
     println!("Fetching awesome-noir README...");
-    let readme_url = "https://raw.githubusercontent.com/noir-lang/awesome-noir/main/README.md";
-    let readme_content = fetch_readme(readme_url).await?;
+    let readme_content = fetch_readme(README_URL).await?;
     println!("✅ Fetched README ({} bytes)", readme_content.len());
     // Parse the markdown to find libraries
     println!("Parsing packages for the README....");
-    let packages = parse_packages(&readme_content)?;
+    if let Some(section) = &section {
+        println!("   Only the \"{}\" section", section);
+    }
+    let packages = parse_packages_in_section(&readme_content, section.as_deref())?;
     println!("✅ Found {} packages", packages.len());
 
+    let mut checkpoint = if resume {
+        match ScrapeCheckpoint::load(&checkpoint_path)? {
+            Some(checkpoint) => {
+                println!(
+                    "⏩ Resuming the run started {}: {} packages already done",
+                    checkpoint.started_at.to_rfc3339(),
+                    checkpoint.done.len()
+                );
+                checkpoint
+            }
+            None => {
+                println!(
+                    "No checkpoint at {}, starting from the beginning",
+                    checkpoint_path.display()
+                );
+                ScrapeCheckpoint::new()
+            }
+        }
+    } else {
+        ScrapeCheckpoint::new()
+    };
+
+    checkpoint.save(&checkpoint_path)?;
+
     // Create HTTP client for GitHub API calls
-    let client = reqwest::Client::new();
-    println!("\n📡 Fetching GitHub metadata...");
-    let mut enriched_packages = Vec::new();
+    let client = noir_registry_server::http::build_client();
+    let cache = use_cache.then(ResponseCache::from_env);
+    let limiter = GithubLimiter::from_env();
+    // Each package is inserted as soon as it's fetched and then checkpointed,
+    // so a run that dies midway keeps everything done so far
+    println!("\n📡 Fetching GitHub metadata and inserting packages...");
+    let mut enriched_count = 0;
+    let mut inserted_count = 0;
+    let mut skipped_count = 0;
+    let mut failures = Vec::new();
 
     for (i, pkg) in packages.iter().enumerate() {
+        if checkpoint.is_done(&pkg.name) {
+            skipped_count += 1;
+            continue;
+        }
         print!("  [{}/{}] Fetching {}... ", i + 1, packages.len(), pkg.name);
 
-        match enrich_package(&client, pkg, github_token.as_deref()).await {
+        let enriched = with_secondary_limit_backoff(|| {
+            enrich_package(
+                &client,
+                &limiter,
+                pkg,
+                github_token.as_deref(),
+                cache.as_ref(),
+            )
+        })
+        .await;
+        match enriched {
             Ok(enriched) => {
                 println!("✅ ({} stars)", enriched.stars);
-                enriched_packages.push(enriched);
+                enriched_count += 1;
+                match insert_package(&pool, &enriched).await {
+                    Ok(true) => {
+                        inserted_count += 1;
+                        checkpoint.mark_done(&pkg.name);
+                    }
+                    Ok(false) => {
+                        eprintln!("⏭️  Skipped {}: a newer fetch already updated it", pkg.name);
+                        checkpoint.mark_done(&pkg.name);
+                    }
+                    Err(e) => {
+                        eprintln!("❌ Failed to insert {}: {}", pkg.name, e);
+                        failures.push(ScrapeFailure::new(&pkg.name, ScrapePhase::Insert, &e));
+                    }
+                }
+                checkpoint.save(&checkpoint_path)?;
             }
             Err(e) => {
                 println!("❌ Error: {}", e);
+                failures.push(ScrapeFailure::new(&pkg.name, ScrapePhase::Enrich, &e));
             }
         }
 
         // Be nice to GitHub API - add small delay
         tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
     }
-    println!("\n✅ Enriched {} packages", enriched_packages.len());
-    // Print sample enriched packages
-    println!("\n📦 Sample enriched packages:");
-    for pkg in enriched_packages.iter().take(3) {
+    if skipped_count > 0 {
         println!(
-            "  • {} by @{} ({} ⭐)",
-            pkg.name, pkg.owner_username, pkg.stars
+            "\n⏩ Skipped {} packages already done by the resumed run",
+            skipped_count
         );
     }
-
-    // Insert to the db
-    println!("\n💾 Inserting packages into database...");
-    let mut inserted_count = 0;
-    let mut failed_count = 0;
-
-    for pkg in enriched_packages.iter() {
-        match insert_package(&pool, pkg).await {
-            Ok(_) => {
-                inserted_count += 1;
-                print!(".");
-            }
-            Err(e) => {
-                failed_count += 1;
-                eprintln!("\n❌ Failed to insert {}: {}", pkg.name, e);
-            }
-        }
-    }
-
+    println!("\n✅ Enriched {} packages", enriched_count);
     println!("\n✅ Inserted {} packages into database", inserted_count);
-    if failed_count > 0 {
-        println!("⚠️  {} packages failed to insert", failed_count);
+    if !failures.is_empty() {
+        println!("\n⚠️  {} packages failed:", failures.len());
+        println!("{}", failure_summary(&failures));
+    }
+    if let Some(path) = &failures_out {
+        write_failures(std::path::Path::new(path), &failures)?;
+        println!("📝 Wrote {} failures to {}", failures.len(), path);
+    }
+    if failures.is_empty() {
+        // Nothing left to resume
+        if checkpoint_path.exists() {
+            std::fs::remove_file(&checkpoint_path)?;
+        }
+    } else {
+        println!(
+            "⏸️  Progress saved to {}; run again with --resume to retry the failed packages",
+            checkpoint_path.display()
+        );
     }
 
     //close connection
@@ -92,53 +174,3 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
-
-/// This function should be fetching the raw readme content from github
-async fn fetch_readme(url: &str) -> Result<String> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get(url)
-        .header("User-Agent", "noir-registry-scraper")
-        .send()
-        .await?;
-    let content = response.text().await?;
-    Ok(content)
-}
-
-/// Parses the README to extract package information
-fn parse_packages(readme: &str) -> Result<Vec<Package>> {
-    let mut packages = Vec::new();
-    // Regex pattern to match: - [Name](url) - description
-    // Pattern explanation:
-    // - \[([^\]]+)\]  -> matches [Name] and captures "Name"
-    // - \(([^)]+)\)   -> matches (url) and captures "url"
-    // - \s*-\s*(.+)   -> matches " - description" and captures "description"
-    let re = Regex::new(r"-\s*\[([^\]]+)\]\(([^)]+)\)\s*-\s*(.+)")?;
-    for line in readme.lines() {
-        if let Some(caps) = re.captures(line) {
-            let name = caps
-                .get(1)
-                .map(|m| m.as_str().trim().to_string())
-                .unwrap_or_default();
-            let url = caps
-                .get(2)
-                .map(|m| m.as_str().trim().to_string())
-                .unwrap_or_default();
-            let description = caps
-                .get(3)
-                .map(|m| m.as_str().trim().to_string())
-                .unwrap_or_default();
-
-            // Only include if it's a GitHub URL
-            if url.contains("github.com") {
-                packages.push(Package {
-                    name,
-                    github_url: url,
-                    description,
-                });
-            }
-        }
-    }
-
-    Ok(packages)
-}
@@ -0,0 +1,271 @@
+//! Reading and checking the `compiler_version` requirement packages declare in
+//! their `Nargo.toml`.
+//!
+//! Requirements use Cargo-style comparators (`>=0.31.0`, `^1.0.0-beta.2`,
+//! `~0.36`, `>=0.30, <0.40`). A bare version is treated as a caret requirement,
+//! matching how nargo reads the field. Pre-release tags are ordered normally
+//! rather than excluded, since most of the Noir ecosystem targets 1.0 betas.
+
+use semver::{BuildMetadata, Comparator, Op, Prerelease, Version, VersionReq};
+
+/// Extracts `compiler_version` from a Nargo.toml, looking under `[package]`
+/// first and falling back to a top-level key.
+pub fn parse_compiler_version(nargo_toml: &str) -> Option<String> {
+    let doc: toml::Table = nargo_toml.parse().ok()?;
+    let value = doc
+        .get("package")
+        .and_then(|p| p.get("compiler_version"))
+        .or_else(|| doc.get("compiler_version"))?;
+    let requirement = value.as_str()?.trim();
+    if requirement.is_empty() {
+        None
+    } else {
+        Some(requirement.to_string())
+    }
+}
+
+/// Whether `version` is a full version number such as `1.0.0-beta.3`
+pub fn is_valid_version(version: &str) -> bool {
+    parse_version(version).is_some()
+}
+
+/// Returns whether `version` satisfies `requirement`, or None if either can't
+/// be parsed.
+pub fn requirement_satisfied(requirement: &str, version: &str) -> Option<bool> {
+    let mut version = parse_version(version)?;
+    version.build = BuildMetadata::EMPTY;
+    let requirement = VersionReq::parse(requirement.trim()).ok()?;
+    Some(
+        requirement
+            .comparators
+            .iter()
+            .all(|comparator| comparator_matches(comparator, &version)),
+    )
+}
+
+/// Parses `1.2.3`, `v1.2.3` or `1.0.0-beta.3`
+fn parse_version(s: &str) -> Option<Version> {
+    let s = s.trim();
+    Version::parse(s.strip_prefix('v').unwrap_or(s)).ok()
+}
+
+/// Like [`Comparator::matches`], minus semver's rule that a pre-release only
+/// matches comparators naming the same release, so pre-releases just sort
+/// below their release.
+fn comparator_matches(comparator: &Comparator, version: &Version) -> bool {
+    let base = Version {
+        major: comparator.major,
+        minor: comparator.minor.unwrap_or(0),
+        patch: comparator.patch.unwrap_or(0),
+        pre: comparator.pre.clone(),
+        build: BuildMetadata::EMPTY,
+    };
+    let parts = match (comparator.minor, comparator.patch) {
+        (None, _) => 1,
+        (Some(_), None) => 2,
+        (Some(_), Some(_)) => 3,
+    };
+    let exact = parts == 3;
+    // Below every release sharing the given component; nothing lies above
+    // the bump when it would overflow
+    let below_bump = |component| bump(&base, component).is_none_or(|upper| *version < upper);
+
+    match comparator.op {
+        Op::GreaterEq => *version >= base,
+        Op::Greater if exact => *version > base,
+        Op::Greater => bump(&base, parts - 1).is_some_and(|lower| *version >= lower),
+        Op::Less => *version < base,
+        Op::LessEq if exact => *version <= base,
+        Op::LessEq => below_bump(parts - 1),
+        Op::Exact | Op::Wildcard if exact => *version == base,
+        Op::Exact | Op::Wildcard => *version >= base && below_bump(parts - 1),
+        Op::Tilde => *version >= base && below_bump(if parts == 1 { 0 } else { 1 }),
+        Op::Caret => {
+            // Bump the left-most non-zero component that was given
+            let component = if base.major > 0 || parts == 1 {
+                0
+            } else if base.minor > 0 || parts == 2 {
+                1
+            } else {
+                2
+            };
+            *version >= base && below_bump(component)
+        }
+        _ => false,
+    }
+}
+
+/// Smallest version above every release sharing the given component, e.g.
+/// bumping the minor of 0.36.2 gives 0.37.0-0 so that 0.37 betas are excluded.
+/// None if the component is already at its maximum.
+fn bump(version: &Version, component: usize) -> Option<Version> {
+    let (major, minor, patch) = match component {
+        0 => (version.major.checked_add(1)?, 0, 0),
+        1 => (version.major, version.minor.checked_add(1)?, 0),
+        _ => (version.major, version.minor, version.patch.checked_add(1)?),
+    };
+    Some(Version {
+        major,
+        minor,
+        patch,
+        pre: Prerelease::new("0").expect("0 is a valid pre-release"),
+        build: BuildMetadata::EMPTY,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn satisfied(requirement: &str, version: &str) -> bool {
+        requirement_satisfied(requirement, version)
+            .unwrap_or_else(|| panic!("{:?} / {:?} should parse", requirement, version))
+    }
+
+    #[test]
+    fn reads_compiler_version_from_package_table_first() {
+        let toml = "compiler_version = \">=0.30.0\"\n[package]\nname = \"x\"\ncompiler_version = \" ^1.0.0-beta.2 \"\n";
+        assert_eq!(
+            parse_compiler_version(toml).as_deref(),
+            Some("^1.0.0-beta.2")
+        );
+        assert_eq!(
+            parse_compiler_version("compiler_version = \">=0.30.0\"").as_deref(),
+            Some(">=0.30.0")
+        );
+        assert_eq!(
+            parse_compiler_version("[package]\ncompiler_version = \"\""),
+            None
+        );
+        assert_eq!(parse_compiler_version("not toml ["), None);
+    }
+
+    #[test]
+    fn valid_versions() {
+        for version in [
+            "0.36.0",
+            "1.0.0-beta.3",
+            "v1.2.3",
+            "1.2.3+build.5",
+            "10.20.30",
+        ] {
+            assert!(is_valid_version(version), "{}", version);
+        }
+    }
+
+    #[test]
+    fn invalid_versions() {
+        for version in [
+            "",
+            "1",
+            "1.2",
+            "1.2.3.4",
+            "1..3",
+            "1.2.x",
+            "1.2.*",
+            "a.b.c",
+            "1.2.+3",
+            "1.0.0-",
+            "1.0.0-beta..1",
+            "1.0.0-beta_1",
+            "1.2.3 4",
+        ] {
+            assert!(!is_valid_version(version), "{:?}", version);
+        }
+        assert_eq!(requirement_satisfied(">=0.30.0", "1.2.3.4"), None);
+        assert_eq!(requirement_satisfied(">=0.30.0.1", "1.0.0"), None);
+        assert_eq!(requirement_satisfied(">=banana", "1.0.0"), None);
+        assert_eq!(requirement_satisfied("1.*.3", "1.0.0"), None);
+    }
+
+    #[test]
+    fn comparison_operators() {
+        assert!(satisfied(">=0.30.0", "0.30.0"));
+        assert!(!satisfied(">=0.30.0", "0.29.9"));
+        assert!(satisfied(">0.30.0", "0.30.1"));
+        assert!(!satisfied(">0.30.0", "0.30.0"));
+        assert!(satisfied("<0.40.0", "0.39.9"));
+        assert!(!satisfied("<0.40.0", "0.40.0"));
+        assert!(satisfied("<=0.40.0", "0.40.0"));
+        assert!(satisfied("=0.36.0", "0.36.0"));
+        assert!(!satisfied("=0.36.0", "0.36.1"));
+        assert!(satisfied("*", "0.1.0"));
+    }
+
+    #[test]
+    fn partial_versions_cover_the_whole_range() {
+        assert!(satisfied(">0.36", "0.37.0"));
+        assert!(!satisfied(">0.36", "0.36.9"));
+        assert!(satisfied("<=0.36", "0.36.9"));
+        assert!(!satisfied("<=0.36", "0.37.0"));
+        assert!(satisfied("=0.36", "0.36.4"));
+        assert!(satisfied("0.36.*", "0.36.4"));
+        assert!(!satisfied("0.36.*", "0.37.0"));
+    }
+
+    #[test]
+    fn caret_and_tilde() {
+        // A bare version is a caret requirement
+        assert!(satisfied("1.2.0", "1.9.0"));
+        assert!(!satisfied("1.2.0", "2.0.0"));
+        assert!(satisfied("^0.36.0", "0.36.5"));
+        assert!(!satisfied("^0.36.0", "0.37.0"));
+        assert!(satisfied("^0.0.3", "0.0.3"));
+        assert!(!satisfied("^0.0.3", "0.0.4"));
+        assert!(satisfied("~0.36", "0.36.9"));
+        assert!(!satisfied("~0.36", "0.37.0"));
+        assert!(satisfied("~1", "1.9.0"));
+        assert!(!satisfied("~1", "2.0.0"));
+    }
+
+    #[test]
+    fn comma_separated_comparators_must_all_match() {
+        assert!(satisfied(">=0.30, <0.40", "0.35.0"));
+        assert!(!satisfied(">=0.30, <0.40", "0.40.0"));
+        assert!(!satisfied(">=0.30, <0.40", "0.29.0"));
+    }
+
+    #[test]
+    fn pre_releases_order_by_semver_precedence() {
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+        ];
+        for pair in ordered.windows(2) {
+            let (a, b) = (
+                parse_version(pair[0]).unwrap(),
+                parse_version(pair[1]).unwrap(),
+            );
+            assert!(a < b, "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn pre_releases_are_matched_not_excluded() {
+        assert!(satisfied(">=1.0.0-beta.2", "1.0.0-beta.3"));
+        assert!(!satisfied(">=1.0.0-beta.2", "1.0.0-beta.1"));
+        assert!(satisfied("^1.0.0-beta.2", "1.0.0"));
+        // The bump to 0.37.0-0 keeps 0.37 betas out of ~0.36
+        assert!(!satisfied("~0.36", "0.37.0-beta.1"));
+        assert!(satisfied("<1.0.0", "1.0.0-beta.16"));
+    }
+
+    #[test]
+    fn maximal_components_do_not_overflow() {
+        let max = u64::MAX;
+        assert!(satisfied(&format!("^{}.0.0", max), &format!("{}.5.0", max)));
+        assert!(satisfied(&format!("~0.{}", max), &format!("0.{}.7", max)));
+        assert!(satisfied(
+            &format!("<=0.0.{}", max),
+            &format!("0.0.{}", max)
+        ));
+        assert!(!satisfied(&format!(">{}", max), &format!("{}.9.9", max)));
+    }
+}
@@ -1,69 +1,162 @@
+use crate::request_id;
 use anyhow::Result;
+use serde::Serialize;
 use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
 use std::str::FromStr;
-/// Creates a database connection pool from the DATABASE_URL environment variable
-pub async fn create_pool() -> Result<PgPool> {
-    let mut database_url = std::env::var("DATABASE_URL")
-        .expect("DATABASE_URL must be set in environment or .env file");
-
-    let is_production = std::env::var("ENVIRONMENT")
-        .unwrap_or_else(|_| "development".to_string())
-        .eq_ignore_ascii_case("production");
-
-    // In production, don't auto-modify DATABASE_URL (assume it's correct)
-    if !is_production {
-        // Development-only: auto-fix PgBouncer issues
-        let original_url = database_url.clone();
+/// What the development-only PgBouncer workaround did to DATABASE_URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlAutoFix {
+    /// `DB_NO_AUTO_FIX` is set
+    Disabled,
+    /// Production URLs are assumed to be correct
+    SkippedInProduction,
+    Unchanged,
+    Rewritten,
+}
 
-        if database_url.contains(":6543") {
-            println!(
-                "⚠️  Detected PgBouncer pooler (port 6543) - switching to direct connection (port 5432)"
-            );
-            database_url = database_url.replace(":6543", ":5432");
-        }
+/// Pool sizing, which differs between production and development
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub idle_timeout_secs: u64,
+    pub max_lifetime_secs: u64,
+}
 
-        if !database_url.contains("statement_cache_size") {
-            if database_url.contains('?') {
-                database_url.push_str("&statement_cache_size=0");
-            } else {
-                database_url.push_str("?statement_cache_size=0");
+impl PoolSettings {
+    fn for_environment(is_production: bool) -> Self {
+        if is_production {
+            PoolSettings {
+                max_connections: 20,
+                min_connections: 5,
+                idle_timeout_secs: 300,
+                max_lifetime_secs: 1800,
+            }
+        } else {
+            PoolSettings {
+                max_connections: 10,
+                min_connections: 2,
+                idle_timeout_secs: 60,
+                max_lifetime_secs: 300,
             }
-            println!("✅ Added statement_cache_size=0 to DATABASE_URL");
         }
+    }
+}
 
-        // Log URL changes for debugging
-        if original_url != database_url {
-            println!(
-                "   Original: {}",
-                original_url.split('@').last().unwrap_or(&original_url)
-            );
+/// Database settings resolved from the environment
+#[derive(Debug)]
+pub struct DbConfig {
+    /// `ENVIRONMENT`, `development` when unset
+    pub environment: String,
+    pub is_production: bool,
+    /// DATABASE_URL after the PgBouncer auto-fix; contains the password
+    pub database_url: String,
+    pub auto_fix: UrlAutoFix,
+    pub pool: PoolSettings,
+}
+
+impl DbConfig {
+    /// Reads DATABASE_URL and ENVIRONMENT, applying the PgBouncer auto-fix
+    /// outside production
+    pub fn from_env() -> Self {
+        let mut database_url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set in environment or .env file");
+
+        let environment =
+            std::env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string());
+        let is_production = environment.eq_ignore_ascii_case("production");
+
+        // DB_NO_AUTO_FIX=1 opts out of the rewriting below, e.g. to go through
+        // PgBouncer on purpose
+        let no_auto_fix = std::env::var("DB_NO_AUTO_FIX")
+            .is_ok_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"));
+
+        let auto_fix = if no_auto_fix {
             println!(
-                "   Updated:  {}",
-                database_url.split('@').last().unwrap_or(&database_url)
+                "ℹ️  DB_NO_AUTO_FIX is set - using DATABASE_URL as given (no PgBouncer auto-fix)"
             );
+            UrlAutoFix::Disabled
+        } else if is_production {
+            UrlAutoFix::SkippedInProduction
         } else {
-            println!("✅ DATABASE_URL is properly configured");
+            // Development-only: auto-fix PgBouncer issues. In production the URL
+            // is assumed to be correct.
+            let original_url = database_url.clone();
+
+            if database_url.contains(":6543") {
+                println!(
+                    "⚠️  Detected PgBouncer pooler (port 6543) - switching to direct connection (port 5432)"
+                );
+                database_url = database_url.replace(":6543", ":5432");
+            }
+
+            if !database_url.contains("statement_cache_size") {
+                if database_url.contains('?') {
+                    database_url.push_str("&statement_cache_size=0");
+                } else {
+                    database_url.push_str("?statement_cache_size=0");
+                }
+                println!("✅ Added statement_cache_size=0 to DATABASE_URL");
+            }
+
+            // Log URL changes for debugging
+            if original_url != database_url {
+                println!(
+                    "   Original: {}",
+                    original_url.split('@').next_back().unwrap_or(&original_url)
+                );
+                println!(
+                    "   Updated:  {}",
+                    database_url.split('@').next_back().unwrap_or(&database_url)
+                );
+                UrlAutoFix::Rewritten
+            } else {
+                println!("✅ DATABASE_URL is properly configured");
+                UrlAutoFix::Unchanged
+            }
+        };
+
+        DbConfig {
+            environment,
+            is_production,
+            database_url,
+            auto_fix,
+            pool: PoolSettings::for_environment(is_production),
         }
     }
+}
+
+/// Creates a database connection pool from the DATABASE_URL environment variable
+pub async fn create_pool() -> Result<PgPool> {
+    create_pool_with(&DbConfig::from_env()).await
+}
 
-    let connect_options = PgConnectOptions::from_str(&database_url)?
-        .statement_cache_capacity(0);
+/// Creates a database connection pool from already resolved settings
+pub async fn create_pool_with(config: &DbConfig) -> Result<PgPool> {
+    let connect_options =
+        PgConnectOptions::from_str(&config.database_url)?.statement_cache_capacity(0);
 
     // Production vs Development pool settings
-    let mut pool_builder = PgPoolOptions::new();
-
-    if is_production {
-        pool_builder = pool_builder
-            .max_connections(20)
-            .min_connections(5)
-            .idle_timeout(std::time::Duration::from_secs(300))
-            .max_lifetime(std::time::Duration::from_secs(1800));
-    } else {
-        pool_builder = pool_builder
-            .max_connections(10)
-            .min_connections(2)
-            .idle_timeout(std::time::Duration::from_secs(60))
-            .max_lifetime(std::time::Duration::from_secs(300));
+    let settings = config.pool;
+    let mut pool_builder = PgPoolOptions::new()
+        .max_connections(settings.max_connections)
+        .min_connections(settings.min_connections)
+        .idle_timeout(std::time::Duration::from_secs(settings.idle_timeout_secs))
+        .max_lifetime(std::time::Duration::from_secs(settings.max_lifetime_secs));
+
+    if request_id::tag_connections() {
+        println!("🏷️  DB_TAG_REQUESTS is set - tagging connections with request ids");
+        pool_builder = pool_builder.before_acquire(|conn, _meta| {
+            Box::pin(async move {
+                sqlx::query("SELECT set_config('application_name', $1, false)")
+                    .bind(request_id::application_name())
+                    .persistent(false)
+                    .execute(&mut *conn)
+                    .await?;
+                Ok(true)
+            })
+        });
     }
 
     let pool = pool_builder
@@ -128,13 +221,14 @@ pub async fn run_migrations(pool: &PgPool) -> Result<(), Box<dyn std::error::Err
 
 /// Initializes the database connection and runs migrations
 pub async fn init_db() -> Result<PgPool, Box<dyn std::error::Error>> {
-    let pool = create_pool().await?;
+    init_db_with(&DbConfig::from_env()).await
+}
 
-    let is_production = std::env::var("ENVIRONMENT")
-        .unwrap_or_else(|_| "development".to_string())
-        .eq_ignore_ascii_case("production");
+/// Like `init_db`, from already resolved settings
+pub async fn init_db_with(config: &DbConfig) -> Result<PgPool, Box<dyn std::error::Error>> {
+    let pool = create_pool_with(config).await?;
 
-    if is_production {
+    if config.is_production {
         // Skip migrations in production; sqlx::migrate!() uses named prepared statements
         // internally which pollute the PgBouncer connection pool on failure.
         // Run migrations manually: sqlx migrate run --database-url <URL>
@@ -0,0 +1,44 @@
+//! Reading the `[dependencies]` a package declares in its `Nargo.toml`, so
+//! the registry can answer which packages depend on a given one.
+
+use crate::models::ManifestDependency;
+
+/// Lists the dependencies in a Nargo.toml. Git dependencies keep their
+/// (normalized) URL, directory and tag; path dependencies are listed without
+/// them.
+/// Returns None if the manifest doesn't parse.
+pub fn parse_dependencies(nargo_toml: &str) -> Option<Vec<ManifestDependency>> {
+    let doc: toml::Table = nargo_toml.parse().ok()?;
+    let Some(deps) = doc.get("dependencies").and_then(|d| d.as_table()) else {
+        return Some(Vec::new());
+    };
+
+    let dependencies = deps
+        .iter()
+        .map(|(name, spec)| ManifestDependency {
+            name: name.clone(),
+            git_url: spec
+                .get("git")
+                .and_then(|v| v.as_str())
+                .map(normalize_git_url),
+            directory: spec
+                .get("directory")
+                .and_then(|v| v.as_str())
+                .map(|d| d.trim_matches('/').to_string())
+                .filter(|d| !d.is_empty()),
+            tag: spec
+                .get("tag")
+                .and_then(|v| v.as_str())
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty()),
+        })
+        .collect();
+    Some(dependencies)
+}
+
+/// Canonical form of a repository URL for matching dependencies against
+/// packages: lowercase, without a trailing slash or `.git`.
+pub fn normalize_git_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    url.strip_suffix(".git").unwrap_or(url).to_lowercase()
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Directory under the user's cache directory holding cached GitHub responses
+const CACHE_SUBDIR: &str = "noir-registry/github";
+
+/// On-disk cache of GitHub API responses keyed by repo slug. Entries keep the
+/// response's ETag so the next request can be conditional (`If-None-Match`);
+/// a 304 reuses the stored body and doesn't count against the rate limit.
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub etag: String,
+    pub body: String,
+}
+
+impl ResponseCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Cache rooted at `GITHUB_CACHE_DIR`, or [`default_dir`](Self::default_dir)
+    pub fn from_env() -> Self {
+        match std::env::var("GITHUB_CACHE_DIR") {
+            Ok(dir) if !dir.is_empty() => Self::new(dir),
+            _ => Self::new(Self::default_dir()),
+        }
+    }
+
+    /// `noir-registry/github` under `XDG_CACHE_HOME` (or `~/.cache`), falling
+    /// back to the temp directory. Always absolute, so the scraper binary and
+    /// scrapes started by the server share one cache whatever directory
+    /// either runs from.
+    pub fn default_dir() -> PathBuf {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME")
+                    .filter(|home| !home.is_empty())
+                    .map(|home| PathBuf::from(home).join(".cache"))
+            })
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(std::env::temp_dir);
+        base.join(CACHE_SUBDIR)
+    }
+
+    fn path_for(&self, owner: &str, repo: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{}__{}.json",
+            owner.to_lowercase(),
+            repo.to_lowercase()
+        ))
+    }
+
+    pub fn load(&self, owner: &str, repo: &str) -> Option<CachedResponse> {
+        let content = std::fs::read_to_string(self.path_for(owner, repo)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Stores a response. Failures are logged and otherwise ignored: the cache
+    /// only saves API calls, it is never required for a scrape to succeed.
+    pub fn store(&self, owner: &str, repo: &str, etag: &str, body: &str) {
+        let entry = CachedResponse {
+            etag: etag.to_string(),
+            body: body.to_string(),
+        };
+        let result = std::fs::create_dir_all(&self.dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(serde_json::to_string(&entry)?))
+            .and_then(|json| Ok(std::fs::write(self.path_for(owner, repo), json)?));
+        if let Err(e) = result {
+            eprintln!(
+                "Failed to cache GitHub response for {}/{}: {}",
+                owner, repo, e
+            );
+        }
+    }
+}
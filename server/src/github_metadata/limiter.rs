@@ -0,0 +1,101 @@
+use super::secondary_limit::backoff_up_to;
+use anyhow::Result;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Concurrent GitHub requests allowed when `GITHUB_MAX_CONCURRENT_REQUESTS` is unset
+const DEFAULT_MAX_CONCURRENT: usize = 4;
+/// Longest secondary-limit wait a request handler sits out before failing
+const MAX_WAIT: Duration = Duration::from_secs(90);
+
+/// Caps how many GitHub requests the server has open at once, shared by every
+/// handler, so a burst of publishes queues up instead of tripping GitHub's
+/// secondary rate limit.
+#[derive(Debug)]
+pub struct GithubLimiter {
+    permits: Semaphore,
+    max_concurrent: usize,
+}
+
+impl GithubLimiter {
+    pub fn new(max_concurrent: usize) -> Self {
+        GithubLimiter {
+            permits: Semaphore::new(max_concurrent),
+            max_concurrent,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let max_concurrent = std::env::var("GITHUB_MAX_CONCURRENT_REQUESTS")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_MAX_CONCURRENT);
+        Self::new(max_concurrent)
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    /// Requests currently holding a permit
+    pub fn in_flight(&self) -> usize {
+        self.max_concurrent - self.permits.available_permits()
+    }
+
+    /// Awaits `request` once a permit is free, waiting for one rather than
+    /// failing. No retries; callers that back off themselves use this.
+    pub async fn limit<F: Future>(&self, request: F) -> F::Output {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("the semaphore is never closed");
+        request.await
+    }
+
+    /// [`Self::limit`], plus backing off and retrying on a secondary rate
+    /// limit; the permit is given back while waiting, so other requests can
+    /// use it.
+    pub async fn run<T, F, Fut>(&self, mut call: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        backoff_up_to(MAX_WAIT, || self.limit(call())).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn calls_beyond_the_limit_wait_for_a_permit() {
+        let limiter = GithubLimiter::new(2);
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let call = || async {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        };
+        let results = futures_util::future::join_all((0..6).map(|_| limiter.run(call))).await;
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(limiter.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn in_flight_counts_held_permits() {
+        let limiter = GithubLimiter::new(3);
+        let held = limiter.permits.acquire().await.unwrap();
+        assert_eq!(limiter.in_flight(), 1);
+        drop(held);
+        assert_eq!(limiter.in_flight(), 0);
+    }
+}
@@ -1,5 +1,28 @@
-use crate::models::{EnrichedPackage, GitHubRepo, Package};
+use crate::compiler_version::parse_compiler_version;
+use crate::dependencies::parse_dependencies;
+use crate::models::{EnrichedPackage, GitHubRepo, GitHubTag, Package};
 use anyhow::Result;
+use reqwest::Url;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+mod cache;
+mod limiter;
+mod secondary_limit;
+pub use cache::ResponseCache;
+pub use limiter::GithubLimiter;
+pub use secondary_limit::{SecondaryRateLimit, with_secondary_limit_backoff};
+
+/// How long a repo's tag list is reused before asking GitHub again
+const TAG_CACHE_TTL: Duration = Duration::from_secs(60);
+
+type TagCache = Mutex<HashMap<(String, String), (Instant, Vec<GitHubTag>)>>;
+
+fn tag_cache() -> &'static TagCache {
+    static CACHE: OnceLock<TagCache> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
 pub fn parse_github_url(url: &str) -> Option<(String, String)> {
     // This is the URL Pattern: https://github.com/owner/repo
     let parts: Vec<&str> = url.split('/').collect();
@@ -10,11 +33,93 @@ pub fn parse_github_url(url: &str) -> Option<(String, String)> {
     }
     None
 }
-/// Fetches repository metadata from GitHub API
+/// Splits a GitHub URL that points into a subdirectory
+/// (`https://github.com/owner/repo/tree/<branch>/<path>`) into the repository
+/// URL and the path. Other URLs are returned unchanged with no directory.
+pub fn split_github_url(url: &str) -> (String, Option<String>) {
+    let trimmed = url.trim_end_matches('/');
+    let parts: Vec<&str> = trimmed.split('/').collect();
+    if parts.len() >= 8 && parts[2].ends_with("github.com") && parts[5] == "tree" {
+        let repo_url = parts[..5].join("/");
+        let directory = parts[7..].join("/");
+        return (repo_url, Some(directory));
+    }
+    (url.to_string(), None)
+}
+
+/// Whether an address is only reachable from inside a network: loopback,
+/// private (RFC 1918 / unique local), link-local, shared (CGNAT) or
+/// unspecified
+pub fn is_non_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || (a == 100 && (64..128).contains(&b))
+        }
+        IpAddr::V6(v6) => {
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_unique_local()
+                || v6.is_unicast_link_local()
+                || v6
+                    .to_ipv4_mapped()
+                    .is_some_and(|v4| is_non_public_ip(IpAddr::V4(v4)))
+        }
+    }
+}
+
+/// Whether a URL's host (as `Url::host_str` gives it) is localhost or a
+/// non-public IP literal. Domain names other than localhost pass; they have
+/// to be checked again once resolved.
+pub fn is_non_public_host(host: &str) -> bool {
+    // IPv6 hosts come bracketed; IPv4 ones already normalized (`127.1` is 127.0.0.1)
+    match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) => is_non_public_ip(ip),
+        Err(_) => {
+            let domain = host.trim_end_matches('.');
+            domain == "localhost" || domain.ends_with(".localhost")
+        }
+    }
+}
+
+/// Checks a repository URL given on publish before the server makes any
+/// request for it: it must be an http(s) URL on github.com. Localhost and
+/// internal addresses are called out, since they'd point the server's fetches
+/// at internal services. The error is the reason, for the publisher.
+pub fn validate_github_url(url: &str) -> Result<(), String> {
+    let parsed = Url::parse(url).map_err(|e| format!("'{}' is not a valid URL: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("'{}' must be an http(s) URL", url));
+    }
+    let Some(host) = parsed.host_str() else {
+        return Err(format!("'{}' has no host", url));
+    };
+    if is_non_public_host(host) {
+        return Err(format!(
+            "'{}' points at a local or private network address; \
+             the repository must be a public GitHub repository",
+            url
+        ));
+    }
+    if !matches!(parsed.host_str(), Some("github.com" | "www.github.com")) {
+        return Err(format!("'{}' is not a github.com repository URL", url));
+    }
+    Ok(())
+}
+
+/// Fetches repository metadata from GitHub API. With a cache, the request is
+/// conditional on the stored ETag and a 304 is served from disk. A secondary
+/// rate limit fails with [`SecondaryRateLimit`], for callers to back off on.
 pub async fn fetch_github_metadata(
     client: &reqwest::Client,
     github_url: &str,
     token: Option<&str>,
+    cache: Option<&ResponseCache>,
 ) -> Result<GitHubRepo> {
     let (owner, repo) = parse_github_url(github_url)
         .ok_or_else(|| anyhow::anyhow!("Invalid GitHub URL: {}", github_url))?;
@@ -23,7 +128,6 @@ pub async fn fetch_github_metadata(
 
     let mut request = client
         .get(&api_url)
-        .header("User-Agent", "noir-registry-scraper")
         .header("Accept", "application/vnd.github.v3+json");
 
     // Add authentication if token is provided
@@ -31,33 +135,312 @@ pub async fn fetch_github_metadata(
         request = request.header("Authorization", format!("Bearer {}", token));
     }
 
+    let cached = cache.and_then(|c| c.load(&owner, &repo));
+    if let Some(cached) = &cached {
+        request = request.header("If-None-Match", &cached.etag);
+    }
+
     let response = request.send().await?;
 
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED
+        && let Some(cached) = cached
+    {
+        return Ok(serde_json::from_str(&cached.body)?);
+    }
+
+    if let Some(limit) = SecondaryRateLimit::from_response(response.status(), response.headers()) {
+        return Err(limit.into());
+    }
     if !response.status().is_success() {
         anyhow::bail!("GitHub API error: {}", response.status());
     }
 
-    let repo_data: GitHubRepo = response.json().await?;
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().await?;
+    let repo_data: GitHubRepo = serde_json::from_str(&body)?;
+
+    if let (Some(cache), Some(etag)) = (cache, etag) {
+        cache.store(&owner, &repo, &etag, &body);
+    }
     Ok(repo_data)
 }
 
+/// Fetches the package's raw Nargo.toml (inside its subdirectory, for monorepo
+/// URLs) from `branch`, or the default branch when it isn't known. A missing
+/// manifest is not an error.
+pub async fn fetch_manifest(
+    client: &reqwest::Client,
+    github_url: &str,
+    branch: Option<&str>,
+) -> Option<String> {
+    let (owner, repo) = parse_github_url(github_url)?;
+    let manifest_path = match split_github_url(github_url) {
+        (_, Some(directory)) => format!("{}/Nargo.toml", directory),
+        (_, None) => "Nargo.toml".to_string(),
+    };
+    let raw_url = format!(
+        "https://raw.githubusercontent.com/{}/{}/{}/{}",
+        owner,
+        repo,
+        branch.unwrap_or("HEAD"),
+        manifest_path
+    );
+
+    let response = client.get(&raw_url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+
+    response.text().await.ok()
+}
+
+/// Cleans up a repo's homepage so clients can render it as a link: blank
+/// becomes None, a missing scheme gets `https://`, and anything that still
+/// isn't an http(s) URL with a dotted host is dropped.
+pub fn normalize_homepage(homepage: Option<String>) -> Option<String> {
+    let homepage = homepage?;
+    let homepage = homepage.trim();
+    if homepage.is_empty() {
+        return None;
+    }
+
+    let candidate = if homepage.contains("://") {
+        homepage.to_string()
+    } else {
+        format!("https://{}", homepage)
+    };
+    let url = reqwest::Url::parse(&candidate).ok()?;
+    let valid = matches!(url.scheme(), "http" | "https")
+        && url.host_str().is_some_and(|host| host.contains('.'));
+    valid.then_some(candidate)
+}
+
 /// Enriches a package with GitHub metadata
 pub async fn enrich_package(
     client: &reqwest::Client,
+    limiter: &GithubLimiter,
     pkg: &Package,
     token: Option<&str>,
+    cache: Option<&ResponseCache>,
 ) -> Result<EnrichedPackage> {
-    let github_data = fetch_github_metadata(client, &pkg.github_url, token).await?;
+    let fetched_at = chrono::Utc::now();
+    let github_data = limiter
+        .limit(fetch_github_metadata(client, &pkg.github_url, token, cache))
+        .await?;
+    let manifest = limiter
+        .limit(fetch_manifest(
+            client,
+            &pkg.github_url,
+            github_data.default_branch.as_deref(),
+        ))
+        .await;
+    let compiler_version = manifest.as_deref().and_then(parse_compiler_version);
+    let dependencies = manifest.as_deref().and_then(parse_dependencies);
+    let (github_url, directory) = split_github_url(&pkg.github_url);
 
     Ok(EnrichedPackage {
         name: pkg.name.clone(),
         description: pkg.description.clone(),
-        github_url: pkg.github_url.clone(),
+        github_url,
+        directory,
         owner_username: github_data.owner.login,
         owner_avatar: github_data.owner.avatar_url,
         stars: github_data.stargazers_count,
-        license: github_data.license.map(|l| l.spdx_id),
-        homepage: github_data.homepage,
+        fork: github_data.fork,
+        license: github_data.license.and_then(|l| l.spdx_id),
+        homepage: normalize_homepage(github_data.homepage),
         last_commit_at: github_data.pushed_at,
+        compiler_version,
+        dependencies,
+        category: pkg.category.clone(),
+        fetched_at,
     })
 }
+
+/// Lists a repository's tags, reusing a recent result for the same repo so
+/// repeated publishes and tag lookups don't hammer the GitHub API.
+pub async fn fetch_repo_tags(
+    client: &reqwest::Client,
+    owner: &str,
+    repo: &str,
+    github_token: Option<&str>,
+) -> Result<Vec<GitHubTag>> {
+    let key = (owner.to_lowercase(), repo.to_lowercase());
+    if let Some((fetched_at, tags)) = tag_cache().lock().unwrap().get(&key)
+        && fetched_at.elapsed() < TAG_CACHE_TTL
+    {
+        return Ok(tags.clone());
+    }
+
+    let mut tags = Vec::new();
+    // 100 per page is GitHub's max; 10 pages covers any realistic Noir library
+    for page in 1..=10 {
+        let api_url = format!(
+            "https://api.github.com/repos/{}/{}/tags?per_page=100&page={}",
+            owner, repo, page
+        );
+        let mut request = client
+            .get(&api_url)
+            .header("Accept", "application/vnd.github.v3+json");
+        if let Some(token) = github_token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let response = request.send().await?;
+
+        if let Some(limit) =
+            SecondaryRateLimit::from_response(response.status(), response.headers())
+        {
+            return Err(limit.into());
+        }
+        if !response.status().is_success() {
+            if response.status() == 404 {
+                anyhow::bail!("Repository not found: {}/{}", owner, repo);
+            }
+            anyhow::bail!("GitHub API error: {}", response.status());
+        }
+
+        let batch: Vec<GitHubTag> = response.json().await?;
+        let done = batch.len() < 100;
+        tags.extend(batch);
+        if done {
+            break;
+        }
+    }
+
+    tag_cache()
+        .lock()
+        .unwrap()
+        .insert(key, (Instant::now(), tags.clone()));
+    Ok(tags)
+}
+
+/// Resolves a (possibly abbreviated) commit SHA to the full SHA.
+/// Returns None if the repository has no such commit.
+pub async fn fetch_commit_sha(
+    client: &reqwest::Client,
+    owner: &str,
+    repo: &str,
+    rev: &str,
+    github_token: Option<&str>,
+) -> Result<Option<String>> {
+    let api_url = format!(
+        "https://api.github.com/repos/{}/{}/commits/{}",
+        owner, repo, rev
+    );
+    let mut request = client
+        .get(&api_url)
+        .header("Accept", "application/vnd.github.v3+json");
+    if let Some(token) = github_token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    let response = request.send().await?;
+
+    if let Some(limit) = SecondaryRateLimit::from_response(response.status(), response.headers()) {
+        return Err(limit.into());
+    }
+    // GitHub answers 422 for a SHA that matches no commit
+    if response.status() == 404 || response.status() == 422 {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        anyhow::bail!("GitHub API error: {}", response.status());
+    }
+
+    let commit: serde_json::Value = response.json().await?;
+    Ok(commit
+        .get("sha")
+        .and_then(|s| s.as_str())
+        .map(str::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(homepage: &str) -> Option<String> {
+        normalize_homepage(Some(homepage.to_string()))
+    }
+
+    #[test]
+    fn publish_urls_must_be_public_github_repositories() {
+        for url in [
+            "https://github.com/noir-lang/noir-bignum",
+            "http://github.com/noir-lang/noir-bignum",
+            "https://www.github.com/noir-lang/noir-bignum/tree/main/lib",
+        ] {
+            assert_eq!(validate_github_url(url), Ok(()), "{}", url);
+        }
+
+        for url in [
+            "http://localhost/noir-lang/noir-bignum",
+            "http://api.localhost./x/y",
+            "http://127.0.0.1:8080/x/y",
+            "http://127.1/x/y",
+            "http://0x7f000001/x/y",
+            "http://10.0.0.5/x/y",
+            "http://172.16.3.4/x/y",
+            "http://192.168.1.1/x/y",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1/x/y",
+            "http://0.0.0.0/x/y",
+            "http://[::1]/x/y",
+            "http://[fd00::1]/x/y",
+            "http://[fe80::1]/x/y",
+            "http://[::ffff:127.0.0.1]/x/y",
+        ] {
+            let reason = validate_github_url(url).expect_err(url);
+            assert!(reason.contains("private network"), "{}: {}", url, reason);
+        }
+
+        for url in [
+            "https://gitlab.com/owner/repo",
+            "https://github.com.evil.example/owner/repo",
+            "http://8.8.8.8/github.com/owner/repo",
+            "ftp://github.com/owner/repo",
+            "github.com/owner/repo",
+        ] {
+            assert!(validate_github_url(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn empty_homepage_becomes_none() {
+        assert_eq!(normalize_homepage(None), None);
+        assert_eq!(normalize(""), None);
+        assert_eq!(normalize("   "), None);
+    }
+
+    #[test]
+    fn schemeless_homepage_gets_https() {
+        assert_eq!(
+            normalize("noir-lang.org").as_deref(),
+            Some("https://noir-lang.org")
+        );
+        assert_eq!(
+            normalize("  docs.example.com/guide ").as_deref(),
+            Some("https://docs.example.com/guide")
+        );
+        assert_eq!(
+            normalize("http://example.com").as_deref(),
+            Some("http://example.com")
+        );
+    }
+
+    #[test]
+    fn invalid_homepage_is_dropped() {
+        for homepage in [
+            "localhost",
+            "not a url",
+            "ftp://example.com",
+            "javascript://example.com/%0Aalert(1)",
+            "https://",
+            "https://exa mple.com",
+        ] {
+            assert_eq!(normalize(homepage), None, "{:?}", homepage);
+        }
+    }
+}
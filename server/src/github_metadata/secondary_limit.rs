@@ -0,0 +1,178 @@
+use anyhow::Result;
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::future::Future;
+use std::time::Duration;
+
+/// How many times one call is retried after a secondary rate limit
+const MAX_RETRIES: u32 = 3;
+/// Longest wait honored; a longer `Retry-After` fails the call instead
+const MAX_WAIT: Duration = Duration::from_secs(15 * 60);
+
+/// Returned when GitHub's secondary ("abuse detection") rate limit kicks in:
+/// a 403 or 429 carrying `Retry-After`. Unlike running out of the hourly
+/// quota, it clears after the given wait.
+#[derive(Debug)]
+pub struct SecondaryRateLimit {
+    pub retry_after: Duration,
+}
+
+impl std::fmt::Display for SecondaryRateLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "GitHub secondary rate limit hit, retry after {}s",
+            self.retry_after.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for SecondaryRateLimit {}
+
+impl SecondaryRateLimit {
+    /// Recognizes a secondary-limit response by its status and `Retry-After`
+    /// (in seconds, as GitHub sends it)
+    pub fn from_response(status: StatusCode, headers: &HeaderMap) -> Option<Self> {
+        if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+            return None;
+        }
+        let secs = headers
+            .get(RETRY_AFTER)?
+            .to_str()
+            .ok()?
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+        Some(SecondaryRateLimit {
+            retry_after: Duration::from_secs(secs),
+        })
+    }
+}
+
+/// Runs `call`, and when it fails with a [`SecondaryRateLimit`] waits out the
+/// `Retry-After` and tries again, up to `MAX_RETRIES` times. Other errors are
+/// returned straight away.
+pub async fn with_secondary_limit_backoff<T, F, Fut>(call: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    backoff_up_to(MAX_WAIT, call).await
+}
+
+/// [`with_secondary_limit_backoff`], but failing on any `Retry-After` longer
+/// than `max_wait`, for callers with a client waiting on them
+pub(crate) async fn backoff_up_to<T, F, Fut>(max_wait: Duration, mut call: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut retries = 0;
+    loop {
+        let err = match call().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        let Some(limit) = err.downcast_ref::<SecondaryRateLimit>() else {
+            return Err(err);
+        };
+        if retries >= MAX_RETRIES || limit.retry_after > max_wait {
+            return Err(err);
+        }
+        retries += 1;
+        eprintln!(
+            "GitHub secondary rate limit hit, backing off for {}s (retry {}/{})",
+            limit.retry_after.as_secs(),
+            retries,
+            MAX_RETRIES
+        );
+        tokio::time::sleep(limit.retry_after).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Instant;
+
+    fn headers(retry_after: Option<&'static str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = retry_after {
+            headers.insert(RETRY_AFTER, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn forbidden_with_retry_after_is_a_secondary_limit() {
+        let limit = SecondaryRateLimit::from_response(StatusCode::FORBIDDEN, &headers(Some("60")))
+            .expect("secondary limit");
+        assert_eq!(limit.retry_after, Duration::from_secs(60));
+        assert!(
+            SecondaryRateLimit::from_response(StatusCode::TOO_MANY_REQUESTS, &headers(Some("5")))
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn other_responses_are_not_secondary_limits() {
+        // Quota exhaustion: a 403 without Retry-After
+        assert!(SecondaryRateLimit::from_response(StatusCode::FORBIDDEN, &headers(None)).is_none());
+        assert!(
+            SecondaryRateLimit::from_response(StatusCode::FORBIDDEN, &headers(Some("soon")))
+                .is_none()
+        );
+        assert!(SecondaryRateLimit::from_response(StatusCode::OK, &headers(Some("60"))).is_none());
+        assert!(
+            SecondaryRateLimit::from_response(StatusCode::NOT_FOUND, &headers(Some("60")))
+                .is_none()
+        );
+    }
+
+    /// A call that hits the secondary limit `limited` times before succeeding
+    async fn flaky(calls: &AtomicU32, limited: u32) -> Result<&'static str> {
+        if calls.fetch_add(1, Ordering::SeqCst) < limited {
+            Err(SecondaryRateLimit {
+                retry_after: Duration::from_millis(20),
+            }
+            .into())
+        } else {
+            Ok("repo")
+        }
+    }
+
+    #[tokio::test]
+    async fn secondary_limit_is_waited_out_and_retried() {
+        let calls = AtomicU32::new(0);
+        let started = Instant::now();
+        let result = with_secondary_limit_backoff(|| flaky(&calls, 2)).await;
+        assert_eq!(result.unwrap(), "repo");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn retries_stop_after_the_limit() {
+        let calls = AtomicU32::new(0);
+        let err = with_secondary_limit_backoff(|| flaky(&calls, u32::MAX))
+            .await
+            .expect_err("always limited");
+        assert!(err.downcast_ref::<SecondaryRateLimit>().is_some());
+        assert_eq!(calls.load(Ordering::SeqCst), MAX_RETRIES + 1);
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_not_retried() {
+        let calls = AtomicU32::new(0);
+        let err = with_secondary_limit_backoff(|| async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(anyhow::anyhow!("GitHub API error: 404 Not Found"))
+        })
+        .await
+        .expect_err("not found");
+        assert!(err.to_string().contains("404"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
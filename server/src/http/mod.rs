@@ -0,0 +1,25 @@
+//! Shared outbound HTTP client (GitHub API, raw README fetches).
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Sent on every outbound request; GitHub rejects requests without one
+pub const USER_AGENT: &str = concat!("noir-registry/", env!("CARGO_PKG_VERSION"));
+
+/// Applies to every request unless it sets its own timeout
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Returns the process-wide HTTP client. Clones share one connection pool, so
+/// repeated calls reuse connections instead of opening new ones.
+pub fn build_client() -> reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT
+        .get_or_init(|| {
+            reqwest::Client::builder()
+                .user_agent(USER_AGENT)
+                .timeout(DEFAULT_TIMEOUT)
+                .build()
+                .expect("Failed to create HTTP client")
+        })
+        .clone()
+}
@@ -1,7 +1,14 @@
 pub mod db;
 
 pub mod auth;
+pub mod compiler_version;
+pub mod dependencies;
 pub mod github_metadata;
+pub mod http;
 pub mod models;
 pub mod package_storage;
+pub mod request_id;
 pub mod rest_apis;
+pub mod scraper;
+pub mod startup;
+pub mod webhook;
@@ -1,3 +1,5 @@
+use axum::ServiceExt;
+use noir_registry_server::startup::StartupConfig;
 use noir_registry_server::{db, rest_apis};
 use std::net::SocketAddr;
 
@@ -6,18 +8,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load environment variables from .env file
     dotenvy::dotenv().ok();
 
+    let port = std::env::var("PORT")
+        .unwrap_or_else(|_| "8080".to_string())
+        .parse::<u16>()
+        .expect("PORT must be a valid number");
+
     // Initialize database connection and run migrations
-    let pool = db::init_db().await?;
+    let db_config = db::DbConfig::from_env();
+    let pool = db::init_db_with(&db_config).await?;
 
     // Create the API router
     let app = rest_apis::create_router(pool);
 
-    // Start the server
-    let port = std::env::var("PORT")
-        .unwrap_or_else(|_| "8080".to_string())
-        .parse::<u16>()
-        .expect("PORT must be a valid number");
+    StartupConfig::collect(&db_config, port).log();
 
+    // Start the server
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     println!("🚀 Server starting on http://{}", addr);
     println!("📡 Available endpoints:");
@@ -26,10 +31,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("   GET /api/packages/:name - Get package by name");
     println!("   GET /api/search?q=query - Search packages");
     println!("   POST /api/packages/publish - Publish a package (requires API key)");
+    println!("   POST /api/admin/scrape - Trigger a registry scrape (requires ADMIN_API_KEY)");
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     println!("✅ Server running!");
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        // Peer addresses are needed to rate limit auth and publish per client
+        ServiceExt::<axum::extract::Request>::into_make_service_with_connect_info::<SocketAddr>(
+            app,
+        ),
+    )
+    .await?;
 
     Ok(())
 }
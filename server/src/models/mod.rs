@@ -1,3 +1,7 @@
+use axum::{
+    Json,
+    response::{IntoResponse, Response},
+};
 use serde::{Deserialize, Serialize};
 
 /// This should contain the structure of the package we are scraping
@@ -6,6 +10,35 @@ pub struct Package {
     pub name: String,
     pub github_url: String,
     pub description: String,
+    /// The README heading the package is listed under
+    pub category: Option<Category>,
+}
+/// A category derived from a README heading, e.g. "Merkle Trees" / `merkle-trees`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Category {
+    pub name: String,
+    pub slug: String,
+}
+
+impl Category {
+    /// Builds a category from heading text, or None if the heading has no
+    /// letters or digits to make a slug from
+    pub fn from_heading(heading: &str) -> Option<Self> {
+        let slug = heading
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join("-");
+        if slug.is_empty() {
+            return None;
+        }
+        let name = heading
+            .trim_start_matches(|c: char| !c.is_alphanumeric())
+            .trim()
+            .to_string();
+        Some(Category { name, slug })
+    }
 }
 /// This is the structure of the package we expect from an API response
 #[derive(Debug, Clone, Serialize)]
@@ -14,6 +47,8 @@ pub struct PackageResponse {
     pub name: String,
     pub description: Option<String>,
     pub github_repository_url: String,
+    /// Path of the package inside the repository, for monorepo packages
+    pub directory: Option<String>,
     pub homepage: Option<String>,
     pub license: Option<String>,
     pub owner_github_username: String,
@@ -26,16 +61,55 @@ pub struct PackageResponse {
     pub last_commit_at: Option<chrono::DateTime<chrono::Utc>>,
     pub comparison_notes: Option<String>,
     pub max_compatible_nargo_version: Option<String>,
+    /// Commit the `latest_version` tag pointed to when it was published
+    pub latest_version_commit_sha: Option<String>,
+    /// `compiler_version` requirement from the package's Nargo.toml, if any
+    pub compiler_version: Option<String>,
+    /// Set by the owner to steer users elsewhere; softer than yanking
+    pub deprecated: bool,
+    pub deprecation_message: Option<String>,
+    /// Whether the GitHub repository is a fork of another one
+    pub fork: bool,
     pub keywords: Vec<String>,
+    /// Every recorded version, oldest first. Only the NDJSON stream (used for
+    /// exports) fills this in; other responses leave it out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub versions: Option<Vec<PackageVersion>>,
+}
+
+/// One recorded version of a package and the commit it was pinned to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackageVersion {
+    pub version: String,
+    pub commit_sha: Option<String>,
+    #[serde(default)]
+    pub published_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Downloads recorded for this version (absent in older exports)
+    #[serde(default)]
+    pub downloads: i32,
 }
 /// GitHub API response for repository info
 #[derive(Debug, Deserialize)]
 pub struct GitHubRepo {
     pub owner: GitHubOwner,
     pub stargazers_count: i32,
+    #[serde(default, deserialize_with = "deserialize_license")]
     pub license: Option<GitHubLicense>,
     pub homepage: Option<String>,
     pub pushed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Not always `main`; absent in responses cached by older versions
+    #[serde(default)]
+    pub default_branch: Option<String>,
+    #[serde(default)]
+    pub fork: bool,
+    /// The repository a fork was made from
+    #[serde(default)]
+    pub parent: Option<GitHubParent>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitHubParent {
+    pub full_name: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,9 +118,37 @@ pub struct GitHubOwner {
     pub avatar_url: String,
 }
 
-#[derive(Debug, Deserialize)]
+/// GitHub API response item for a repository tag
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubTag {
+    pub name: String,
+    pub commit: GitHubTagCommit,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubTagCommit {
+    pub sha: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct GitHubLicense {
-    pub spdx_id: String,
+    #[serde(default)]
+    pub spdx_id: Option<String>,
+}
+
+/// Accepts whatever GitHub sends for `license`: null, an object, or (seen in
+/// the wild) an array of them. Anything unexpected becomes "unknown" (`None`)
+/// instead of failing the whole repository response.
+fn deserialize_license<'de, D>(deserializer: D) -> Result<Option<GitHubLicense>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    let license = match value {
+        serde_json::Value::Array(items) => items.into_iter().next(),
+        other => Some(other),
+    };
+    Ok(license.and_then(|v| serde_json::from_value::<GitHubLicense>(v).ok()))
 }
 /// Enriched package with GitHub metadata
 #[derive(Debug, Clone)]
@@ -54,10 +156,304 @@ pub struct EnrichedPackage {
     pub name: String,
     pub description: String,
     pub github_url: String,
+    /// Path of the package inside the repository, for monorepo packages
+    pub directory: Option<String>,
     pub owner_username: String,
     pub owner_avatar: String,
     pub stars: i32,
+    /// Whether the GitHub repository is a fork
+    pub fork: bool,
     pub license: Option<String>,
     pub homepage: Option<String>,
     pub last_commit_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub compiler_version: Option<String>,
+    /// Dependencies from Nargo.toml; None if the manifest couldn't be read,
+    /// in which case the ones already indexed are kept
+    pub dependencies: Option<Vec<ManifestDependency>>,
+    /// Category from the README heading; None keeps the stored one
+    pub category: Option<Category>,
+    /// When the GitHub metadata was fetched; an older fetch never overwrites
+    /// a newer one
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+}
+/// A `[dependencies]` entry from a package's Nargo.toml
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestDependency {
+    pub name: String,
+    pub git_url: Option<String>,
+    pub directory: Option<String>,
+    /// Tag the dependency is pinned to, for git dependencies that set one
+    pub tag: Option<String>,
+}
+/// A package record supplied to the admin import endpoint. The data is taken
+/// as-is; nothing is fetched from GitHub.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportPackage {
+    pub name: String,
+    pub description: Option<String>,
+    pub github_repository_url: String,
+    pub directory: Option<String>,
+    pub homepage: Option<String>,
+    pub license: Option<String>,
+    pub owner_github_username: String,
+    pub owner_avatar_url: Option<String>,
+    #[serde(default)]
+    pub github_stars: i32,
+    #[serde(default)]
+    pub total_downloads: i32,
+    pub latest_version: Option<String>,
+    pub last_commit_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Version history; commits already pinned in the registry are kept
+    #[serde(default)]
+    pub versions: Vec<PackageVersion>,
+}
+
+/// Outcome of importing one package record
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportResult {
+    pub name: String,
+    pub success: bool,
+    pub package_id: Option<i32>,
+    pub error: Option<String>,
+}
+
+/// An ownership transfer between two registered users
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageTransfer {
+    pub id: i32,
+    pub package_name: String,
+    pub from_github_username: String,
+    pub to_github_username: String,
+    pub status: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub resolved_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+/// A package name nobody may publish under
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockedName {
+    /// Normalized: lowercase, with `-` stored as `_`
+    pub name: String,
+    pub reason: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// How to reach whoever owns a package, for moderators handling abuse or
+/// DMCA reports
+#[derive(Debug, Clone, Serialize)]
+pub struct OwnerContact {
+    pub package: String,
+    pub owner_github_username: String,
+    /// Registry account that owns the package: its publisher, or else the
+    /// account of the GitHub owner. None if the owner never logged in
+    pub account_github_username: Option<String>,
+    /// The account's public GitHub email at its last login
+    pub email: Option<String>,
+}
+
+/// Progress of a scrape-and-enrich run started via the admin API
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrapeRun {
+    pub id: i32,
+    pub status: String,
+    pub packages_found: i32,
+    pub packages_enriched: i32,
+    pub packages_inserted: i32,
+    pub packages_failed: i32,
+    pub error: Option<String>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Envelope for offset-paginated listings. `next_cursor` is the offset of the
+/// next page, or absent on the last page.
+#[derive(Debug, Clone, Serialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Paginated<T> {
+    /// Wraps one page of an ordered result set of `total` items, fetched
+    /// with `limit` and `offset`
+    pub fn new(items: Vec<T>, total: i64, limit: i64, offset: i64) -> Self {
+        let next = offset + items.len() as i64;
+        Paginated {
+            next_cursor: (next < total && !items.is_empty()).then(|| next.to_string()),
+            items,
+            total,
+            limit,
+            offset,
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Paginated<T> {
+    fn into_response(self) -> Response {
+        Json(self).into_response()
+    }
+}
+
+/// Position in the `since` feed: the last package a client has seen, by
+/// `updated_at` and then id, so packages updated in the same instant are
+/// neither skipped nor repeated at a page boundary. Sent as `<rfc3339>_<id>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UpdatedCursor {
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub id: i32,
+}
+
+impl UpdatedCursor {
+    /// Starts the feed after `since`, before any package updated later
+    pub fn after(since: chrono::DateTime<chrono::Utc>) -> Self {
+        UpdatedCursor {
+            updated_at: since,
+            id: i32::MAX,
+        }
+    }
+
+    pub fn parse(cursor: &str) -> Option<Self> {
+        let (updated_at, id) = cursor.rsplit_once('_')?;
+        Some(UpdatedCursor {
+            updated_at: chrono::DateTime::parse_from_rfc3339(updated_at)
+                .ok()?
+                .with_timezone(&chrono::Utc),
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+impl std::fmt::Display for UpdatedCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}_{}",
+            self.updated_at
+                .to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+            self.id
+        )
+    }
+}
+
+/// Envelope for the `since` feed. `next_cursor` follows the last item and is
+/// absent only on an empty page; a page shorter than `limit` means the client
+/// has caught up, and can resume from `next_cursor` later.
+#[derive(Debug, Clone, Serialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub limit: i64,
+    pub next_cursor: Option<String>,
+}
+
+impl CursorPage<PackageResponse> {
+    pub fn new(items: Vec<PackageResponse>, limit: i64) -> Self {
+        let next_cursor = items.last().and_then(|p| {
+            let cursor = UpdatedCursor {
+                updated_at: p.updated_at?,
+                id: p.id,
+            };
+            Some(cursor.to_string())
+        });
+        CursorPage {
+            items,
+            limit,
+            next_cursor,
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for CursorPage<T> {
+    fn into_response(self) -> Response {
+        Json(self).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A repository response with the given raw `license` value
+    fn repo_with_license(license: &str) -> GitHubRepo {
+        let json = format!(
+            r#"{{
+                "owner": {{"login": "noir-lang", "avatar_url": "https://avatars.example.com/noir-lang"}},
+                "stargazers_count": 7,
+                "license": {},
+                "homepage": null,
+                "pushed_at": null
+            }}"#,
+            license
+        );
+        serde_json::from_str(&json).expect("repository response parses")
+    }
+
+    fn spdx(repo: &GitHubRepo) -> Option<&str> {
+        repo.license.as_ref().and_then(|l| l.spdx_id.as_deref())
+    }
+
+    #[test]
+    fn license_object_is_read() {
+        let repo = repo_with_license(r#"{"key": "mit", "spdx_id": "MIT"}"#);
+        assert_eq!(spdx(&repo), Some("MIT"));
+        assert_eq!(repo.stargazers_count, 7);
+    }
+
+    #[test]
+    fn null_license_is_unknown() {
+        assert!(repo_with_license("null").license.is_none());
+    }
+
+    #[test]
+    fn license_without_spdx_id_has_none() {
+        let repo = repo_with_license("{}");
+        assert!(repo.license.is_some());
+        assert_eq!(spdx(&repo), None);
+
+        let repo = repo_with_license(r#"{"spdx_id": null}"#);
+        assert!(repo.license.is_some());
+        assert_eq!(spdx(&repo), None);
+    }
+
+    #[test]
+    fn license_array_uses_the_first_entry() {
+        let repo = repo_with_license(r#"[{"spdx_id": "Apache-2.0"}, {"spdx_id": "MIT"}]"#);
+        assert_eq!(spdx(&repo), Some("Apache-2.0"));
+        assert!(repo_with_license("[]").license.is_none());
+    }
+
+    #[test]
+    fn unexpected_license_shape_is_unknown_not_an_error() {
+        assert!(repo_with_license(r#""MIT""#).license.is_none());
+        assert!(repo_with_license("42").license.is_none());
+    }
+
+    #[test]
+    fn missing_license_is_unknown() {
+        let repo: GitHubRepo = serde_json::from_str(
+            r#"{"owner": {"login": "a", "avatar_url": "b"}, "stargazers_count": 0,
+                "homepage": null, "pushed_at": null}"#,
+        )
+        .unwrap();
+        assert!(repo.license.is_none());
+    }
+
+    #[test]
+    fn fork_and_its_parent_are_read() {
+        let repo: GitHubRepo = serde_json::from_str(
+            r#"{"owner": {"login": "a", "avatar_url": "b"}, "stargazers_count": 0,
+                "homepage": null, "pushed_at": null, "fork": true,
+                "parent": {"full_name": "noir-lang/noir-bignum"}}"#,
+        )
+        .unwrap();
+        assert!(repo.fork);
+        assert_eq!(
+            repo.parent.map(|p| p.full_name).as_deref(),
+            Some("noir-lang/noir-bignum")
+        );
+        assert!(!repo_with_license("null").fork);
+    }
 }
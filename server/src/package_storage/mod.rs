@@ -1,46 +1,78 @@
-use crate::models::{EnrichedPackage, PackageResponse};
+use crate::auth::User;
+use crate::compiler_version;
+use crate::dependencies::normalize_git_url;
+use crate::github_metadata;
+use crate::models::{
+    BlockedName, Category, EnrichedPackage, GitHubRepo, ImportPackage, ImportResult,
+    ManifestDependency, OwnerContact, PackageResponse, PackageTransfer, PackageVersion, Paginated,
+    ScrapeRun, UpdatedCursor,
+};
+use crate::request_id;
 use anyhow::Result;
-use sqlx::Row;
+use sqlx::{Postgres, QueryBuilder, Row};
 use std::collections::HashMap;
 mod retry;
+mod timeout;
 use retry::retry_on_prepared_statement_error;
+pub use retry::{RetryBreakerState, retry_breaker_state};
+pub use timeout::{QueryTimeout, with_query_timeout};
 
-/// Escape SQL string for safe interpolation (doubles single quotes)
-pub fn escape_sql_string(s: &str) -> String {
-    s.replace('\'', "''")
+/// Blank descriptions are stored as NULL, so the API reports "no description"
+/// as `null` rather than `""`
+pub fn normalize_description(description: Option<&str>) -> Option<String> {
+    description
+        .filter(|d| !d.trim().is_empty())
+        .map(str::to_string)
 }
 
-/// Format an optional string as SQL: NULL or 'escaped value'
-fn sql_opt(opt: &Option<String>) -> String {
-    match opt {
-        None => "NULL".to_string(),
-        Some(s) => format!("'{}'", escape_sql_string(s)),
-    }
+/// Maps a `packages` row (with `max_compatible_nargo_version` and
+/// `latest_version_commit_sha`) to a response.
+/// Keywords are left empty; callers fill them in via `fetch_keywords_map`.
+fn row_to_package_response(row: sqlx::postgres::PgRow) -> Result<PackageResponse, sqlx::Error> {
+    Ok(PackageResponse {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        description: row.try_get("description")?,
+        github_repository_url: row.try_get("github_repository_url")?,
+        directory: row.try_get("directory")?,
+        homepage: row.try_get("homepage")?,
+        license: row.try_get("license")?,
+        owner_github_username: row.try_get("owner_github_username")?,
+        owner_avatar_url: row.try_get("owner_avatar_url")?,
+        total_downloads: row.try_get("total_downloads")?,
+        github_stars: row.try_get("github_stars")?,
+        latest_version: row.try_get("latest_version")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        last_commit_at: row.try_get("last_commit_at")?,
+        comparison_notes: row.try_get("comparison_notes")?,
+        max_compatible_nargo_version: row.try_get("max_compatible_nargo_version")?,
+        latest_version_commit_sha: row.try_get("latest_version_commit_sha")?,
+        compiler_version: row.try_get("compiler_version")?,
+        deprecated: row.try_get("deprecated")?,
+        deprecation_message: row.try_get("deprecation_message")?,
+        fork: row.try_get("is_fork")?,
+        keywords: vec![],
+        versions: None,
+    })
 }
 
 /// Fetches keywords for a batch of package IDs.
 /// Returns a map of package_id -> Vec<keyword>.
-/// Safe to interpolate: IDs are integers only.
-async fn fetch_keywords_map(
-    pool: &sqlx::PgPool,
-    ids: &[i32],
-) -> Result<HashMap<i32, Vec<String>>> {
+async fn fetch_keywords_map(pool: &sqlx::PgPool, ids: &[i32]) -> Result<HashMap<i32, Vec<String>>> {
     if ids.is_empty() {
         return Ok(HashMap::new());
     }
-    let ids_str = ids
-        .iter()
-        .map(|id| id.to_string())
-        .collect::<Vec<_>>()
-        .join(",");
-
-    let query = format!(
-        "SELECT package_id, keyword FROM package_keywords \
-         WHERE package_id IN ({}) ORDER BY keyword",
-        ids_str
-    );
-
-    let rows = sqlx::raw_sql(&query).fetch_all(pool).await?;
+    let rows = with_query_timeout(
+        sqlx::query(
+            "SELECT package_id, keyword FROM package_keywords
+             WHERE package_id = ANY($1) ORDER BY keyword",
+        )
+        .bind(ids)
+        .persistent(false)
+        .fetch_all(pool),
+    )
+    .await?;
 
     let mut map: HashMap<i32, Vec<String>> = HashMap::new();
     for row in rows {
@@ -51,83 +83,412 @@ async fn fetch_keywords_map(
     Ok(map)
 }
 
-/// Inserts an enriched package into the database
-pub async fn insert_package(pool: &sqlx::PgPool, pkg: &EnrichedPackage) -> Result<()> {
-    let last_commit = match &pkg.last_commit_at {
-        Some(dt) => format!("'{}'", dt.to_rfc3339()),
-        None => "NULL".to_string(),
+/// Inserts an enriched package into the database.
+///
+/// Safe to re-run: on conflict only the GitHub-derived columns are refreshed.
+/// `total_downloads`, `latest_version`, `comparison_notes` and keyword rows are
+/// never touched, and a homepage, license, last commit or compiler version that
+/// GitHub didn't return doesn't wipe the value already stored. Dependencies are
+/// re-indexed only when the manifest was read.
+///
+/// A fetch older than the one already stored (`fetched_at` before
+/// `metadata_fetched_at`) changes nothing and returns `false`, so a slow or
+/// out-of-order scrape can't regress fresher data. A star count of 0 doesn't
+/// replace a positive one either, since a partial GitHub response reads as 0.
+///
+/// Names that differ only by case or `-`/`_` are the same Noir package. If one
+/// is already stored under another spelling, the row is updated in place when
+/// it points at the same repository, and the insert fails (so the caller logs
+/// it) when it doesn't.
+pub async fn insert_package(pool: &sqlx::PgPool, pkg: &EnrichedPackage) -> Result<bool> {
+    let name = match find_name_collision(pool, &pkg.name).await? {
+        Some((existing, repo_url, directory))
+            if normalize_git_url(&repo_url) == normalize_git_url(&pkg.github_url)
+                && directory == pkg.directory =>
+        {
+            eprintln!(
+                "Name collision: '{}' is the same package as '{}', merging into it",
+                pkg.name, existing
+            );
+            existing
+        }
+        Some((existing, repo_url, _)) => {
+            anyhow::bail!(
+                "name collision: '{}' normalizes to the same name as '{}', \
+                 which points at a different repository ({})",
+                pkg.name,
+                existing,
+                repo_url
+            );
+        }
+        None => pkg.name.clone(),
     };
-    let sql = format!(
-        r#"INSERT INTO packages (
+
+    // No row back means the WHERE guard kept a fresher fetch
+    let rows = with_query_timeout(
+        sqlx::query(
+            r#"INSERT INTO packages (
             name, description, github_repository_url, homepage, license,
             owner_github_username, owner_avatar_url, github_stars, total_downloads,
-            last_commit_at
-        ) VALUES ('{}', '{}', '{}', {}, {}, '{}', '{}', {}, 0, {})
+            last_commit_at, compiler_version, directory, metadata_fetched_at, is_fork
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, 0, $9, $10, $11, $12, $13)
         ON CONFLICT (name) DO UPDATE SET
             description = EXCLUDED.description,
             github_repository_url = EXCLUDED.github_repository_url,
-            homepage = EXCLUDED.homepage,
-            license = EXCLUDED.license,
-            owner_github_username = EXCLUDED.owner_github_username,
-            owner_avatar_url = EXCLUDED.owner_avatar_url,
-            github_stars = EXCLUDED.github_stars,
-            last_commit_at = EXCLUDED.last_commit_at,
-            updated_at = CURRENT_TIMESTAMP"#,
-        escape_sql_string(&pkg.name),
-        escape_sql_string(&pkg.description),
-        escape_sql_string(&pkg.github_url),
-        sql_opt(&pkg.homepage),
-        sql_opt(&pkg.license),
-        escape_sql_string(&pkg.owner_username),
-        escape_sql_string(&pkg.owner_avatar),
-        pkg.stars,
-        last_commit,
-    );
-    sqlx::raw_sql(&sql).execute(pool).await?;
+            directory = EXCLUDED.directory,
+            homepage = COALESCE(EXCLUDED.homepage, packages.homepage),
+            license = COALESCE(EXCLUDED.license, packages.license),
+            -- A published or transferred package's owner is its registry
+            -- account, not whoever owns the GitHub repository
+            owner_github_username = CASE
+                WHEN packages.published_by IS NULL THEN EXCLUDED.owner_github_username
+                ELSE packages.owner_github_username
+            END,
+            owner_avatar_url = CASE
+                WHEN packages.published_by IS NULL THEN EXCLUDED.owner_avatar_url
+                ELSE packages.owner_avatar_url
+            END,
+            github_stars = CASE
+                WHEN EXCLUDED.github_stars = 0 THEN packages.github_stars
+                ELSE EXCLUDED.github_stars
+            END,
+            last_commit_at = COALESCE(EXCLUDED.last_commit_at, packages.last_commit_at),
+            compiler_version = COALESCE(EXCLUDED.compiler_version, packages.compiler_version),
+            metadata_fetched_at = EXCLUDED.metadata_fetched_at,
+            is_fork = EXCLUDED.is_fork,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE packages.metadata_fetched_at IS NULL
+            OR packages.metadata_fetched_at <= EXCLUDED.metadata_fetched_at
+        RETURNING id"#,
+        )
+        .bind(&name)
+        .bind(normalize_description(Some(&pkg.description)))
+        .bind(&pkg.github_url)
+        .bind(&pkg.homepage)
+        .bind(&pkg.license)
+        .bind(&pkg.owner_username)
+        .bind(&pkg.owner_avatar)
+        .bind(pkg.stars)
+        .bind(pkg.last_commit_at)
+        .bind(&pkg.compiler_version)
+        .bind(&pkg.directory)
+        .bind(pkg.fetched_at)
+        .bind(pkg.fork)
+        .persistent(false)
+        .fetch_all(pool),
+    )
+    .await?;
+    let Some(row) = rows.first() else {
+        return Ok(false);
+    };
+    let package_id: i32 = row.try_get("id")?;
+
+    if let Some(dependencies) = &pkg.dependencies {
+        save_dependencies(pool, package_id, dependencies).await?;
+    }
+    if let Some(category) = &pkg.category {
+        save_category(pool, package_id, category).await?;
+    }
+    Ok(true)
+}
+
+/// Files a package under a category, creating the category on first use.
+/// A later heading with the same slug updates the category's display name.
+pub async fn save_category(
+    pool: &sqlx::PgPool,
+    package_id: i32,
+    category: &Category,
+) -> Result<()> {
+    with_query_timeout(
+        sqlx::query(
+            "WITH category AS (
+                 INSERT INTO categories (name, slug) VALUES ($2, $3)
+                 ON CONFLICT (slug) DO UPDATE SET name = EXCLUDED.name
+                 RETURNING id
+             )
+             INSERT INTO package_categories (package_id, category_id)
+             SELECT $1, id FROM category
+             ON CONFLICT (package_id) DO UPDATE SET category_id = EXCLUDED.category_id",
+        )
+        .bind(package_id)
+        .bind(&category.name)
+        .bind(&category.slug)
+        .persistent(false)
+        .execute(pool),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Finds a package stored under a different spelling of `name` (case or
+/// `-`/`_`), returning its name, repository URL and directory
+async fn find_name_collision(
+    pool: &sqlx::PgPool,
+    name: &str,
+) -> Result<Option<(String, String, Option<String>)>> {
+    let row = with_query_timeout(
+        sqlx::query(
+            "SELECT name, github_repository_url, directory FROM packages
+             WHERE LOWER(REPLACE(name, '-', '_')) = LOWER(REPLACE($1, '-', '_'))
+               AND name <> $1
+             ORDER BY id
+             LIMIT 1",
+        )
+        .bind(name)
+        .persistent(false)
+        .fetch_optional(pool),
+    )
+    .await?;
+
+    row.map(|r| {
+        Ok((
+            r.try_get("name")?,
+            r.try_get("github_repository_url")?,
+            r.try_get("directory")?,
+        ))
+    })
+    .transpose()
+}
+
+/// Replaces the indexed dependencies of a package
+pub async fn save_dependencies(
+    pool: &sqlx::PgPool,
+    package_id: i32,
+    dependencies: &[ManifestDependency],
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    with_query_timeout(
+        sqlx::query("DELETE FROM package_dependencies WHERE package_id = $1")
+            .bind(package_id)
+            .persistent(false)
+            .execute(&mut *tx),
+    )
+    .await?;
+
+    for dep in dependencies {
+        with_query_timeout(
+            sqlx::query(
+                "INSERT INTO package_dependencies (package_id, dependency_name, git_url, directory, tag)
+                 VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT DO NOTHING",
+            )
+            .bind(package_id)
+            .bind(&dep.name)
+            .bind(dep.git_url.as_deref())
+            .bind(dep.directory.as_deref())
+            .bind(dep.tag.as_deref())
+            .persistent(false)
+            .execute(&mut *tx),
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
     Ok(())
 }
 
-/// Retrieves all packages from the database
+/// The dependencies indexed from `name`'s Nargo.toml, alphabetically.
+/// Empty if the package doesn't exist or its manifest was never read.
+pub async fn get_dependencies(pool: &sqlx::PgPool, name: &str) -> Result<Vec<ManifestDependency>> {
+    let rows = with_query_timeout(
+        sqlx::query(
+            "SELECT d.dependency_name, d.git_url, d.directory, d.tag
+             FROM package_dependencies d
+             JOIN packages p ON p.id = d.package_id
+             WHERE p.name = $1
+             ORDER BY d.dependency_name",
+        )
+        .bind(name)
+        .persistent(false)
+        .fetch_all(pool),
+    )
+    .await?;
+
+    rows.into_iter()
+        .map(|r| {
+            Ok(ManifestDependency {
+                name: r.try_get("dependency_name")?,
+                git_url: r.try_get("git_url")?,
+                directory: r.try_get("directory")?,
+                tag: r.try_get("tag")?,
+            })
+        })
+        .collect()
+}
+
+/// Names of the registry packages whose Nargo.toml depends on `name`'s
+/// repository (and directory, for monorepo packages), alphabetically
+pub async fn get_dependents(pool: &sqlx::PgPool, name: &str) -> Result<Vec<String>> {
+    let rows = with_query_timeout(
+        sqlx::query(
+            "SELECT DISTINCT dependent.name
+             FROM packages target
+             JOIN package_dependencies d
+               ON d.git_url = LOWER(RTRIM(target.github_repository_url, '/'))
+              AND d.directory IS NOT DISTINCT FROM target.directory
+             JOIN packages dependent ON dependent.id = d.package_id
+             WHERE target.name = $1 AND dependent.id <> target.id
+             ORDER BY dependent.name",
+        )
+        .bind(name)
+        .persistent(false)
+        .fetch_all(pool),
+    )
+    .await?;
+
+    rows.into_iter()
+        .map(|r| r.try_get("name").map_err(Into::into))
+        .collect()
+}
+
+/// Retrieves all packages from the database.
+///
+/// Every package listing uses the same order: stars descending, then name,
+/// then id, so the order is total and stable across requests.
 pub async fn get_all_packages(pool: &sqlx::PgPool) -> Result<Vec<PackageResponse>> {
     retry_on_prepared_statement_error(|| async {
-        let rows = sqlx::raw_sql(
+        let rows = with_query_timeout(
+            sqlx::raw_sql(
+                r#"SELECT
+                    id, name, description, github_repository_url, homepage, license,
+                    owner_github_username, owner_avatar_url, total_downloads, github_stars,
+                    latest_version, created_at, updated_at,
+                    last_commit_at, comparison_notes, compiler_version, directory,
+                    deprecated, deprecation_message, is_fork,
+                    (SELECT nargo_version FROM package_compat_results
+                     WHERE package_id = packages.id AND status = 'ok'
+                     ORDER BY nargo_version DESC LIMIT 1) AS max_compatible_nargo_version,
+                    (SELECT commit_sha FROM package_versions
+                     WHERE package_id = packages.id AND version = packages.latest_version) AS latest_version_commit_sha
+                FROM packages
+                ORDER BY github_stars DESC, name ASC, id ASC"#,
+            )
+            .fetch_all(pool),
+        )
+        .await?;
+
+        let packages: Vec<PackageResponse> = rows
+            .into_iter()
+            .map(row_to_package_response)
+            .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+        let ids: Vec<i32> = packages.iter().map(|p| p.id).collect();
+        let mut keywords_map = fetch_keywords_map(pool, &ids).await?;
+        let packages = packages
+            .into_iter()
+            .map(|mut p| {
+                p.keywords = keywords_map.remove(&p.id).unwrap_or_default();
+                p
+            })
+            .collect();
+
+        Ok(packages)
+    })
+    .await
+}
+
+/// One page of the package listing (same order as `get_all_packages`), with
+/// the total number of packages. `keyword` keeps only packages carrying it;
+/// `compatible` (see `compatible_requirements`) keeps only packages without a
+/// `compiler_version` or with one of those. Both apply before the page is cut.
+pub async fn get_packages_page(
+    pool: &sqlx::PgPool,
+    keyword: Option<&str>,
+    compatible: Option<&[String]>,
+    limit: i64,
+    offset: i64,
+) -> Result<Paginated<PackageResponse>> {
+    const CONDITIONS: &str = "($1::text IS NULL
+            OR id IN (SELECT package_id FROM package_keywords WHERE keyword = $1))
+          AND ($2::text[] IS NULL OR compiler_version IS NULL OR compiler_version = ANY($2))";
+
+    let total_row = with_query_timeout(
+        sqlx::query(&format!(
+            "SELECT COUNT(*) AS total FROM packages WHERE {}",
+            CONDITIONS
+        ))
+        .bind(keyword)
+        .bind(compatible)
+        .persistent(false)
+        .fetch_one(pool),
+    )
+    .await?;
+    let total: i64 = total_row.try_get("total")?;
+
+    let rows = with_query_timeout(
+        sqlx::query(&format!(
             r#"SELECT
                 id, name, description, github_repository_url, homepage, license,
                 owner_github_username, owner_avatar_url, total_downloads, github_stars,
                 latest_version, created_at, updated_at,
-                last_commit_at, comparison_notes,
+                last_commit_at, comparison_notes, compiler_version, directory,
+                deprecated, deprecation_message, is_fork,
                 (SELECT nargo_version FROM package_compat_results
                  WHERE package_id = packages.id AND status = 'ok'
-                 ORDER BY nargo_version DESC LIMIT 1) AS max_compatible_nargo_version
+                 ORDER BY nargo_version DESC LIMIT 1) AS max_compatible_nargo_version,
+                (SELECT commit_sha FROM package_versions
+                 WHERE package_id = packages.id AND version = packages.latest_version) AS latest_version_commit_sha
             FROM packages
-            ORDER BY github_stars DESC, name ASC"#,
+            WHERE {}
+            ORDER BY github_stars DESC, name ASC, id ASC
+            LIMIT $3 OFFSET $4"#,
+            CONDITIONS
+        ))
+        .bind(keyword)
+        .bind(compatible)
+        .bind(limit)
+        .bind(offset)
+        .persistent(false)
+        .fetch_all(pool),
+    )
+    .await?;
+
+    let packages: Vec<PackageResponse> = rows
+        .into_iter()
+        .map(row_to_package_response)
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+    let ids: Vec<i32> = packages.iter().map(|p| p.id).collect();
+    let mut keywords_map = fetch_keywords_map(pool, &ids).await?;
+    let items = packages
+        .into_iter()
+        .map(|mut p| {
+            p.keywords = keywords_map.remove(&p.id).unwrap_or_default();
+            p
+        })
+        .collect();
+
+    Ok(Paginated::new(items, total, limit, offset))
+}
+
+/// Featured packages in their curated order (see `set_featured`)
+pub async fn get_featured(pool: &sqlx::PgPool) -> Result<Vec<PackageResponse>> {
+    retry_on_prepared_statement_error(|| async {
+        let rows = with_query_timeout(
+            sqlx::query(
+                r#"SELECT
+                    id, name, description, github_repository_url, homepage, license,
+                    owner_github_username, owner_avatar_url, total_downloads, github_stars,
+                    latest_version, created_at, updated_at,
+                    last_commit_at, comparison_notes, compiler_version, directory,
+                    deprecated, deprecation_message, is_fork,
+                    (SELECT nargo_version FROM package_compat_results
+                     WHERE package_id = packages.id AND status = 'ok'
+                     ORDER BY nargo_version DESC LIMIT 1) AS max_compatible_nargo_version,
+                    (SELECT commit_sha FROM package_versions
+                     WHERE package_id = packages.id AND version = packages.latest_version) AS latest_version_commit_sha
+                FROM packages
+                WHERE featured_rank IS NOT NULL
+                ORDER BY featured_rank ASC"#,
+            )
+            .persistent(false)
+            .fetch_all(pool),
         )
-        .fetch_all(pool)
         .await?;
 
         let packages: Vec<PackageResponse> = rows
             .into_iter()
-            .map(|row| {
-                Ok(PackageResponse {
-                    id: row.try_get("id")?,
-                    name: row.try_get("name")?,
-                    description: row.try_get("description")?,
-                    github_repository_url: row.try_get("github_repository_url")?,
-                    homepage: row.try_get("homepage")?,
-                    license: row.try_get("license")?,
-                    owner_github_username: row.try_get("owner_github_username")?,
-                    owner_avatar_url: row.try_get("owner_avatar_url")?,
-                    total_downloads: row.try_get("total_downloads")?,
-                    github_stars: row.try_get("github_stars")?,
-                    latest_version: row.try_get("latest_version")?,
-                    created_at: row.try_get("created_at")?,
-                    updated_at: row.try_get("updated_at")?,
-                    last_commit_at: row.try_get("last_commit_at")?,
-                    comparison_notes: row.try_get("comparison_notes")?,
-                    max_compatible_nargo_version: row.try_get("max_compatible_nargo_version")?,
-                    keywords: vec![],
-                })
-            })
+            .map(row_to_package_response)
             .collect::<Result<Vec<_>, sqlx::Error>>()?;
 
         let ids: Vec<i32> = packages.iter().map(|p| p.id).collect();
@@ -145,49 +506,84 @@ pub async fn get_all_packages(pool: &sqlx::PgPool) -> Result<Vec<PackageResponse
     .await
 }
 
+/// Replaces the featured list with `names`, ranked in the given order. If any
+/// name isn't a package, nothing changes and those names are returned.
+pub async fn set_featured(pool: &sqlx::PgPool, names: &[String]) -> Result<Vec<String>> {
+    let mut tx = pool.begin().await?;
+
+    let rows = with_query_timeout(
+        sqlx::query("SELECT name FROM packages WHERE name = ANY($1)")
+            .bind(names)
+            .persistent(false)
+            .fetch_all(&mut *tx),
+    )
+    .await?;
+    let found: std::collections::HashSet<String> = rows
+        .iter()
+        .map(|r| r.try_get("name"))
+        .collect::<Result<_, sqlx::Error>>()?;
+    let missing: Vec<String> = names
+        .iter()
+        .filter(|n| !found.contains(*n))
+        .cloned()
+        .collect();
+    if !missing.is_empty() {
+        return Ok(missing);
+    }
+
+    with_query_timeout(
+        sqlx::query("UPDATE packages SET featured_rank = NULL WHERE featured_rank IS NOT NULL")
+            .persistent(false)
+            .execute(&mut *tx),
+    )
+    .await?;
+    // WITH ORDINALITY numbers the names 1..n in the order given
+    with_query_timeout(
+        sqlx::query(
+            "UPDATE packages p SET featured_rank = f.rank
+             FROM UNNEST($1::text[]) WITH ORDINALITY AS f(name, rank)
+             WHERE p.name = f.name",
+        )
+        .bind(names)
+        .persistent(false)
+        .execute(&mut *tx),
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(Vec::new())
+}
+
 /// Get a single package by name
 pub async fn get_package_by_name(
     pool: &sqlx::PgPool,
     name: &str,
 ) -> Result<Option<PackageResponse>> {
     retry_on_prepared_statement_error(|| async {
-        let escaped_name = escape_sql_string(name);
-        let query = format!(
-            r#"SELECT
+        let row = with_query_timeout(
+            sqlx::query(
+                r#"SELECT
                 id, name, description, github_repository_url, homepage, license,
                 owner_github_username, owner_avatar_url, total_downloads, github_stars,
                 latest_version, created_at, updated_at,
-                last_commit_at, comparison_notes,
+                last_commit_at, comparison_notes, compiler_version, directory,
+                deprecated, deprecation_message, is_fork,
                 (SELECT nargo_version FROM package_compat_results
                  WHERE package_id = packages.id AND status = 'ok'
-                 ORDER BY nargo_version DESC LIMIT 1) AS max_compatible_nargo_version
-            FROM packages WHERE name = '{}'"#,
-            escaped_name
-        );
-
-        let row = sqlx::raw_sql(&query).fetch_all(pool).await?.into_iter().next();
+                 ORDER BY nargo_version DESC LIMIT 1) AS max_compatible_nargo_version,
+                (SELECT commit_sha FROM package_versions
+                 WHERE package_id = packages.id AND version = packages.latest_version) AS latest_version_commit_sha
+            FROM packages WHERE name = $1"#,
+            )
+            .bind(name)
+            .persistent(false)
+            .fetch_optional(pool),
+        )
+        .await?;
 
         match row {
             Some(row) => {
-                let mut pkg = PackageResponse {
-                    id: row.try_get("id")?,
-                    name: row.try_get("name")?,
-                    description: row.try_get("description")?,
-                    github_repository_url: row.try_get("github_repository_url")?,
-                    homepage: row.try_get("homepage")?,
-                    license: row.try_get("license")?,
-                    owner_github_username: row.try_get("owner_github_username")?,
-                    owner_avatar_url: row.try_get("owner_avatar_url")?,
-                    total_downloads: row.try_get("total_downloads")?,
-                    github_stars: row.try_get("github_stars")?,
-                    latest_version: row.try_get("latest_version")?,
-                    created_at: row.try_get("created_at")?,
-                    updated_at: row.try_get("updated_at")?,
-                    last_commit_at: row.try_get("last_commit_at")?,
-                    comparison_notes: row.try_get("comparison_notes")?,
-                    max_compatible_nargo_version: row.try_get("max_compatible_nargo_version")?,
-                    keywords: vec![],
-                };
+                let mut pkg = row_to_package_response(row)?;
                 let mut map = fetch_keywords_map(pool, &[pkg.id]).await?;
                 pkg.keywords = map.remove(&pkg.id).unwrap_or_default();
                 Ok(Some(pkg))
@@ -198,66 +594,289 @@ pub async fn get_package_by_name(
     .await
 }
 
-/// Search packages by name, description, or keywords
-pub async fn search_packages(pool: &sqlx::PgPool, query: &str) -> Result<Vec<PackageResponse>> {
-    retry_on_prepared_statement_error(|| async {
-        let escaped_query = escape_sql_string(query);
-        let search_pattern = format!("%{}%", escaped_query);
-        let search_prefix = format!("{}%", escaped_query);
+/// The package named `name`, compared the way names collide: ignoring case
+/// and `-`/`_`. A package spelled exactly as given wins over another spelling.
+pub async fn find_package_by_exact_name(
+    pool: &sqlx::PgPool,
+    name: &str,
+) -> Result<Option<PackageResponse>> {
+    let row = with_query_timeout(
+        sqlx::query(
+            "SELECT name FROM packages
+             WHERE LOWER(REPLACE(name, '-', '_')) = LOWER(REPLACE($1, '-', '_'))
+             ORDER BY name = $1 DESC, id
+             LIMIT 1",
+        )
+        .bind(name.trim())
+        .persistent(false)
+        .fetch_optional(pool),
+    )
+    .await?;
+    match row {
+        Some(row) => {
+            let name: String = row.try_get("name")?;
+            get_package_by_name(pool, &name).await
+        }
+        None => Ok(None),
+    }
+}
+
+/// Most results a search returns, so a short query that matches most of the
+/// table can't pull all of it into memory. Only the first ones in the
+/// requested order are kept.
+pub const SEARCH_RESULT_CAP: usize = 200;
+
+/// Orderings a search can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchSort {
+    Relevance,
+    Stars,
+    Downloads,
+    Name,
+}
+
+impl SearchSort {
+    /// ORDER BY for the search query. Ties fall back to relevance, then the
+    /// listing order, so the order is total.
+    fn order_by(self) -> &'static str {
+        match self {
+            SearchSort::Relevance => "relevance, p.github_stars DESC, p.name ASC, p.id ASC",
+            SearchSort::Stars => "p.github_stars DESC, relevance, p.name ASC, p.id ASC",
+            SearchSort::Downloads => {
+                "p.total_downloads DESC, relevance, p.github_stars DESC, p.name ASC, p.id ASC"
+            }
+            SearchSort::Name => "p.name ASC, p.id ASC",
+        }
+    }
+}
+
+/// Narrows a search; every given filter must hold. Applied in the query,
+/// before the cap.
+#[derive(Debug, Default)]
+pub struct SearchFilters {
+    /// License, compared case-insensitively
+    pub license: Option<String>,
+    pub min_stars: Option<i32>,
+    /// Keywords a package must all carry
+    pub keywords: Vec<String>,
+}
+
+impl SearchFilters {
+    /// Pushes `AND ...` conditions on `p`, nothing when no filter is set
+    fn push_sql(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        if let Some(license) = &self.license {
+            qb.push(" AND LOWER(p.license) = LOWER(")
+                .push_bind(license.trim().to_string())
+                .push(")");
+        }
+        if let Some(min_stars) = self.min_stars {
+            qb.push(" AND p.github_stars >= ").push_bind(min_stars);
+        }
+        let mut keywords: Vec<String> = self
+            .keywords
+            .iter()
+            .map(|k| k.trim().to_lowercase())
+            .filter(|k| !k.is_empty())
+            .collect();
+        keywords.sort();
+        keywords.dedup();
+        if !keywords.is_empty() {
+            let count = keywords.len() as i64;
+            qb.push(
+                " AND p.id IN (SELECT package_id FROM package_keywords
+                    WHERE keyword = ANY(",
+            )
+            .push_bind(keywords)
+            .push(") GROUP BY package_id HAVING COUNT(DISTINCT keyword) = ")
+            .push_bind(count)
+            .push(")");
+        }
+    }
+}
 
-        let sql_query = format!(
+/// Search matches, cut at [`SEARCH_RESULT_CAP`]
+#[derive(Debug)]
+pub struct SearchResults {
+    pub packages: Vec<PackageResponse>,
+    /// More packages matched than were returned
+    pub truncated: bool,
+}
+
+/// Search packages by name, owner, description, or keywords.
+/// By relevance, name matches rank first, then owner matches, then
+/// description matches; ties fall back to the listing order (stars, name, id).
+/// The filters and sort are applied in the query, so the cap keeps the first
+/// [`SEARCH_RESULT_CAP`] matching packages in that order.
+pub async fn search_packages(
+    pool: &sqlx::PgPool,
+    query: &str,
+    filters: &SearchFilters,
+    sort: SearchSort,
+) -> Result<SearchResults> {
+    search_capped(pool, query, None, filters, sort).await
+}
+
+/// One page of `search_packages`, with the total number of matches. Pages
+/// are cut in the query, so they reach past the cap.
+pub async fn search_packages_page(
+    pool: &sqlx::PgPool,
+    query: &str,
+    filters: &SearchFilters,
+    sort: SearchSort,
+    limit: i64,
+    offset: i64,
+) -> Result<Paginated<PackageResponse>> {
+    search_page(pool, query, None, filters, sort, limit, offset).await
+}
+
+/// Same search and ranking as `search_packages`, limited to one owner's
+/// packages (owner compared case-insensitively)
+pub async fn search_owner_packages(
+    pool: &sqlx::PgPool,
+    owner: &str,
+    query: &str,
+    sort: SearchSort,
+) -> Result<SearchResults> {
+    search_capped(pool, query, Some(owner), &SearchFilters::default(), sort).await
+}
+
+/// One page of `search_owner_packages`, like `search_packages_page`
+pub async fn search_owner_packages_page(
+    pool: &sqlx::PgPool,
+    owner: &str,
+    query: &str,
+    sort: SearchSort,
+    limit: i64,
+    offset: i64,
+) -> Result<Paginated<PackageResponse>> {
+    let filters = SearchFilters::default();
+    search_page(pool, query, Some(owner), &filters, sort, limit, offset).await
+}
+
+async fn search_capped(
+    pool: &sqlx::PgPool,
+    query: &str,
+    owner: Option<&str>,
+    filters: &SearchFilters,
+    sort: SearchSort,
+) -> Result<SearchResults> {
+    // One extra row tells us the cap was hit
+    let limit = SEARCH_RESULT_CAP as i64 + 1;
+    let mut packages = search_rows(pool, query, owner, filters, sort, limit, 0).await?;
+    let truncated = packages.len() > SEARCH_RESULT_CAP;
+    if truncated {
+        eprintln!(
+            "Search for '{}' matched more than {} packages; returning only the top {}",
+            query, SEARCH_RESULT_CAP, SEARCH_RESULT_CAP
+        );
+        packages.truncate(SEARCH_RESULT_CAP);
+    }
+    Ok(SearchResults {
+        packages,
+        truncated,
+    })
+}
+
+async fn search_page(
+    pool: &sqlx::PgPool,
+    query: &str,
+    owner: Option<&str>,
+    filters: &SearchFilters,
+    sort: SearchSort,
+    limit: i64,
+    offset: i64,
+) -> Result<Paginated<PackageResponse>> {
+    let items = search_rows(pool, query, owner, filters, sort, limit, offset).await?;
+    let total = retry_on_prepared_statement_error(|| async {
+        let mut qb = QueryBuilder::new(
+            "SELECT COUNT(DISTINCT p.id) AS total
+            FROM packages p
+            LEFT JOIN package_keywords pk ON p.id = pk.package_id
+            WHERE ",
+        );
+        push_search_conditions(&mut qb, query, owner, filters);
+        let row = with_query_timeout(qb.build().persistent(false).fetch_one(pool)).await?;
+        Ok(row.try_get::<i64, _>("total")?)
+    })
+    .await?;
+    Ok(Paginated::new(items, total, limit, offset))
+}
+
+/// Pushes the WHERE conditions of a search over `packages p` left-joined
+/// with `package_keywords pk`
+fn push_search_conditions(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    query: &str,
+    owner: Option<&str>,
+    filters: &SearchFilters,
+) {
+    let search_pattern = format!("%{}%", query);
+    qb.push("(p.name ILIKE ")
+        .push_bind(search_pattern.clone())
+        .push(" OR p.owner_github_username ILIKE ")
+        .push_bind(search_pattern.clone())
+        .push(" OR p.description ILIKE ")
+        .push_bind(search_pattern.clone())
+        .push(" OR pk.keyword ILIKE ")
+        .push_bind(search_pattern)
+        .push(")");
+    if let Some(owner) = owner {
+        qb.push(" AND LOWER(p.owner_github_username) = LOWER(")
+            .push_bind(owner.to_string())
+            .push(")");
+    }
+    filters.push_sql(qb);
+}
+
+async fn search_rows(
+    pool: &sqlx::PgPool,
+    query: &str,
+    owner: Option<&str>,
+    filters: &SearchFilters,
+    sort: SearchSort,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<PackageResponse>> {
+    retry_on_prepared_statement_error(|| async {
+        let mut qb = QueryBuilder::new(
             r#"SELECT DISTINCT
                 p.id, p.name, p.description, p.github_repository_url, p.homepage, p.license,
                 p.owner_github_username, p.owner_avatar_url, p.total_downloads, p.github_stars,
                 p.latest_version, p.created_at, p.updated_at,
-                p.last_commit_at, p.comparison_notes,
+                p.last_commit_at, p.comparison_notes, p.compiler_version, p.directory,
+                p.deprecated, p.deprecation_message, p.is_fork,
                 (SELECT nargo_version FROM package_compat_results
                  WHERE package_id = p.id AND status = 'ok'
                  ORDER BY nargo_version DESC LIMIT 1) AS max_compatible_nargo_version,
+                (SELECT commit_sha FROM package_versions
+                 WHERE package_id = p.id AND version = p.latest_version) AS latest_version_commit_sha,
                 CASE
-                    WHEN p.name ILIKE '{prefix}' THEN 1
-                    WHEN p.description ILIKE '{prefix}' THEN 2
-                    ELSE 3
-                END AS relevance
+                    WHEN p.name ILIKE "#,
+        );
+        qb.push_bind(format!("{}%", query))
+            .push(" THEN 1 WHEN p.owner_github_username ILIKE ")
+            .push_bind(format!("%{}%", query))
+            .push(" THEN 2 WHEN p.description ILIKE ")
+            .push_bind(format!("{}%", query))
+            .push(
+                " THEN 3 ELSE 4 END AS relevance
             FROM packages p
             LEFT JOIN package_keywords pk ON p.id = pk.package_id
-            WHERE
-                p.name ILIKE '{pat}'
-                OR p.description ILIKE '{pat}'
-                OR pk.keyword ILIKE '{pat}'
-            ORDER BY
-                relevance,
-                p.github_stars DESC,
-                p.name ASC"#,
-            pat = search_pattern,
-            prefix = search_prefix
-        );
+            WHERE ",
+            );
+        push_search_conditions(&mut qb, query, owner, filters);
+        qb.push(" ORDER BY ")
+            .push(sort.order_by())
+            .push(" LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
 
-        let rows = sqlx::raw_sql(&sql_query).fetch_all(pool).await?;
+        let rows = with_query_timeout(qb.build().persistent(false).fetch_all(pool)).await?;
 
         let packages: Vec<PackageResponse> = rows
             .into_iter()
-            .map(|row| {
-                Ok(PackageResponse {
-                    id: row.try_get("id")?,
-                    name: row.try_get("name")?,
-                    description: row.try_get("description")?,
-                    github_repository_url: row.try_get("github_repository_url")?,
-                    homepage: row.try_get("homepage")?,
-                    license: row.try_get("license")?,
-                    owner_github_username: row.try_get("owner_github_username")?,
-                    owner_avatar_url: row.try_get("owner_avatar_url")?,
-                    total_downloads: row.try_get("total_downloads")?,
-                    github_stars: row.try_get("github_stars")?,
-                    latest_version: row.try_get("latest_version")?,
-                    created_at: row.try_get("created_at")?,
-                    updated_at: row.try_get("updated_at")?,
-                    last_commit_at: row.try_get("last_commit_at")?,
-                    comparison_notes: row.try_get("comparison_notes")?,
-                    max_compatible_nargo_version: row.try_get("max_compatible_nargo_version")?,
-                    keywords: vec![],
-                })
-            })
+            .map(row_to_package_response)
             .collect::<Result<Vec<_>, sqlx::Error>>()?;
 
         let ids: Vec<i32> = packages.iter().map(|p| p.id).collect();
@@ -275,54 +894,396 @@ pub async fn search_packages(pool: &sqlx::PgPool, query: &str) -> Result<Vec<Pac
     .await
 }
 
+/// Applies freshly fetched GitHub metadata to a published package. License and
+/// homepage given by the publisher win over GitHub's; GitHub's homepage is
+/// normalized like a scraped one. As in `insert_package`, 0 stars don't
+/// replace a positive count, and the fetch time is recorded so an older
+/// scrape can't undo this.
+pub async fn update_github_metadata(
+    pool: &sqlx::PgPool,
+    package_id: i32,
+    repo: &GitHubRepo,
+    compiler_version: Option<&str>,
+) -> Result<()> {
+    with_query_timeout(
+        sqlx::query(
+            "UPDATE packages SET
+                github_stars = CASE WHEN $2 = 0 THEN github_stars ELSE $2 END,
+                owner_avatar_url = $3,
+                license = COALESCE(license, $4),
+                homepage = COALESCE(homepage, $5),
+                last_commit_at = COALESCE($6, last_commit_at),
+                compiler_version = COALESCE($7, compiler_version),
+                metadata_fetched_at = CURRENT_TIMESTAMP
+             WHERE id = $1",
+        )
+        .bind(package_id)
+        .bind(repo.stargazers_count)
+        .bind(&repo.owner.avatar_url)
+        .bind(repo.license.as_ref().and_then(|l| l.spdx_id.as_deref()))
+        .bind(github_metadata::normalize_homepage(repo.homepage.clone()))
+        .bind(repo.pushed_at)
+        .bind(compiler_version)
+        .persistent(false)
+        .execute(pool),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Cheap existence check, without loading the package record
+pub async fn package_exists(pool: &sqlx::PgPool, name: &str) -> Result<bool> {
+    let row = with_query_timeout(
+        sqlx::query("SELECT 1 FROM packages WHERE name = $1")
+            .bind(name)
+            .persistent(false)
+            .fetch_optional(pool),
+    )
+    .await?;
+    Ok(row.is_some())
+}
+
+/// The registry account a package belongs to (its publisher, or whoever it
+/// was transferred to). None for an unknown or scraped-only package.
+pub async fn get_publisher(pool: &sqlx::PgPool, name: &str) -> Result<Option<i32>> {
+    let row = with_query_timeout(
+        sqlx::query("SELECT published_by FROM packages WHERE name = $1")
+            .bind(name)
+            .persistent(false)
+            .fetch_optional(pool),
+    )
+    .await?;
+    Ok(match row {
+        Some(row) => row.try_get("published_by")?,
+        None => None,
+    })
+}
+
+/// Names of packages that look like `name` (trigram similarity), best match
+/// first. Used to offer "did you mean" hints when a lookup misses.
+pub async fn suggest_similar(pool: &sqlx::PgPool, name: &str) -> Result<Vec<String>> {
+    let rows = with_query_timeout(
+        sqlx::query(
+            "SELECT name FROM packages
+             WHERE similarity(name, $1) > 0.3
+             ORDER BY similarity(name, $1) DESC, name ASC
+             LIMIT 5",
+        )
+        .bind(name)
+        .persistent(false)
+        .fetch_all(pool),
+    )
+    .await?;
+
+    rows.into_iter()
+        .map(|r| r.try_get("name").map_err(Into::into))
+        .collect()
+}
+
+/// Imports package records in a single transaction, overwriting any existing
+/// package with the same name. Each record runs in its own savepoint, so a bad
+/// record is reported in its result without aborting the rest.
+pub async fn import_packages(
+    pool: &sqlx::PgPool,
+    packages: &[ImportPackage],
+) -> Result<Vec<ImportResult>> {
+    let mut tx = pool.begin().await?;
+    let mut results = Vec::with_capacity(packages.len());
+
+    for pkg in packages {
+        let mut savepoint = sqlx::Connection::begin(&mut *tx).await?;
+        match import_one(&mut savepoint, pkg).await {
+            Ok(package_id) => {
+                savepoint.commit().await?;
+                results.push(ImportResult {
+                    name: pkg.name.clone(),
+                    success: true,
+                    package_id: Some(package_id),
+                    error: None,
+                });
+            }
+            Err(e) => {
+                savepoint.rollback().await?;
+                results.push(ImportResult {
+                    name: pkg.name.clone(),
+                    success: false,
+                    package_id: None,
+                    error: Some(e.to_string()),
+                });
+            }
+        }
+    }
+
+    tx.commit().await?;
+    Ok(results)
+}
+
+async fn import_one(conn: &mut sqlx::PgConnection, pkg: &ImportPackage) -> Result<i32> {
+    let row = with_query_timeout(
+        sqlx::query(
+            "INSERT INTO packages (
+                name, description, github_repository_url, homepage, license,
+                owner_github_username, owner_avatar_url, github_stars, total_downloads,
+                latest_version, last_commit_at, directory, source
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, 'import')
+            ON CONFLICT (name) DO UPDATE SET
+                description = EXCLUDED.description,
+                github_repository_url = EXCLUDED.github_repository_url,
+                homepage = EXCLUDED.homepage,
+                license = EXCLUDED.license,
+                owner_github_username = EXCLUDED.owner_github_username,
+                owner_avatar_url = EXCLUDED.owner_avatar_url,
+                github_stars = EXCLUDED.github_stars,
+                total_downloads = EXCLUDED.total_downloads,
+                latest_version = EXCLUDED.latest_version,
+                last_commit_at = EXCLUDED.last_commit_at,
+                directory = EXCLUDED.directory,
+                updated_at = CURRENT_TIMESTAMP
+            RETURNING id",
+        )
+        .bind(&pkg.name)
+        .bind(normalize_description(pkg.description.as_deref()))
+        .bind(&pkg.github_repository_url)
+        .bind(&pkg.homepage)
+        .bind(&pkg.license)
+        .bind(&pkg.owner_github_username)
+        .bind(&pkg.owner_avatar_url)
+        .bind(pkg.github_stars)
+        .bind(pkg.total_downloads)
+        .bind(&pkg.latest_version)
+        .bind(pkg.last_commit_at)
+        .bind(&pkg.directory)
+        .persistent(false)
+        .fetch_one(&mut *conn),
+    )
+    .await?;
+    let package_id: i32 = row.try_get("id")?;
+
+    with_query_timeout(
+        sqlx::query("DELETE FROM package_keywords WHERE package_id = $1")
+            .bind(package_id)
+            .persistent(false)
+            .execute(&mut *conn),
+    )
+    .await?;
+    for keyword in &pkg.keywords {
+        let kw = keyword.trim().to_lowercase();
+        if kw.is_empty() {
+            continue;
+        }
+        with_query_timeout(
+            sqlx::query(
+                "INSERT INTO package_keywords (package_id, keyword)
+                 VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            )
+            .bind(package_id)
+            .bind(&kw)
+            .persistent(false)
+            .execute(&mut *conn),
+        )
+        .await?;
+    }
+
+    for version in &pkg.versions {
+        with_query_timeout(
+            sqlx::query(
+                "INSERT INTO package_versions
+                     (package_id, version, commit_sha, published_at, downloads)
+                 VALUES ($1, $2, $3, COALESCE($4, CURRENT_TIMESTAMP), $5)
+                 ON CONFLICT (package_id, version) DO UPDATE
+                 SET commit_sha = COALESCE(package_versions.commit_sha, EXCLUDED.commit_sha),
+                     downloads = EXCLUDED.downloads",
+            )
+            .bind(package_id)
+            .bind(&version.version)
+            .bind(&version.commit_sha)
+            .bind(version.published_at)
+            .bind(version.downloads)
+            .persistent(false)
+            .execute(&mut *conn),
+        )
+        .await?;
+    }
+
+    Ok(package_id)
+}
+
 /// Get packages filtered by a specific keyword
 pub async fn get_packages_by_keyword(
     pool: &sqlx::PgPool,
     keyword: &str,
 ) -> Result<Vec<PackageResponse>> {
-    let escaped = escape_sql_string(keyword);
-    let query = format!(
-        r#"SELECT
+    let rows = with_query_timeout(
+        sqlx::query(
+            r#"SELECT
             p.id, p.name, p.description, p.github_repository_url,
             p.homepage, p.license, p.owner_github_username, p.owner_avatar_url,
             p.total_downloads, p.github_stars, p.latest_version,
             p.created_at, p.updated_at,
-            p.last_commit_at, p.comparison_notes,
+            p.last_commit_at, p.comparison_notes, p.compiler_version, p.directory,
+            p.deprecated, p.deprecation_message, p.is_fork,
             (SELECT nargo_version FROM package_compat_results
              WHERE package_id = p.id AND status = 'ok'
-             ORDER BY nargo_version DESC LIMIT 1) AS max_compatible_nargo_version
+             ORDER BY nargo_version DESC LIMIT 1) AS max_compatible_nargo_version,
+            (SELECT commit_sha FROM package_versions
+             WHERE package_id = p.id AND version = p.latest_version) AS latest_version_commit_sha
         FROM packages p
         INNER JOIN package_keywords pk ON p.id = pk.package_id
-        WHERE pk.keyword = '{}'
-        ORDER BY p.github_stars DESC, p.name ASC"#,
-        escaped
-    );
+        WHERE pk.keyword = $1
+        ORDER BY p.github_stars DESC, p.name ASC, p.id ASC"#,
+        )
+        .bind(keyword)
+        .persistent(false)
+        .fetch_all(pool),
+    )
+    .await?;
 
-    let rows = sqlx::raw_sql(&query).fetch_all(pool).await?;
+    let packages: Vec<PackageResponse> = rows
+        .into_iter()
+        .map(row_to_package_response)
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+    let ids: Vec<i32> = packages.iter().map(|p| p.id).collect();
+    let mut keywords_map = fetch_keywords_map(pool, &ids).await?;
+    let packages = packages
+        .into_iter()
+        .map(|mut p| {
+            p.keywords = keywords_map.remove(&p.id).unwrap_or_default();
+            p
+        })
+        .collect();
+
+    Ok(packages)
+}
+
+/// The distinct `compiler_version` requirements stored that `compiler`
+/// satisfies. There are only a handful, so they are checked here and the
+/// listing queries filter on the result, before they paginate.
+pub async fn compatible_requirements(pool: &sqlx::PgPool, compiler: &str) -> Result<Vec<String>> {
+    let rows = with_query_timeout(
+        sqlx::query(
+            "SELECT DISTINCT compiler_version FROM packages WHERE compiler_version IS NOT NULL",
+        )
+        .persistent(false)
+        .fetch_all(pool),
+    )
+    .await?;
+    let mut compatible = Vec::new();
+    for row in rows {
+        let requirement: String = row.try_get("compiler_version")?;
+        if compiler_version::requirement_satisfied(&requirement, compiler).unwrap_or(false) {
+            compatible.push(requirement);
+        }
+    }
+    Ok(compatible)
+}
+
+/// Get packages modified after the cursor position, oldest change first, for
+/// incremental mirroring. Callers page by passing the last package they saw
+/// back in as the cursor. `compatible` (see `compatible_requirements`) keeps
+/// only packages without a `compiler_version` or with one of those.
+pub async fn get_updated_since(
+    pool: &sqlx::PgPool,
+    after: &UpdatedCursor,
+    compatible: Option<&[String]>,
+    limit: i64,
+) -> Result<Vec<PackageResponse>> {
+    let rows = with_query_timeout(
+        sqlx::query(
+            r#"SELECT
+                id, name, description, github_repository_url, homepage, license,
+                owner_github_username, owner_avatar_url, total_downloads, github_stars,
+                latest_version, created_at, updated_at,
+                last_commit_at, comparison_notes, compiler_version, directory,
+                deprecated, deprecation_message, is_fork,
+                (SELECT nargo_version FROM package_compat_results
+                 WHERE package_id = packages.id AND status = 'ok'
+                 ORDER BY nargo_version DESC LIMIT 1) AS max_compatible_nargo_version,
+                (SELECT commit_sha FROM package_versions
+                 WHERE package_id = packages.id AND version = packages.latest_version) AS latest_version_commit_sha
+            FROM packages
+            WHERE (updated_at, id) > ($1, $2)
+              AND ($3::text[] IS NULL OR compiler_version IS NULL OR compiler_version = ANY($3))
+            ORDER BY updated_at ASC, id ASC
+            LIMIT $4"#,
+        )
+        .bind(after.updated_at)
+        .bind(after.id)
+        .bind(compatible)
+        .bind(limit)
+        .persistent(false)
+        .fetch_all(pool),
+    )
+    .await?;
 
     let packages: Vec<PackageResponse> = rows
         .into_iter()
-        .map(|row| {
-            Ok(PackageResponse {
-                id: row.try_get("id")?,
-                name: row.try_get("name")?,
-                description: row.try_get("description")?,
-                github_repository_url: row.try_get("github_repository_url")?,
-                homepage: row.try_get("homepage")?,
-                license: row.try_get("license")?,
-                owner_github_username: row.try_get("owner_github_username")?,
-                owner_avatar_url: row.try_get("owner_avatar_url")?,
-                total_downloads: row.try_get("total_downloads")?,
-                github_stars: row.try_get("github_stars")?,
-                latest_version: row.try_get("latest_version")?,
-                created_at: row.try_get("created_at")?,
-                updated_at: row.try_get("updated_at")?,
-                last_commit_at: row.try_get("last_commit_at")?,
-                comparison_notes: row.try_get("comparison_notes")?,
-                max_compatible_nargo_version: row.try_get("max_compatible_nargo_version")?,
-                keywords: vec![],
-            })
+        .map(row_to_package_response)
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+    let ids: Vec<i32> = packages.iter().map(|p| p.id).collect();
+    let mut keywords_map = fetch_keywords_map(pool, &ids).await?;
+    let packages = packages
+        .into_iter()
+        .map(|mut p| {
+            p.keywords = keywords_map.remove(&p.id).unwrap_or_default();
+            p
         })
+        .collect();
+
+    Ok(packages)
+}
+
+/// Packages related to `package_id`: those sharing the most keywords first,
+/// then ones by the same owner, excluding the package itself. Packages with
+/// neither a shared keyword nor the same owner are never returned.
+pub async fn get_similar_packages(
+    pool: &sqlx::PgPool,
+    package_id: i32,
+    limit: i64,
+) -> Result<Vec<PackageResponse>> {
+    let rows = with_query_timeout(
+        sqlx::query(
+            r#"SELECT
+                p.id, p.name, p.description, p.github_repository_url, p.homepage, p.license,
+                p.owner_github_username, p.owner_avatar_url, p.total_downloads, p.github_stars,
+                p.latest_version, p.created_at, p.updated_at,
+                p.last_commit_at, p.comparison_notes, p.compiler_version, p.directory,
+                p.deprecated, p.deprecation_message, p.is_fork,
+                (SELECT nargo_version FROM package_compat_results
+                 WHERE package_id = p.id AND status = 'ok'
+                 ORDER BY nargo_version DESC LIMIT 1) AS max_compatible_nargo_version,
+                (SELECT commit_sha FROM package_versions
+                 WHERE package_id = p.id AND version = p.latest_version) AS latest_version_commit_sha
+            FROM packages p
+            JOIN packages target ON target.id = $1
+            LEFT JOIN LATERAL (
+                SELECT COUNT(*) AS shared
+                FROM package_keywords pk
+                JOIN package_keywords tk ON tk.keyword = pk.keyword AND tk.package_id = target.id
+                WHERE pk.package_id = p.id
+            ) overlap ON TRUE
+            WHERE p.id <> target.id
+              AND (overlap.shared > 0
+                   OR LOWER(p.owner_github_username) = LOWER(target.owner_github_username))
+            ORDER BY
+                overlap.shared DESC,
+                (LOWER(p.owner_github_username) = LOWER(target.owner_github_username)) DESC,
+                p.github_stars DESC,
+                p.name ASC,
+                p.id ASC
+            LIMIT $2"#,
+        )
+        .bind(package_id)
+        .bind(limit)
+        .persistent(false)
+        .fetch_all(pool),
+    )
+    .await?;
+
+    let packages: Vec<PackageResponse> = rows
+        .into_iter()
+        .map(row_to_package_response)
         .collect::<Result<Vec<_>, sqlx::Error>>()?;
 
     let ids: Vec<i32> = packages.iter().map(|p| p.id).collect();
@@ -338,12 +1299,193 @@ pub async fn get_packages_by_keyword(
     Ok(packages)
 }
 
+/// Rows buffered between the DB cursor and a slow client before reading pauses
+const STREAM_BUFFER: usize = 64;
+
+/// Streams packages straight from a DB cursor, so memory stays flat however
+/// many there are. With `after`, yields packages updated after that cursor
+/// position, ordered by `updated_at`; otherwise uses the listing order.
+/// `keyword` narrows either.
+/// Keywords and the version history come from the same query, and reading
+/// stops once the receiver is dropped.
+pub fn stream_packages(
+    pool: sqlx::PgPool,
+    after: Option<UpdatedCursor>,
+    keyword: Option<String>,
+) -> tokio::sync::mpsc::Receiver<Result<PackageResponse>> {
+    use futures_util::TryStreamExt;
+
+    let (tx, rx) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+    let request_id = request_id::current();
+    tokio::spawn(request_id::scope(request_id, async move {
+        let mut rows = sqlx::query(
+            r#"SELECT
+                id, name, description, github_repository_url, homepage, license,
+                owner_github_username, owner_avatar_url, total_downloads, github_stars,
+                latest_version, created_at, updated_at,
+                last_commit_at, comparison_notes, compiler_version, directory,
+                deprecated, deprecation_message, is_fork,
+                (SELECT nargo_version FROM package_compat_results
+                 WHERE package_id = packages.id AND status = 'ok'
+                 ORDER BY nargo_version DESC LIMIT 1) AS max_compatible_nargo_version,
+                (SELECT commit_sha FROM package_versions
+                 WHERE package_id = packages.id AND version = packages.latest_version) AS latest_version_commit_sha,
+                ARRAY(SELECT keyword FROM package_keywords
+                      WHERE package_id = packages.id ORDER BY keyword) AS keywords,
+                ARRAY(SELECT version FROM package_versions
+                      WHERE package_id = packages.id ORDER BY published_at, id) AS version_names,
+                ARRAY(SELECT commit_sha FROM package_versions
+                      WHERE package_id = packages.id ORDER BY published_at, id) AS version_commits,
+                ARRAY(SELECT published_at FROM package_versions
+                      WHERE package_id = packages.id ORDER BY published_at, id) AS version_dates,
+                ARRAY(SELECT downloads FROM package_versions
+                      WHERE package_id = packages.id ORDER BY published_at, id) AS version_downloads
+            FROM packages
+            WHERE ($1::timestamptz IS NULL OR (updated_at, id) > ($1, $2))
+              AND ($3::text IS NULL OR EXISTS (
+                  SELECT 1 FROM package_keywords pk
+                  WHERE pk.package_id = packages.id AND pk.keyword = $3))
+            ORDER BY
+                CASE WHEN $1::timestamptz IS NOT NULL THEN updated_at END ASC,
+                CASE WHEN $1::timestamptz IS NOT NULL THEN id END ASC,
+                github_stars DESC,
+                name ASC,
+                id ASC"#,
+        )
+        .bind(after.map(|a| a.updated_at))
+        .bind(after.map(|a| a.id))
+        .bind(keyword.as_deref())
+        .persistent(false)
+        .fetch(&pool);
+
+        loop {
+            let item = match rows.try_next().await {
+                Ok(Some(row)) => {
+                    let keywords = row.try_get::<Vec<String>, _>("keywords");
+                    let versions = stream_row_versions(&row);
+                    row_to_package_response(row)
+                        .and_then(|mut p| {
+                            p.keywords = keywords?;
+                            p.versions = Some(versions?);
+                            Ok(p)
+                        })
+                        .map_err(anyhow::Error::from)
+                }
+                Ok(None) => break,
+                Err(e) => Err(e.into()),
+            };
+            let failed = item.is_err();
+            if tx.send(item).await.is_err() || failed {
+                break;
+            }
+        }
+    }));
+    rx
+}
+
+/// Zips the `version_*` arrays selected by `stream_packages` back together
+fn stream_row_versions(row: &sqlx::postgres::PgRow) -> Result<Vec<PackageVersion>, sqlx::Error> {
+    let names: Vec<String> = row.try_get("version_names")?;
+    let commits: Vec<Option<String>> = row.try_get("version_commits")?;
+    let dates: Vec<Option<chrono::DateTime<chrono::Utc>>> = row.try_get("version_dates")?;
+    let downloads: Vec<i32> = row.try_get("version_downloads")?;
+    Ok(names
+        .into_iter()
+        .zip(commits)
+        .zip(dates)
+        .zip(downloads)
+        .map(
+            |(((version, commit_sha), published_at), downloads)| PackageVersion {
+                version,
+                commit_sha,
+                published_at,
+                downloads,
+            },
+        )
+        .collect())
+}
+
+/// Get the commit SHA recorded for a published version, if that version exists
+pub async fn get_version_commit_sha(
+    pool: &sqlx::PgPool,
+    package_name: &str,
+    version: &str,
+) -> Result<Option<String>> {
+    let row = with_query_timeout(
+        sqlx::query(
+            "SELECT v.commit_sha
+             FROM package_versions v
+             JOIN packages p ON p.id = v.package_id
+             WHERE p.name = $1 AND v.version = $2",
+        )
+        .bind(package_name)
+        .bind(version)
+        .persistent(false)
+        .fetch_optional(pool),
+    )
+    .await?;
+
+    match row {
+        Some(r) => Ok(r.try_get("commit_sha")?),
+        None => Ok(None),
+    }
+}
+
+/// Fills in the commit of a published version recorded without one (rows
+/// from before commits were pinned). Never creates a row, and never replaces
+/// a stored SHA. Returns whether a row was filled in.
+pub async fn backfill_version_commit_sha(
+    pool: &sqlx::PgPool,
+    package_id: i32,
+    version: &str,
+    commit_sha: &str,
+) -> Result<bool> {
+    let result = with_query_timeout(
+        sqlx::query(
+            "UPDATE package_versions SET commit_sha = $3
+             WHERE package_id = $1 AND version = $2 AND commit_sha IS NULL",
+        )
+        .bind(package_id)
+        .bind(version)
+        .bind(commit_sha)
+        .persistent(false)
+        .execute(pool),
+    )
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Records a published version and the commit its tag resolved to. A SHA that
+/// is already stored is kept, so a moved tag can't rewrite history.
+pub async fn record_package_version(
+    pool: &sqlx::PgPool,
+    package_id: i32,
+    version: &str,
+    commit_sha: &str,
+) -> Result<()> {
+    with_query_timeout(
+        sqlx::query(
+            "INSERT INTO package_versions (package_id, version, commit_sha)
+             VALUES ($1, $2, $3)
+             ON CONFLICT (package_id, version) DO UPDATE
+             SET commit_sha = COALESCE(package_versions.commit_sha, EXCLUDED.commit_sha)",
+        )
+        .bind(package_id)
+        .bind(version)
+        .bind(commit_sha)
+        .persistent(false)
+        .execute(pool),
+    )
+    .await?;
+    Ok(())
+}
+
 /// Get all unique keywords in the registry
 pub async fn get_all_keywords(pool: &sqlx::PgPool) -> Result<Vec<String>> {
-    let rows = sqlx::raw_sql(
-        "SELECT DISTINCT keyword FROM package_keywords ORDER BY keyword",
+    let rows = with_query_timeout(
+        sqlx::raw_sql("SELECT DISTINCT keyword FROM package_keywords ORDER BY keyword")
+            .fetch_all(pool),
     )
-    .fetch_all(pool)
     .await?;
 
     let keywords = rows
@@ -360,37 +1502,535 @@ pub async fn save_keywords(
     package_id: i32,
     keywords: &[String],
 ) -> Result<()> {
-    let delete_query = format!(
-        "DELETE FROM package_keywords WHERE package_id = {}",
-        package_id
-    );
-    sqlx::raw_sql(&delete_query).execute(pool).await?;
+    with_query_timeout(
+        sqlx::query("DELETE FROM package_keywords WHERE package_id = $1")
+            .bind(package_id)
+            .persistent(false)
+            .execute(pool),
+    )
+    .await?;
 
     for keyword in keywords {
         let kw = keyword.trim().to_lowercase();
         if kw.is_empty() {
             continue;
         }
-        let escaped_kw = escape_sql_string(&kw);
-        let insert_query = format!(
-            "INSERT INTO package_keywords (package_id, keyword) \
-             VALUES ({}, '{}') ON CONFLICT DO NOTHING",
-            package_id, escaped_kw
-        );
-        sqlx::raw_sql(&insert_query).execute(pool).await?;
+        with_query_timeout(
+            sqlx::query(
+                "INSERT INTO package_keywords (package_id, keyword)
+                 VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            )
+            .bind(package_id)
+            .bind(&kw)
+            .persistent(false)
+            .execute(pool),
+        )
+        .await?;
     }
 
     Ok(())
 }
 
-/// Increment the download counter for a package by name
-pub async fn increment_downloads(pool: &sqlx::PgPool, name: &str) -> Result<()> {
-    let escaped = escape_sql_string(name);
-    let query = format!(
-        "UPDATE packages SET total_downloads = total_downloads + 1 WHERE name = '{}'",
-        escaped
-    );
-    sqlx::raw_sql(&query).execute(pool).await?;
+/// Counts one download of a package by name, in its total, in today's
+/// bucket and, when `version` names a published one, in that version's count.
+/// A single statement, so concurrent pings for the same package and day each
+/// add exactly one without losing counts or deadlocking.
+pub async fn increment_downloads(
+    pool: &sqlx::PgPool,
+    name: &str,
+    version: Option<&str>,
+) -> Result<()> {
+    with_query_timeout(
+        sqlx::query(
+            "WITH counted AS (
+                UPDATE packages SET total_downloads = total_downloads + 1
+                WHERE name = $1
+                RETURNING id
+            ), version_counted AS (
+                UPDATE package_versions v SET downloads = v.downloads + 1
+                FROM counted
+                WHERE v.package_id = counted.id AND v.version = $2
+            )
+            INSERT INTO package_daily_downloads (package_id, day, count)
+            SELECT id, (NOW() AT TIME ZONE 'UTC')::date, 1 FROM counted
+            ON CONFLICT (package_id, day)
+                DO UPDATE SET count = package_daily_downloads.count + 1",
+        )
+        .bind(name)
+        .bind(version)
+        .persistent(false)
+        .execute(pool),
+    )
+    .await?;
     Ok(())
 }
 
+/// Every published version of a package with its download count, oldest first
+pub async fn get_versions(pool: &sqlx::PgPool, name: &str) -> Result<Vec<PackageVersion>> {
+    let rows = with_query_timeout(
+        sqlx::query(
+            "SELECT v.version, v.commit_sha, v.published_at, v.downloads
+             FROM package_versions v
+             JOIN packages p ON p.id = v.package_id
+             WHERE p.name = $1
+             ORDER BY v.published_at, v.id",
+        )
+        .bind(name)
+        .persistent(false)
+        .fetch_all(pool),
+    )
+    .await?;
+    rows.into_iter()
+        .map(|row| {
+            Ok(PackageVersion {
+                version: row.try_get("version")?,
+                commit_sha: row.try_get("commit_sha")?,
+                published_at: row.try_get("published_at")?,
+                downloads: row.try_get("downloads")?,
+            })
+        })
+        .collect()
+}
+
+fn row_to_scrape_run(row: sqlx::postgres::PgRow) -> Result<ScrapeRun, sqlx::Error> {
+    Ok(ScrapeRun {
+        id: row.try_get("id")?,
+        status: row.try_get("status")?,
+        packages_found: row.try_get("packages_found")?,
+        packages_enriched: row.try_get("packages_enriched")?,
+        packages_inserted: row.try_get("packages_inserted")?,
+        packages_failed: row.try_get("packages_failed")?,
+        error: row.try_get("error")?,
+        started_at: row.try_get("started_at")?,
+        finished_at: row.try_get("finished_at")?,
+    })
+}
+
+/// A run still 'running' after this long is assumed to have died with the
+/// process that ran it, and no longer blocks a new one
+const STALE_SCRAPE_RUN_HOURS: i32 = 6;
+
+/// Start a new scrape run in the 'running' state and return its id, or None
+/// if another run is still in progress
+pub async fn start_scrape_run(pool: &sqlx::PgPool) -> Result<Option<i32>> {
+    let mut tx = pool.begin().await?;
+
+    // Serializes concurrent starts, so two requests can't both see no run in
+    // progress; the lock is released when the transaction ends
+    with_query_timeout(
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext('scrape_runs'))")
+            .persistent(false)
+            .execute(&mut *tx),
+    )
+    .await?;
+
+    let running = with_query_timeout(
+        sqlx::query(
+            "SELECT id FROM scrape_runs
+             WHERE status = 'running' AND started_at > NOW() - make_interval(hours => $1)
+             LIMIT 1",
+        )
+        .bind(STALE_SCRAPE_RUN_HOURS)
+        .persistent(false)
+        .fetch_optional(&mut *tx),
+    )
+    .await?;
+    if running.is_some() {
+        return Ok(None);
+    }
+
+    let row = with_query_timeout(
+        sqlx::query("INSERT INTO scrape_runs (status) VALUES ('running') RETURNING id")
+            .persistent(false)
+            .fetch_one(&mut *tx),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(Some(row.try_get("id")?))
+}
+
+/// Record progress counters for an in-flight scrape run
+pub async fn update_scrape_run_counts(
+    pool: &sqlx::PgPool,
+    run_id: i32,
+    found: i32,
+    enriched: i32,
+    inserted: i32,
+    failed: i32,
+) -> Result<()> {
+    with_query_timeout(
+        sqlx::query(
+            "UPDATE scrape_runs
+             SET packages_found = $2, packages_enriched = $3,
+                 packages_inserted = $4, packages_failed = $5
+             WHERE id = $1",
+        )
+        .bind(run_id)
+        .bind(found)
+        .bind(enriched)
+        .bind(inserted)
+        .bind(failed)
+        .persistent(false)
+        .execute(pool),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Mark a scrape run as finished. `error` is None for a successful run.
+pub async fn finish_scrape_run(
+    pool: &sqlx::PgPool,
+    run_id: i32,
+    error: Option<&str>,
+) -> Result<()> {
+    let status = if error.is_some() {
+        "failed"
+    } else {
+        "completed"
+    };
+    with_query_timeout(
+        sqlx::query(
+            "UPDATE scrape_runs SET status = $2, error = $3, finished_at = NOW() WHERE id = $1",
+        )
+        .bind(run_id)
+        .bind(status)
+        .bind(error)
+        .persistent(false)
+        .execute(pool),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Get a scrape run by id
+pub async fn get_scrape_run(pool: &sqlx::PgPool, run_id: i32) -> Result<Option<ScrapeRun>> {
+    let row = with_query_timeout(
+        sqlx::query(
+            "SELECT id, status, packages_found, packages_enriched, packages_inserted,
+                    packages_failed, error, started_at, finished_at
+             FROM scrape_runs WHERE id = $1",
+        )
+        .bind(run_id)
+        .persistent(false)
+        .fetch_optional(pool),
+    )
+    .await?;
+
+    match row {
+        Some(r) => Ok(Some(row_to_scrape_run(r)?)),
+        None => Ok(None),
+    }
+}
+
+fn row_to_package_transfer(row: sqlx::postgres::PgRow) -> Result<PackageTransfer, sqlx::Error> {
+    Ok(PackageTransfer {
+        id: row.try_get("id")?,
+        package_name: row.try_get("package_name")?,
+        from_github_username: row.try_get("from_github_username")?,
+        to_github_username: row.try_get("to_github_username")?,
+        status: row.try_get("status")?,
+        created_at: row.try_get("created_at")?,
+        resolved_at: row.try_get("resolved_at")?,
+    })
+}
+
+/// Get a package transfer by id
+pub async fn get_package_transfer(
+    pool: &sqlx::PgPool,
+    transfer_id: i32,
+) -> Result<Option<PackageTransfer>> {
+    let row = with_query_timeout(
+        sqlx::query(
+            "SELECT t.id, p.name AS package_name,
+                    fu.github_username AS from_github_username,
+                    tu.github_username AS to_github_username,
+                    t.status, t.created_at, t.resolved_at
+             FROM package_transfers t
+             JOIN packages p ON p.id = t.package_id
+             JOIN users fu ON fu.id = t.from_user_id
+             JOIN users tu ON tu.id = t.to_user_id
+             WHERE t.id = $1",
+        )
+        .bind(transfer_id)
+        .persistent(false)
+        .fetch_optional(pool),
+    )
+    .await?;
+
+    match row {
+        Some(r) => Ok(Some(row_to_package_transfer(r)?)),
+        None => Ok(None),
+    }
+}
+
+/// Opens a pending ownership transfer, cancelling any transfer already pending
+/// for the package. Nothing changes hands until the recipient accepts.
+pub async fn create_package_transfer(
+    pool: &sqlx::PgPool,
+    package_id: i32,
+    from_user_id: i32,
+    to_user_id: i32,
+) -> Result<PackageTransfer> {
+    let mut tx = pool.begin().await?;
+
+    with_query_timeout(
+        sqlx::query(
+            "UPDATE package_transfers SET status = 'cancelled', resolved_at = NOW()
+             WHERE package_id = $1 AND status = 'pending'",
+        )
+        .bind(package_id)
+        .persistent(false)
+        .execute(&mut *tx),
+    )
+    .await?;
+
+    let row = with_query_timeout(
+        sqlx::query(
+            "INSERT INTO package_transfers (package_id, from_user_id, to_user_id)
+             VALUES ($1, $2, $3) RETURNING id",
+        )
+        .bind(package_id)
+        .bind(from_user_id)
+        .bind(to_user_id)
+        .persistent(false)
+        .fetch_one(&mut *tx),
+    )
+    .await?;
+    let transfer_id: i32 = row.try_get("id")?;
+
+    tx.commit().await?;
+
+    get_package_transfer(pool, transfer_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Transfer {} vanished after insert", transfer_id))
+}
+
+/// Completes the pending transfer of `package_name` to `recipient`, making them
+/// the package owner. Returns None if no transfer to them is pending. A
+/// transfer whose sender no longer owns the package is cancelled instead.
+pub async fn accept_package_transfer(
+    pool: &sqlx::PgPool,
+    package_name: &str,
+    recipient: &User,
+) -> Result<Option<PackageTransfer>> {
+    let mut tx = pool.begin().await?;
+
+    let pending = with_query_timeout(
+        sqlx::query(
+            "SELECT t.id, t.package_id, t.from_user_id, fu.github_username AS from_username
+             FROM package_transfers t
+             JOIN packages p ON p.id = t.package_id
+             JOIN users fu ON fu.id = t.from_user_id
+             WHERE p.name = $1 AND t.to_user_id = $2 AND t.status = 'pending'
+             FOR UPDATE OF t",
+        )
+        .bind(package_name)
+        .bind(recipient.id)
+        .persistent(false)
+        .fetch_optional(&mut *tx),
+    )
+    .await?;
+
+    let Some(pending) = pending else {
+        return Ok(None);
+    };
+    let transfer_id: i32 = pending.try_get("id")?;
+    let package_id: i32 = pending.try_get("package_id")?;
+    let from_user_id: i32 = pending.try_get("from_user_id")?;
+    let from_username: String = pending.try_get("from_username")?;
+
+    // Ownership may have changed since the offer was made, so the sender must
+    // still own the package (as its publisher, or as the GitHub owner of an
+    // unclaimed one) for it to move
+    let moved = with_query_timeout(
+        sqlx::query(
+            "UPDATE packages
+             SET owner_github_username = $1, owner_avatar_url = $2,
+                 published_by = $3, updated_at = CURRENT_TIMESTAMP
+             WHERE id = $4
+               AND (published_by = $5
+                    OR (published_by IS NULL AND LOWER(owner_github_username) = LOWER($6)))",
+        )
+        .bind(&recipient.github_username)
+        .bind(&recipient.github_avatar_url)
+        .bind(recipient.id)
+        .bind(package_id)
+        .bind(from_user_id)
+        .bind(&from_username)
+        .persistent(false)
+        .execute(&mut *tx),
+    )
+    .await?
+    .rows_affected()
+        > 0;
+
+    let status = if moved { "accepted" } else { "cancelled" };
+    with_query_timeout(
+        sqlx::query(
+            "UPDATE package_transfers SET status = $2, resolved_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(transfer_id)
+        .bind(status)
+        .persistent(false)
+        .execute(&mut *tx),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    if !moved {
+        return Ok(None);
+    }
+    get_package_transfer(pool, transfer_id).await
+}
+
+/// Marks a package deprecated with `message`, or clears the deprecation when
+/// it is None
+pub async fn set_deprecation(
+    pool: &sqlx::PgPool,
+    package_id: i32,
+    message: Option<&str>,
+) -> Result<()> {
+    with_query_timeout(
+        sqlx::query(
+            "UPDATE packages
+             SET deprecated = $1, deprecation_message = $2, updated_at = CURRENT_TIMESTAMP
+             WHERE id = $3",
+        )
+        .bind(message.is_some())
+        .bind(message)
+        .bind(package_id)
+        .persistent(false)
+        .execute(pool),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Blocked names are compared like colliding package names: ignoring case
+/// and `-`/`_`
+fn normalize_blocked_name(name: &str) -> String {
+    name.trim().to_lowercase().replace('-', "_")
+}
+
+/// Why `name` may not be published, if it's blocked
+pub async fn blocked_name_reason(pool: &sqlx::PgPool, name: &str) -> Result<Option<String>> {
+    let row = with_query_timeout(
+        sqlx::query("SELECT reason FROM blocked_names WHERE name = $1")
+            .bind(normalize_blocked_name(name))
+            .persistent(false)
+            .fetch_optional(pool),
+    )
+    .await?;
+    Ok(row.map(|r| r.try_get("reason")).transpose()?)
+}
+
+/// All blocked names, alphabetically
+pub async fn list_blocked_names(pool: &sqlx::PgPool) -> Result<Vec<BlockedName>> {
+    let rows = with_query_timeout(
+        sqlx::query("SELECT name, reason, created_at FROM blocked_names ORDER BY name")
+            .persistent(false)
+            .fetch_all(pool),
+    )
+    .await?;
+    rows.into_iter()
+        .map(|r| {
+            Ok(BlockedName {
+                name: r.try_get("name")?,
+                reason: r.try_get("reason")?,
+                created_at: r.try_get("created_at")?,
+            })
+        })
+        .collect()
+}
+
+/// Blocks `name` (every spelling of it), or updates the reason if it already
+/// is. Packages already published under it are left alone.
+pub async fn block_name(pool: &sqlx::PgPool, name: &str, reason: &str) -> Result<BlockedName> {
+    let row = with_query_timeout(
+        sqlx::query(
+            "INSERT INTO blocked_names (name, reason) VALUES ($1, $2)
+             ON CONFLICT (name) DO UPDATE SET reason = EXCLUDED.reason
+             RETURNING name, reason, created_at",
+        )
+        .bind(normalize_blocked_name(name))
+        .bind(reason)
+        .persistent(false)
+        .fetch_one(pool),
+    )
+    .await?;
+    Ok(BlockedName {
+        name: row.try_get("name")?,
+        reason: row.try_get("reason")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+/// Unblocks `name`. Returns whether it was blocked.
+pub async fn unblock_name(pool: &sqlx::PgPool, name: &str) -> Result<bool> {
+    let result = with_query_timeout(
+        sqlx::query("DELETE FROM blocked_names WHERE name = $1")
+            .bind(normalize_blocked_name(name))
+            .persistent(false)
+            .execute(pool),
+    )
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Contact details of a package's owner (see [`OwnerContact`]). None if the
+/// package doesn't exist.
+pub async fn get_owner_contact(pool: &sqlx::PgPool, name: &str) -> Result<Option<OwnerContact>> {
+    let row = with_query_timeout(
+        sqlx::query(
+            "SELECT p.name, p.owner_github_username,
+                    u.github_username AS account_github_username, u.email
+             FROM packages p
+             LEFT JOIN users u ON u.id = COALESCE(
+                 p.published_by,
+                 (SELECT id FROM users
+                  WHERE LOWER(github_username) = LOWER(p.owner_github_username)
+                  ORDER BY id LIMIT 1)
+             )
+             WHERE p.name = $1",
+        )
+        .bind(name)
+        .persistent(false)
+        .fetch_optional(pool),
+    )
+    .await?;
+
+    row.map(|r| {
+        Ok(OwnerContact {
+            package: r.try_get("name")?,
+            owner_github_username: r.try_get("owner_github_username")?,
+            account_github_username: r.try_get("account_github_username")?,
+            email: r.try_get("email")?,
+        })
+    })
+    .transpose()
+}
+
+/// Links a package to `user` as its owning registry account. Only succeeds
+/// while the package is unclaimed (or already theirs), so a claim can never
+/// take a package away from another registered user. Returns whether it did.
+pub async fn claim_package(pool: &sqlx::PgPool, package_id: i32, user: &User) -> Result<bool> {
+    let result = with_query_timeout(
+        sqlx::query(
+            "UPDATE packages
+             SET published_by = $1, owner_github_username = $2,
+                 owner_avatar_url = COALESCE($3, owner_avatar_url),
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $4 AND (published_by IS NULL OR published_by = $1)",
+        )
+        .bind(user.id)
+        .bind(&user.github_username)
+        .bind(&user.github_avatar_url)
+        .bind(package_id)
+        .persistent(false)
+        .execute(pool),
+    )
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -1,7 +1,104 @@
 use anyhow::Result;
-use std::time::Duration;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
+const DEFAULT_BREAKER_THRESHOLD: u32 = 5;
+const DEFAULT_BREAKER_COOLDOWN_SECS: u64 = 30;
+
+/// Process-wide circuit breaker for the retry below. After
+/// DB_RETRY_BREAKER_THRESHOLD consecutive prepared-statement failures (default
+/// 5) it opens for DB_RETRY_BREAKER_COOLDOWN_SECS (default 30s); while open,
+/// operations run once and fail fast instead of backing off for ~15s each.
+struct RetryBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: AtomicU32,
+    open_until: Mutex<Option<Instant>>,
+    trips_total: AtomicU64,
+}
+
+impl RetryBreaker {
+    fn from_env() -> Self {
+        let threshold = std::env::var("DB_RETRY_BREAKER_THRESHOLD")
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(DEFAULT_BREAKER_THRESHOLD);
+        let cooldown_secs = std::env::var("DB_RETRY_BREAKER_COOLDOWN_SECS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_BREAKER_COOLDOWN_SECS);
+        RetryBreaker {
+            threshold,
+            cooldown: Duration::from_secs(cooldown_secs),
+            consecutive_failures: AtomicU32::new(0),
+            open_until: Mutex::new(None),
+            trips_total: AtomicU64::new(0),
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.open_until
+            .lock()
+            .unwrap()
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    fn record_success(&self) {
+        if self.consecutive_failures.swap(0, Ordering::Relaxed) > 0 {
+            *self.open_until.lock().unwrap() = None;
+        }
+    }
+
+    fn record_failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures < self.threshold {
+            return;
+        }
+        let mut open_until = self.open_until.lock().unwrap();
+        // Trip once per cooldown, not on every failure while open
+        if open_until.is_none_or(|until| Instant::now() >= until) {
+            *open_until = Some(Instant::now() + self.cooldown);
+            self.trips_total.fetch_add(1, Ordering::Relaxed);
+            eprintln!(
+                "❌ {} consecutive prepared statement failures, retries paused for {}s",
+                failures,
+                self.cooldown.as_secs()
+            );
+        }
+    }
+}
+
+fn breaker() -> &'static RetryBreaker {
+    static BREAKER: OnceLock<RetryBreaker> = OnceLock::new();
+    BREAKER.get_or_init(RetryBreaker::from_env)
+}
+
+/// Snapshot of the retry circuit breaker, for `/metrics`
+#[derive(Debug, Clone, Copy)]
+pub struct RetryBreakerState {
+    pub open: bool,
+    pub consecutive_failures: u32,
+    pub trips_total: u64,
+}
+
+pub fn retry_breaker_state() -> RetryBreakerState {
+    let breaker = breaker();
+    RetryBreakerState {
+        open: breaker.is_open(),
+        consecutive_failures: breaker.consecutive_failures.load(Ordering::Relaxed),
+        trips_total: breaker.trips_total.load(Ordering::Relaxed),
+    }
+}
+
+fn is_prepared_statement_error(e: &anyhow::Error) -> bool {
+    let error_msg = e.to_string();
+    error_msg.contains("prepared statement") && error_msg.contains("already exists")
+}
+
 /// Retries a database operation if it fails due to prepared statement cache issues
 /// This handles the PgBouncer "prepared statement already exists" error gracefully
 pub async fn retry_on_prepared_statement_error<F, Fut, T>(mut operation: F) -> Result<T>
@@ -13,15 +110,21 @@ where
     // Longer delays since cache needs time to clear: 500ms, 1s, 2s, 4s, 8s
     const INITIAL_DELAY_MS: u64 = 500;
 
+    let breaker = breaker();
     for attempt in 0..=MAX_RETRIES {
         match operation().await {
-            Ok(result) => return Ok(result),
+            Ok(result) => {
+                breaker.record_success();
+                return Ok(result);
+            }
             Err(e) => {
-                let error_msg = e.to_string();
-
                 // Check if it's a prepared statement error
-                if error_msg.contains("prepared statement") && error_msg.contains("already exists")
-                {
+                if is_prepared_statement_error(&e) {
+                    breaker.record_failure();
+                    if breaker.is_open() {
+                        // Sustained incident: don't pile up slow requests
+                        return Err(e);
+                    }
                     if attempt < MAX_RETRIES {
                         // Exponential backoff with longer delays: 500ms, 1s, 2s, 4s, 8s
                         let delay_ms = INITIAL_DELAY_MS * (1 << attempt);
@@ -0,0 +1,45 @@
+use anyhow::Result;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const DEFAULT_QUERY_TIMEOUT_SECS: u64 = 10;
+
+/// Returned when a query runs longer than DB_QUERY_TIMEOUT_SECS.
+/// Handlers downcast to this to answer 503 instead of 500.
+#[derive(Debug)]
+pub struct QueryTimeout(pub Duration);
+
+impl std::fmt::Display for QueryTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "query timed out after {}s", self.0.as_secs())
+    }
+}
+
+impl std::error::Error for QueryTimeout {}
+
+/// Per-query limit, read once from DB_QUERY_TIMEOUT_SECS (default 10s).
+/// Unlike the pool's acquire_timeout, this bounds the query itself.
+fn query_timeout() -> Duration {
+    static TIMEOUT: OnceLock<Duration> = OnceLock::new();
+    *TIMEOUT.get_or_init(|| {
+        let secs = std::env::var("DB_QUERY_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_QUERY_TIMEOUT_SECS);
+        Duration::from_secs(secs)
+    })
+}
+
+/// Runs a query future, failing with `QueryTimeout` if it doesn't finish in time
+pub async fn with_query_timeout<F, T, E>(query: F) -> Result<T>
+where
+    F: std::future::Future<Output = std::result::Result<T, E>>,
+    E: Into<anyhow::Error>,
+{
+    let limit = query_timeout();
+    match tokio::time::timeout(limit, query).await {
+        Ok(result) => result.map_err(Into::into),
+        Err(_) => Err(QueryTimeout(limit).into()),
+    }
+}
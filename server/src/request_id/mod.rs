@@ -0,0 +1,91 @@
+//! Per-request ids, so an API call can be traced into the database.
+//!
+//! Every request gets an id (the client's `X-Request-Id` if it sent a sane
+//! one), echoed back in the response header. With `DB_TAG_REQUESTS=1` the
+//! pool also tags each connection it hands out with the id as its Postgres
+//! `application_name`, which `pg_stat_activity` and slow-query logs (via
+//! `%a` in `log_line_prefix`) show next to the query.
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::future::Future;
+use std::sync::OnceLock;
+
+pub const HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// `application_name` for connections used outside any request
+const APPLICATION_NAME: &str = "noir-registry";
+
+/// Client-supplied ids longer than this are replaced, keeping the tagged
+/// `application_name` under Postgres's 63-byte limit
+const MAX_CLIENT_ID_LEN: usize = 40;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Id of the request the current task is serving, if any
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Runs `future` as part of request `id`; for work a handler spawns onto
+/// another task, which doesn't inherit the id by itself
+pub async fn scope<F: Future>(id: Option<String>, future: F) -> F::Output {
+    match id {
+        Some(id) => REQUEST_ID.scope(id, future).await,
+        None => future.await,
+    }
+}
+
+/// Whether pooled connections are tagged with the request id (DB_TAG_REQUESTS).
+/// Off by default: tagging costs a round trip each time a connection is acquired.
+pub fn tag_connections() -> bool {
+    static TAG: OnceLock<bool> = OnceLock::new();
+    *TAG.get_or_init(|| {
+        std::env::var("DB_TAG_REQUESTS")
+            .is_ok_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+    })
+}
+
+/// `application_name` for a connection acquired by the current task
+pub fn application_name() -> String {
+    match current() {
+        Some(id) => format!("{} req={}", APPLICATION_NAME, id),
+        None => APPLICATION_NAME.to_string(),
+    }
+}
+
+fn is_valid_client_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_CLIENT_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn generate() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+/// Assigns the request its id for the rest of its handling and returns it in
+/// the `X-Request-Id` response header
+pub async fn assign(req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(&HEADER)
+        .and_then(|h| h.to_str().ok())
+        .filter(|id| is_valid_client_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(generate);
+
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(req)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(HEADER, value);
+    }
+    response
+}
@@ -0,0 +1,89 @@
+use super::ndjson::accepts_compiler;
+use crate::models::PackageResponse;
+use anyhow::Result;
+use axum::{
+    body::{Body, Bytes},
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::{StreamExt, stream};
+use std::convert::Infallible;
+use tokio::sync::mpsc::Receiver;
+
+const CSV_HEADER: &str = "name,stars,downloads,license,owner,repo\n";
+
+/// Whether the client asked for CSV, via `?format=csv` or `Accept: text/csv`.
+/// An explicit `format` wins over the header.
+pub fn wants_csv(format: Option<&str>, headers: &HeaderMap) -> bool {
+    match format {
+        Some(format) => format.eq_ignore_ascii_case("csv"),
+        None => headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|accept| {
+                accept
+                    .split(',')
+                    .any(|media| media.trim().starts_with("text/csv"))
+            }),
+    }
+}
+
+/// Writes the header, then each package as one CSV row as it arrives. As with
+/// NDJSON, a DB error mid-stream is logged and ends the body early.
+pub fn csv_response(
+    packages: Receiver<Result<PackageResponse>>,
+    compiler: Option<String>,
+) -> Response {
+    let header_row = stream::once(async { Ok::<_, Infallible>(Bytes::from(CSV_HEADER)) });
+    let rows = stream::unfold(packages, move |mut packages| {
+        let compiler = compiler.clone();
+        async move {
+            loop {
+                let package = match packages.recv().await? {
+                    Ok(package) => package,
+                    Err(e) => {
+                        eprintln!("Error streaming packages: {}", e);
+                        return None;
+                    }
+                };
+                if !accepts_compiler(&package, compiler.as_deref()) {
+                    continue;
+                }
+                return Some((Ok(Bytes::from(csv_row(package))), packages));
+            }
+        }
+    });
+
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"packages.csv\"",
+            ),
+        ],
+        Body::from_stream(header_row.chain(rows)),
+    )
+        .into_response()
+}
+
+fn csv_row(p: PackageResponse) -> String {
+    format!(
+        "{},{},{},{},{},{}\n",
+        csv_field(&p.name),
+        p.github_stars,
+        p.total_downloads,
+        csv_field(p.license.as_deref().unwrap_or("")),
+        csv_field(&p.owner_github_username),
+        csv_field(&p.github_repository_url),
+    )
+}
+
+/// Quotes a field when it contains a delimiter, quote or newline (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
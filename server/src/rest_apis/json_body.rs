@@ -0,0 +1,73 @@
+use axum::{
+    Json, async_trait,
+    extract::{FromRequest, Request, rejection::JsonRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::de::DeserializeOwned;
+
+/// Drop-in replacement for `Json<T>` as an extractor. Malformed bodies are
+/// rejected with a 400 and a `{"error": "..."}` body naming the offending
+/// field (e.g. "name is required") instead of axum's plain-text rejection.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(ApiJson(value)),
+            Err(rejection) => Err(rejection_response(rejection)),
+        }
+    }
+}
+
+fn rejection_response(rejection: JsonRejection) -> Response {
+    let (status, message) = match &rejection {
+        JsonRejection::JsonDataError(e) => {
+            (StatusCode::BAD_REQUEST, describe_data_error(&e.body_text()))
+        }
+        JsonRejection::JsonSyntaxError(_) => (
+            StatusCode::BAD_REQUEST,
+            "Request body is not valid JSON".to_string(),
+        ),
+        JsonRejection::MissingJsonContentType(_) => (
+            rejection.status(),
+            "Expected request with `Content-Type: application/json`".to_string(),
+        ),
+        _ => (rejection.status(), rejection.body_text()),
+    };
+
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Turns serde's deserialization message into something a client can act on.
+/// axum formats these as "Failed to deserialize ...: <path>: <detail> at line X column Y".
+fn describe_data_error(body_text: &str) -> String {
+    let detail = body_text
+        .split_once("target type: ")
+        .map(|(_, d)| d)
+        .unwrap_or(body_text);
+    let detail = detail
+        .rsplit_once(" at line ")
+        .map(|(d, _)| d)
+        .unwrap_or(detail);
+
+    if let Some(rest) = detail.split_once("missing field `").map(|(_, r)| r)
+        && let Some((field, _)) = rest.split_once('`')
+    {
+        return format!("{} is required", field);
+    }
+
+    match detail.split_once(": ") {
+        Some((field, reason)) if !field.contains(' ') => {
+            format!("{} is malformed: {}", field, reason)
+        }
+        _ => detail.to_string(),
+    }
+}
@@ -0,0 +1,90 @@
+use super::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Seconds clients are asked to wait before retrying a shed request
+const RETRY_AFTER_SECS: &str = "1";
+
+/// Fast-fail guard for when the DB pool is exhausted. Opt-in: with
+/// `MAX_IN_FLIGHT_REQUESTS` unset, requests are only counted, never rejected.
+#[derive(Debug, Default)]
+pub struct LoadShed {
+    max_in_flight: Option<usize>,
+    in_flight: AtomicUsize,
+    shed_total: AtomicU64,
+}
+
+impl LoadShed {
+    pub fn from_env() -> Self {
+        let max_in_flight = std::env::var("MAX_IN_FLIGHT_REQUESTS")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .filter(|&n| n > 0);
+        LoadShed {
+            max_in_flight,
+            ..Default::default()
+        }
+    }
+
+    /// Configured threshold; None when load shedding is off
+    pub fn max_in_flight(&self) -> Option<usize> {
+        self.max_in_flight
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    pub fn shed_total(&self) -> u64 {
+        self.shed_total.load(Ordering::Relaxed)
+    }
+}
+
+/// Decrements the in-flight count when the request finishes (or is dropped)
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// No idle connection and no room to open another
+fn pool_exhausted(pool: &PgPool) -> bool {
+    pool.num_idle() == 0 && pool.size() >= pool.options().get_max_connections()
+}
+
+/// Rejects with 503 + `Retry-After` when over the threshold and the pool is
+/// exhausted, instead of queueing for the pool's acquire timeout.
+/// `/metrics` is never shed so saturation stays observable.
+pub async fn shed_load(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    let shed = &state.load_shed;
+    let current = shed.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+    let _guard = InFlight(&shed.in_flight);
+
+    if let Some(max) = shed.max_in_flight
+        && current > max
+        && pool_exhausted(&state.db)
+        && req.uri().path() != "/metrics"
+    {
+        shed.shed_total.fetch_add(1, Ordering::Relaxed);
+        let mut response = (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Server is overloaded, retry shortly",
+        )
+            .into_response();
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from_static(RETRY_AFTER_SECS));
+        return response;
+    }
+
+    next.run(req).await
+}
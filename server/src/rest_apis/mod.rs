@@ -1,35 +1,118 @@
 use crate::auth;
-use crate::models::PackageResponse;
-use crate::package_storage;
+use crate::compiler_version;
+use crate::dependencies;
+use crate::github_metadata;
+use crate::http;
+use crate::models::{
+    BlockedName, CursorPage, GitHubRepo, ImportPackage, ImportResult, ManifestDependency,
+    OwnerContact, PackageResponse, PackageTransfer, PackageVersion, Paginated, ScrapeRun,
+    UpdatedCursor,
+};
+use crate::package_storage::{self, SearchFilters, SearchSort};
+use crate::request_id;
+use crate::scraper;
+use crate::webhook;
 use anyhow::Result;
 use axum::body::Body;
 use axum::{
     Router,
     extract::{Path, Query, State},
-    http::{HeaderMap, StatusCode},
-    response::{Json, Response},
-    routing::{delete, get, post},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get, post, put},
 };
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::sync::Arc;
+use tower::Layer;
 use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
+use tower_http::normalize_path::{NormalizePath, NormalizePathLayer};
+use tower_http::set_header::SetResponseHeaderLayer;
+mod csv_export;
+mod json_body;
+mod load_shed;
+mod ndjson;
+mod rate_limit;
+use json_body::ApiJson;
+pub use load_shed::LoadShed;
+pub use rate_limit::RateLimits;
+
+/// Major version of the HTTP API, sent on every response as
+/// `X-Registry-Api-Version`. Bumped only for breaking changes (removed or
+/// renamed fields/routes, changed semantics); additive changes keep it.
+pub const API_VERSION: &str = "1";
 
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub db: PgPool,
+    pub load_shed: Arc<LoadShed>,
+    pub rate_limits: Arc<RateLimits>,
+    /// Shared cap on outbound GitHub requests
+    pub github: Arc<github_metadata::GithubLimiter>,
 }
 
-/// Query parameters for /api/packages (optional keyword filter)
+/// Query parameters for /api/packages (optional keyword filter, or `since` /
+/// `cursor` for incremental mirroring; `compiler` narrows either to compatible
+/// packages).
+/// `offset` switches the response to a `Paginated` envelope; `format=csv`
+/// (or `Accept: text/csv`) to a CSV export of the whole result, streamed like
+/// `Accept: application/x-ndjson`, which gives one JSON package per line.
 #[derive(Deserialize)]
 pub struct ListPackagesQuery {
     pub keyword: Option<String>,
+    pub since: Option<String>,
+    /// `next_cursor` from the previous page of the `since` feed
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub compiler: Option<String>,
+    pub format: Option<String>,
 }
 
-/// Query parameters for /api/search
+/// Page size for `since` and `offset` pagination when no limit is given, and the cap on it
+const DEFAULT_PAGE_LIMIT: i64 = 100;
+const MAX_PAGE_LIMIT: i64 = 1000;
+
+/// Query parameters for /api/search (`offset`/`limit` paginate as for /api/packages)
 #[derive(Deserialize)]
 pub struct SearchQuery {
     pub q: String,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// relevance (default), stars, downloads or name
+    pub sort: Option<String>,
+    /// Only the package named `q` (ignoring case and `-`/`_`), if any
+    #[serde(default)]
+    pub exact: bool,
+}
+
+/// JSON body for POST /api/search: the GET parameters plus filters. A package
+/// must carry every one of `keywords`; an empty `q` matches everything.
+#[derive(Deserialize)]
+pub struct SearchRequest {
+    #[serde(default)]
+    pub q: String,
+    pub license: Option<String>,
+    pub min_stars: Option<i32>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    pub sort: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Query parameters for /api/packages/publish. `enrich=false` skips
+/// re-fetching GitHub metadata for a package that already exists.
+#[derive(Deserialize)]
+pub struct PublishQuery {
+    pub enrich: Option<bool>,
+}
+
+/// Query parameters for POST /api/packages/:name/download: `version` also
+/// counts the download against that published version
+#[derive(Deserialize)]
+pub struct DownloadQuery {
+    pub version: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -38,9 +121,14 @@ pub struct PublishRequest {
     pub description: Option<String>,
     pub github_repository_url: String,
     pub version: Option<String>,
+    /// Commit to publish instead of a tag; the full SHA becomes the version
+    pub rev: Option<String>,
     pub license: Option<String>,
     pub homepage: Option<String>,
     pub keywords: Option<Vec<String>>,
+    /// Confirms publishing a repository that is a fork
+    #[serde(default)]
+    pub allow_fork: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -50,11 +138,20 @@ pub struct PublishResponse {
     pub package_id: Option<i32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Deserialize)]
 pub struct GitHubAuthRequest {
     pub github_token: String,
 }
 
+/// Redacts the token so logging the request can't leak it
+impl std::fmt::Debug for GitHubAuthRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GitHubAuthRequest")
+            .field("github_token", &"***")
+            .finish()
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct GitHubAuthResponse {
     pub success: bool,
@@ -67,6 +164,18 @@ pub struct GitHubAuthResponse {
     pub github_username: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ValidateKeyResponse {
+    pub valid: bool,
+    pub username: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TriggerScrapeResponse {
+    pub run_id: i32,
+    pub status: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateTokenRequest {
     pub name: String,
@@ -80,16 +189,71 @@ pub struct CreateTokenResponse {
     pub message: String,
 }
 
-/// Creates the API router with all routes
-pub fn create_router(db: PgPool) -> Router {
-    let state = Arc::new(AppState { db });
+#[derive(Debug, Deserialize)]
+pub struct SetWebhookRequest {
+    pub url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookResponse {
+    pub url: Option<String>,
+    /// Signing secret; only present right after it was generated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+/// JSON body for PUT /api/admin/blocked-names/:name
+#[derive(Debug, Deserialize)]
+pub struct BlockNameRequest {
+    /// Told to anyone trying to publish under the name
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeprecateRequest {
+    /// Shown to users adding the package, e.g. "use noir-bignum instead"
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferRequest {
+    pub new_owner_github_username: String,
+}
+
+/// One git tag of a package's repository
+#[derive(Debug, Serialize)]
+pub struct TagResponse {
+    pub name: String,
+    pub commit_sha: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VersionShaResponse {
+    pub name: String,
+    pub version: String,
+    pub commit_sha: String,
+}
 
-    let allowed_origins = std::env::var("ALLOWED_ORIGINS")
+/// CORS origins from the comma-separated `ALLOWED_ORIGINS`; `*` (any) when unset
+pub fn allowed_origins() -> Vec<String> {
+    std::env::var("ALLOWED_ORIGINS")
         .unwrap_or_else(|_| "*".to_string())
         .split(',')
         .map(|s| s.trim().to_string())
-        .collect::<Vec<_>>();
+        .collect()
+}
 
+/// Creates the API router with all routes. Trailing slashes are trimmed
+/// before routing, so `/api/packages/foo/` is served as `/api/packages/foo`.
+pub fn create_router(db: PgPool) -> NormalizePath<Router> {
+    let state = Arc::new(AppState {
+        db,
+        load_shed: Arc::new(LoadShed::from_env()),
+        rate_limits: Arc::new(RateLimits::from_env()),
+        github: Arc::new(github_metadata::GithubLimiter::from_env()),
+    });
+
+    let allowed_origins = allowed_origins();
     let cors = if allowed_origins.contains(&"*".to_string()) {
         CorsLayer::new()
             .allow_origin(Any)
@@ -109,34 +273,238 @@ pub fn create_router(db: PgPool) -> Router {
             )]))
     };
 
-    Router::new()
+    let router = Router::new()
         .route("/api/packages", get(list_packages))
-        .route("/api/packages/:name", get(get_package))
-        .route("/api/search", get(search))
+        .route("/api/packages/:name", get(get_package).head(package_exists))
+        .route("/api/search", get(search).post(search_filtered))
+        .route("/api/owners/:username/search", get(search_owner))
         .route("/health", get(health_check))
-        .route("/api/packages/publish", post(publish_package))
+        .route("/metrics", get(metrics))
+        .route(
+            "/api/packages/publish",
+            post(publish_package).route_layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                rate_limit::limit_publish,
+            )),
+        )
         .route("/api/packages/:name/download", post(record_download))
-        .route("/api/auth/github", post(github_auth))
+        .route("/api/packages/:name/similar", get(get_similar_packages))
+        .route("/api/packages/:name/dependencies", get(get_dependencies))
+        .route("/api/packages/:name/dependents", get(get_dependents))
+        .route("/api/packages/:name/tags", get(get_tags))
+        .route("/api/packages/:name/versions", get(get_versions))
+        .route(
+            "/api/packages/:name/versions/:version/sha",
+            get(get_version_sha),
+        )
+        .route(
+            "/api/packages/:name/deprecation",
+            put(deprecate_package).delete(undeprecate_package),
+        )
+        .route("/api/packages/:name/claim", post(claim_package))
+        .route("/api/packages/:name/transfer", post(transfer_package))
+        .route(
+            "/api/packages/:name/transfer/accept",
+            post(accept_package_transfer),
+        )
+        .route(
+            "/api/auth/github",
+            post(github_auth).route_layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                rate_limit::limit_auth,
+            )),
+        )
+        .route("/api/auth/validate", get(validate_key))
         .route("/api/tokens", get(list_tokens).post(create_token))
         .route("/api/tokens/:id", delete(revoke_token))
+        .route(
+            "/api/webhook",
+            get(get_webhook).put(set_webhook).delete(delete_webhook),
+        )
         .route("/api/keywords", get(get_keywords))
+        .route("/api/featured", get(get_featured))
+        .route("/api/admin/scrape", post(trigger_scrape))
+        .route("/api/admin/scrape/:id", get(get_scrape_run))
+        .route("/api/admin/packages/import", post(import_packages))
+        .route("/api/admin/featured", put(set_featured))
+        .route("/api/admin/packages/:name/owner", get(get_owner_contact))
+        .route("/api/admin/blocked-names", get(list_blocked_names))
+        .route(
+            "/api/admin/blocked-names/:name",
+            put(block_name).delete(unblock_name),
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            load_shed::shed_load,
+        ))
+        .layer(axum::middleware::from_fn(request_id::assign))
         .layer(cors)
-        .with_state(state)
+        .layer(SetResponseHeaderLayer::overriding(
+            HeaderName::from_static("x-registry-api-version"),
+            HeaderValue::from_static(API_VERSION),
+        ))
+        .with_state(state);
+
+    // Must wrap the router rather than be a `Router::layer`, which only runs
+    // after a route has already been matched
+    NormalizePathLayer::trim_trailing_slash().layer(router)
+}
+
+/// Status for a failed storage call: a query timeout is transient (503),
+/// anything else is a server error (500).
+fn storage_error_status(e: &anyhow::Error) -> StatusCode {
+    if e.downcast_ref::<package_storage::QueryTimeout>().is_some() {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
 }
 
-/// GET /api/packages: list all packages, optionally filtered by keyword
+/// GET /api/packages: list all packages, optionally filtered by keyword.
+/// With `?since=<rfc3339>`, returns one `CursorPage` of packages updated after
+/// that time, ordered by `updated_at` ascending; `?cursor=<next_cursor>`
+/// continues from the previous page.
+/// With `?compiler=<version>`, drops packages whose `compiler_version`
+/// requirement that compiler doesn't satisfy (packages without one are kept).
 async fn list_packages(
     State(state): State<Arc<AppState>>,
     Query(params): Query<ListPackagesQuery>,
-) -> Result<Json<Vec<PackageResponse>>, Response> {
-    let result = if let Some(keyword) = params.keyword {
+    headers: HeaderMap,
+) -> Result<Response, Response> {
+    if let Some(format) = &params.format
+        && !format.eq_ignore_ascii_case("json")
+        && !format.eq_ignore_ascii_case("csv")
+    {
+        return Err(json_error(
+            StatusCode::BAD_REQUEST,
+            "format must be json or csv",
+        ));
+    }
+    let as_csv = csv_export::wants_csv(params.format.as_deref(), &headers);
+
+    if params.since.is_some() && params.cursor.is_some() {
+        return Err(json_error(
+            StatusCode::BAD_REQUEST,
+            "since and cursor can't be combined; the cursor already holds a position",
+        ));
+    }
+    if (params.since.is_some() || params.cursor.is_some()) && params.offset.is_some() {
+        return Err(json_error(
+            StatusCode::BAD_REQUEST,
+            "since and offset can't be combined; page through since results with cursor",
+        ));
+    }
+
+    if let Some(compiler) = &params.compiler
+        && !compiler_version::is_valid_version(compiler)
+    {
+        return Err(json_error(
+            StatusCode::BAD_REQUEST,
+            "compiler must be a full version number (e.g. 1.0.0-beta.3)",
+        ));
+    }
+
+    let since = match params
+        .since
+        .as_deref()
+        .map(chrono::DateTime::parse_from_rfc3339)
+    {
+        Some(Ok(ts)) => Some(ts.with_timezone(&chrono::Utc)),
+        Some(Err(_)) => {
+            return Err(json_error(
+                StatusCode::BAD_REQUEST,
+                "since must be an RFC 3339 timestamp (e.g. 2026-01-01T00:00:00Z)",
+            ));
+        }
+        None => None,
+    };
+    let cursor = match params.cursor.as_deref().map(UpdatedCursor::parse) {
+        Some(Some(cursor)) => Some(cursor),
+        Some(None) => {
+            return Err(json_error(
+                StatusCode::BAD_REQUEST,
+                "cursor must be a next_cursor returned by a previous page",
+            ));
+        }
+        None => since.map(UpdatedCursor::after),
+    };
+
+    // Full exports stream row by row instead of being collected first
+    if as_csv {
+        let packages = package_storage::stream_packages(state.db.clone(), cursor, params.keyword);
+        return Ok(csv_export::csv_response(packages, params.compiler));
+    }
+    if ndjson::wants_ndjson(&headers) {
+        let packages = package_storage::stream_packages(state.db.clone(), cursor, params.keyword);
+        return Ok(ndjson::ndjson_response(packages, params.compiler));
+    }
+
+    // Filtered in the query so a page is never cut short by the filter
+    let compatible = match &params.compiler {
+        Some(compiler) if cursor.is_some() || params.offset.is_some() => {
+            match package_storage::compatible_requirements(&state.db, compiler).await {
+                Ok(compatible) => Some(compatible),
+                Err(e) => {
+                    eprintln!("Error reading compiler requirements: {}", e);
+                    return Err(storage_error_status(&e).into_response());
+                }
+            }
+        }
+        _ => None,
+    };
+
+    if cursor.is_none()
+        && let Some(offset) = params.offset
+    {
+        let (limit, offset) = page_bounds(params.limit, offset);
+        return match package_storage::get_packages_page(
+            &state.db,
+            params.keyword.as_deref(),
+            compatible.as_deref(),
+            limit,
+            offset,
+        )
+        .await
+        {
+            Ok(page) => Ok(page.into_response()),
+            Err(e) => {
+                eprintln!("Error fetching packages: {}", e);
+                Err(storage_error_status(&e).into_response())
+            }
+        };
+    }
+
+    let since_limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+    let result = if let Some(cursor) = &cursor {
+        package_storage::get_updated_since(&state.db, cursor, compatible.as_deref(), since_limit)
+            .await
+    } else if let Some(keyword) = params.keyword {
         package_storage::get_packages_by_keyword(&state.db, &keyword).await
     } else {
         package_storage::get_all_packages(&state.db).await
     };
 
     match result {
-        Ok(packages) => Ok(Json(packages)),
+        Ok(mut packages) => {
+            if cursor.is_none()
+                && let Some(compiler) = &params.compiler
+            {
+                packages.retain(|p| match &p.compiler_version {
+                    Some(requirement) => {
+                        compiler_version::requirement_satisfied(requirement, compiler)
+                            .unwrap_or(false)
+                    }
+                    None => true,
+                });
+            }
+            if cursor.is_some() {
+                return Ok(CursorPage::new(packages, since_limit).into_response());
+            }
+            Ok(Json(packages).into_response())
+        }
         Err(e) => {
             let error_msg = e.to_string();
             eprintln!("Error fetching packages: {}", error_msg);
@@ -148,7 +516,7 @@ async fn list_packages(
             }
 
             let response = Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .status(storage_error_status(&e))
                 .header("content-type", "application/json")
                 .body(Body::from(format!(r#"{{"error": "{}"}}"#, error_msg)))
                 .unwrap();
@@ -157,169 +525,903 @@ async fn list_packages(
     }
 }
 
-/// GET /api/packages/:name:get a single package by name
+/// Page size and offset for an offset-paginated request
+fn page_bounds(limit: Option<i64>, offset: i64) -> (i64, i64) {
+    (
+        limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT),
+        offset.max(0),
+    )
+}
+
+fn json_error(status: StatusCode, message: &str) -> Response {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::json!({ "error": message }).to_string(),
+        ))
+        .unwrap()
+}
+
+/// GET /api/packages/:name:get a single package by name.
+/// A 404 carries `suggestions`: similarly named packages, for "did you mean" hints.
 async fn get_package(
     State(state): State<Arc<AppState>>,
     Path(name): Path<String>,
-) -> Result<Json<PackageResponse>, StatusCode> {
+) -> Result<Json<PackageResponse>, Response> {
     match package_storage::get_package_by_name(&state.db, &name).await {
         Ok(Some(package)) => Ok(Json(package)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Ok(None) => {
+            let suggestions = package_storage::suggest_similar(&state.db, &name)
+                .await
+                .unwrap_or_else(|e| {
+                    eprintln!("Error finding suggestions for '{}': {}", name, e);
+                    Vec::new()
+                });
+            let body = serde_json::json!({
+                "error": format!("Package '{}' not found", name),
+                "suggestions": suggestions,
+            });
+            Err((StatusCode::NOT_FOUND, Json(body)).into_response())
+        }
         Err(e) => {
             eprintln!("Error fetching package '{}': {}", name, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(storage_error_status(&e).into_response())
         }
     }
 }
 
-/// GET /api/search?q=query:search by name, description, or keyword
-async fn search(
+/// Maximum number of related packages returned by `/similar`
+const SIMILAR_PACKAGES_LIMIT: i64 = 5;
+
+/// GET /api/packages/:name/similar: related packages ranked by shared
+/// keywords, then same owner
+async fn get_similar_packages(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<SearchQuery>,
-) -> Result<Json<Vec<PackageResponse>>, StatusCode> {
-    match package_storage::search_packages(&state.db, &params.q).await {
+    Path(name): Path<String>,
+) -> Result<Json<Vec<PackageResponse>>, Response> {
+    let package = match package_storage::get_package_by_name(&state.db, &name).await {
+        Ok(Some(package)) => package,
+        Ok(None) => {
+            return Err(json_error(
+                StatusCode::NOT_FOUND,
+                &format!("Package '{}' not found", name),
+            ));
+        }
+        Err(e) => {
+            eprintln!("Error fetching package '{}': {}", name, e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    };
+
+    match package_storage::get_similar_packages(&state.db, package.id, SIMILAR_PACKAGES_LIMIT).await
+    {
         Ok(packages) => Ok(Json(packages)),
         Err(e) => {
-            eprintln!("Error searching packages with query '{}': {}", params.q, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            eprintln!("Error fetching packages similar to '{}': {}", name, e);
+            Err(storage_error_status(&e).into_response())
         }
     }
 }
 
-/// GET /api/keywords:list all unique keywords
-async fn get_keywords(
+/// GET /api/packages/:name/versions/:version/sha: the commit a version's tag
+/// resolves to. Published versions are served from the DB, and one recorded
+/// without a SHA has it filled in on first resolution. Any other tag is
+/// resolved through GitHub without being stored.
+async fn get_version_sha(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<Vec<String>>, StatusCode> {
-    match package_storage::get_all_keywords(&state.db).await {
-        Ok(keywords) => Ok(Json(keywords)),
+    Path((name, version)): Path<(String, String)>,
+) -> Result<Json<VersionShaResponse>, Response> {
+    let package = match package_storage::get_package_by_name(&state.db, &name).await {
+        Ok(Some(package)) => package,
+        Ok(None) => {
+            return Err(json_error(
+                StatusCode::NOT_FOUND,
+                &format!("Package '{}' not found", name),
+            ));
+        }
         Err(e) => {
-            eprintln!("Error fetching keywords: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            eprintln!("Error fetching package '{}': {}", name, e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    };
+
+    match package_storage::get_version_commit_sha(&state.db, &name, &version).await {
+        Ok(Some(commit_sha)) => {
+            return Ok(Json(VersionShaResponse {
+                name,
+                version,
+                commit_sha,
+            }));
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Error fetching commit for {}@{}: {}", name, version, e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    }
+
+    let (owner, repo) = parse_github_url(&package.github_repository_url)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    let commit_sha = match resolve_tag_commit(&state.github, &owner, &repo, &version).await {
+        Ok(Some(sha)) => sha,
+        Ok(None) => {
+            return Err(json_error(
+                StatusCode::NOT_FOUND,
+                &format!("Version '{}' is not a tag in {}/{}", version, owner, repo),
+            ));
+        }
+        Err(e) => {
+            eprintln!("Error resolving tag '{}' for {}: {}", version, name, e);
+            return Err(StatusCode::BAD_GATEWAY.into_response());
         }
+    };
+
+    // A version published before commits were pinned gets its SHA now, so
+    // later lookups stay in the DB. Unpublished tags aren't recorded: this is
+    // an unauthenticated read, and only publishing a version adds it.
+    if let Err(e) =
+        package_storage::backfill_version_commit_sha(&state.db, package.id, &version, &commit_sha)
+            .await
+    {
+        eprintln!("Error storing commit for {}@{}: {}", name, version, e);
     }
+    Ok(Json(VersionShaResponse {
+        name,
+        version,
+        commit_sha,
+    }))
 }
 
-/// POST /api/packages/:name/download:increment download counter
-async fn record_download(
+/// GET /api/packages/:name/tags: the git tags of the package's repository,
+/// in GitHub's order. Fetched with the server's token and cached briefly, so
+/// clients can resolve tags without spending their own GitHub quota.
+async fn get_tags(
     State(state): State<Arc<AppState>>,
     Path(name): Path<String>,
-) -> StatusCode {
-    match package_storage::increment_downloads(&state.db, &name).await {
-        Ok(_) => StatusCode::NO_CONTENT,
+) -> Result<Json<Vec<TagResponse>>, Response> {
+    let package = match package_storage::get_package_by_name(&state.db, &name).await {
+        Ok(Some(package)) => package,
+        Ok(None) => {
+            return Err(json_error(
+                StatusCode::NOT_FOUND,
+                &format!("Package '{}' not found", name),
+            ));
+        }
         Err(e) => {
-            eprintln!("Error recording download for '{}': {}", name, e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            eprintln!("Error fetching package '{}': {}", name, e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    };
+
+    let (owner, repo) = parse_github_url(&package.github_repository_url)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    let client = http::build_client();
+    let github_token = std::env::var("GITHUB_TOKEN").ok();
+    let tags = state
+        .github
+        .run(|| github_metadata::fetch_repo_tags(&client, &owner, &repo, github_token.as_deref()))
+        .await;
+    match tags {
+        Ok(tags) => Ok(Json(
+            tags.into_iter()
+                .map(|t| TagResponse {
+                    name: t.name,
+                    commit_sha: t.commit.sha,
+                })
+                .collect(),
+        )),
+        Err(e) => {
+            eprintln!("Error fetching tags of '{}': {}", name, e);
+            Err(StatusCode::BAD_GATEWAY.into_response())
         }
     }
 }
 
-/// GET /health:health check
-async fn health_check(
+/// GET /api/packages/:name/versions: every published version, oldest first,
+/// with how often each was downloaded
+async fn get_versions(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    match sqlx::raw_sql("SELECT 1").execute(&state.db).await {
-        Ok(_) => Ok(Json(serde_json::json!({
-            "status": "healthy",
-            "database": "connected",
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        }))),
+    Path(name): Path<String>,
+) -> Result<Json<Vec<PackageVersion>>, Response> {
+    match package_storage::package_exists(&state.db, &name).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err(json_error(
+                StatusCode::NOT_FOUND,
+                &format!("Package '{}' not found", name),
+            ));
+        }
         Err(e) => {
-            eprintln!("Health check failed: {}", e);
-            Err(StatusCode::SERVICE_UNAVAILABLE)
+            eprintln!("Error checking package '{}': {}", name, e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    }
+
+    match package_storage::get_versions(&state.db, &name).await {
+        Ok(versions) => Ok(Json(versions)),
+        Err(e) => {
+            eprintln!("Error fetching versions of '{}': {}", name, e);
+            Err(storage_error_status(&e).into_response())
         }
     }
 }
 
-/// POST /api/auth/github:authenticate with GitHub token, return API key
-pub async fn github_auth(
+/// GET /api/packages/:name/dependencies: the direct dependencies declared in
+/// the package's Nargo.toml, as last indexed
+async fn get_dependencies(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<GitHubAuthRequest>,
-) -> Result<Json<GitHubAuthResponse>, StatusCode> {
-    match auth::get_or_create_user_from_github(&state.db, &payload.github_token).await {
-        Ok((user, new_raw_key)) => {
-            let (message, api_key_prefix) = if let Some(ref key) = new_raw_key {
-                (
-                    "Account created. Save your api_key now, it will not be shown again.".to_string(),
-                    Some(key.chars().take(8).collect::<String>()),
-                )
-            } else {
-                (
-                    "Authenticated. Manage tokens via GET /api/tokens and POST /api/tokens.".to_string(),
-                    None,
-                )
-            };
-            Ok(Json(GitHubAuthResponse {
-                success: true,
-                api_key: new_raw_key,
-                api_key_prefix,
-                message,
-                github_username: Some(user.github_username.clone()),
-            }))
+    Path(name): Path<String>,
+) -> Result<Json<Vec<ManifestDependency>>, Response> {
+    match package_storage::package_exists(&state.db, &name).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err(json_error(
+                StatusCode::NOT_FOUND,
+                &format!("Package '{}' not found", name),
+            ));
         }
         Err(e) => {
-            eprintln!("Error authenticating with Github: {}", e);
-            Ok(Json(GitHubAuthResponse {
-                success: false,
-                api_key: None,
-                api_key_prefix: None,
-                message: format!("Failed to authenticate with GitHub: {}", e),
-                github_username: None,
-            }))
+            eprintln!("Error checking package '{}': {}", name, e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    }
+
+    match package_storage::get_dependencies(&state.db, &name).await {
+        Ok(dependencies) => Ok(Json(dependencies)),
+        Err(e) => {
+            eprintln!("Error fetching dependencies of '{}': {}", name, e);
+            Err(storage_error_status(&e).into_response())
         }
     }
 }
 
-/// Extract the Bearer token from Authorization header and resolve it to a user.
-/// Returns 401 if the header is missing/malformed or the token is invalid/revoked.
-async fn require_auth(pool: &PgPool, headers: &HeaderMap) -> Result<auth::User, StatusCode> {
-    let raw_token = headers
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok())
-        .and_then(|s| s.strip_prefix("Bearer "))
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+/// GET /api/packages/:name/dependents: names of registry packages that
+/// declare this one as a direct dependency
+async fn get_dependents(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<String>>, Response> {
+    match package_storage::package_exists(&state.db, &name).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err(json_error(
+                StatusCode::NOT_FOUND,
+                &format!("Package '{}' not found", name),
+            ));
+        }
+        Err(e) => {
+            eprintln!("Error checking package '{}': {}", name, e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    }
 
-    auth::validate_api_key(pool, raw_token)
-        .await
-        .map_err(|e| {
-            eprintln!("Error validating api_key: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .ok_or(StatusCode::UNAUTHORIZED)
+    match package_storage::get_dependents(&state.db, &name).await {
+        Ok(dependents) => Ok(Json(dependents)),
+        Err(e) => {
+            eprintln!("Error fetching dependents of '{}': {}", name, e);
+            Err(storage_error_status(&e).into_response())
+        }
+    }
 }
 
-/// GET /api/tokens: list every token belonging to the authenticated user, newest first.
-pub async fn list_tokens(
+/// HEAD /api/packages/:name: 200 if the package exists, 404 if not
+async fn package_exists(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
-) -> Result<Json<Vec<auth::ApiToken>>, StatusCode> {
-    let user = require_auth(&state.db, &headers).await?;
-    auth::list_tokens_for_user(&state.db, user.id)
-        .await
-        .map(Json)
-        .map_err(|e| {
-            eprintln!("Error listing tokens: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })
+    Path(name): Path<String>,
+) -> StatusCode {
+    match package_storage::package_exists(&state.db, &name).await {
+        Ok(true) => StatusCode::OK,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            eprintln!("Error checking package '{}': {}", name, e);
+            storage_error_status(&e)
+        }
+    }
 }
 
-/// POST /api/tokens: create a new named token for the authenticated user.
-/// The raw token is returned exactly once.
-pub async fn create_token(
+/// GET /api/search?q=query:search by name, owner, description, or keyword
+async fn search(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
-    Json(payload): Json<CreateTokenRequest>,
-) -> Result<Json<CreateTokenResponse>, StatusCode> {
-    let user = require_auth(&state.db, &headers).await?;
-    let name = payload.name.trim();
-    if name.is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
+    Query(params): Query<SearchQuery>,
+) -> Result<Response, Response> {
+    let Some(sort) = parse_search_sort(params.sort.as_deref()) else {
+        return Err(json_error(
+            StatusCode::BAD_REQUEST,
+            "sort must be relevance, stars, downloads or name",
+        ));
+    };
+    if params.exact {
+        return search_exact(&state, &params).await;
     }
-    let (token, raw) = auth::create_token_for_user(&state.db, user.id, name)
-        .await
-        .map_err(|e| {
-            eprintln!("Error creating token: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let filters = SearchFilters::default();
+    let result = match params.offset {
+        Some(offset) => {
+            let (limit, offset) = page_bounds(params.limit, offset);
+            package_storage::search_packages_page(
+                &state.db, &params.q, &filters, sort, limit, offset,
+            )
+            .await
+            .map(IntoResponse::into_response)
+        }
+        None => package_storage::search_packages(&state.db, &params.q, &filters, sort)
+            .await
+            .map(search_response),
+    };
+    match result {
+        Ok(response) => Ok(response),
+        Err(e) => {
+            eprintln!("Error searching packages with query '{}': {}", params.q, e);
+            Err(storage_error_status(&e).into_response())
+        }
+    }
+}
+
+/// GET /api/search?exact=true: at most one result, in the same shapes as a
+/// fuzzy search (a page when `offset` is given, a plain list otherwise)
+async fn search_exact(state: &AppState, params: &SearchQuery) -> Result<Response, Response> {
+    let package = package_storage::find_package_by_exact_name(&state.db, &params.q)
+        .await
+        .map_err(|e| {
+            eprintln!("Error looking up exact name '{}': {}", params.q, e);
+            storage_error_status(&e).into_response()
+        })?;
+    let packages: Vec<PackageResponse> = package.into_iter().collect();
+    Ok(match params.offset {
+        Some(offset) => {
+            let (limit, offset) = page_bounds(params.limit, offset);
+            let total = packages.len() as i64;
+            let items = packages
+                .into_iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .collect();
+            Paginated::new(items, total, limit, offset).into_response()
+        }
+        None => Json(packages).into_response(),
+    })
+}
+
+/// POST /api/search: the same search as GET, narrowed by the filters in the
+/// body. Filters are applied in the query, so they see every match, not just
+/// the capped result set.
+async fn search_filtered(
+    State(state): State<Arc<AppState>>,
+    ApiJson(request): ApiJson<SearchRequest>,
+) -> Result<Response, Response> {
+    let Some(sort) = parse_search_sort(request.sort.as_deref()) else {
+        return Err(json_error(
+            StatusCode::BAD_REQUEST,
+            "sort must be relevance, stars, downloads or name",
+        ));
+    };
+    let filters = SearchFilters {
+        license: request.license.clone(),
+        min_stars: request.min_stars,
+        keywords: request.keywords.clone(),
+    };
+    let result = match request.offset {
+        Some(offset) => {
+            let (limit, offset) = page_bounds(request.limit, offset);
+            package_storage::search_packages_page(
+                &state.db, &request.q, &filters, sort, limit, offset,
+            )
+            .await
+            .map(IntoResponse::into_response)
+        }
+        None => package_storage::search_packages(&state.db, &request.q, &filters, sort)
+            .await
+            .map(search_response),
+    };
+    match result {
+        Ok(response) => Ok(response),
+        Err(e) => {
+            eprintln!("Error searching packages with query '{}': {}", request.q, e);
+            Err(storage_error_status(&e).into_response())
+        }
+    }
+}
+
+/// GET /api/owners/:username/search?q=query: search within one owner's packages
+async fn search_owner(
+    State(state): State<Arc<AppState>>,
+    Path(username): Path<String>,
+    Query(params): Query<SearchQuery>,
+) -> Result<Response, Response> {
+    let Some(sort) = parse_search_sort(params.sort.as_deref()) else {
+        return Err(json_error(
+            StatusCode::BAD_REQUEST,
+            "sort must be relevance, stars, downloads or name",
+        ));
+    };
+    let result = match params.offset {
+        Some(offset) => {
+            let (limit, offset) = page_bounds(params.limit, offset);
+            package_storage::search_owner_packages_page(
+                &state.db, &username, &params.q, sort, limit, offset,
+            )
+            .await
+            .map(IntoResponse::into_response)
+        }
+        None => package_storage::search_owner_packages(&state.db, &username, &params.q, sort)
+            .await
+            .map(search_response),
+    };
+    match result {
+        Ok(response) => Ok(response),
+        Err(e) => {
+            eprintln!(
+                "Error searching {}'s packages with query '{}': {}",
+                username, params.q, e
+            );
+            Err(storage_error_status(&e).into_response())
+        }
+    }
+}
+
+/// None for an unknown value; no `sort` means relevance
+fn parse_search_sort(sort: Option<&str>) -> Option<SearchSort> {
+    match sort.map(str::to_ascii_lowercase).as_deref() {
+        None | Some("relevance") => Some(SearchSort::Relevance),
+        Some("stars") => Some(SearchSort::Stars),
+        Some("downloads") => Some(SearchSort::Downloads),
+        Some("name") => Some(SearchSort::Name),
+        Some(_) => None,
+    }
+}
+
+/// Header set on search responses cut short at `SEARCH_RESULT_CAP`
+const RESULTS_TRUNCATED_HEADER: &str = "x-results-truncated";
+
+/// Unpaginated search results, flagging a capped result set
+fn search_response(results: package_storage::SearchResults) -> Response {
+    let mut response = Json(results.packages).into_response();
+    if results.truncated {
+        response.headers_mut().insert(
+            HeaderName::from_static(RESULTS_TRUNCATED_HEADER),
+            HeaderValue::from_static("true"),
+        );
+    }
+    response
+}
+
+/// GET /api/keywords:list all unique keywords
+async fn get_keywords(State(state): State<Arc<AppState>>) -> Result<Json<Vec<String>>, StatusCode> {
+    match package_storage::get_all_keywords(&state.db).await {
+        Ok(keywords) => Ok(Json(keywords)),
+        Err(e) => {
+            eprintln!("Error fetching keywords: {}", e);
+            Err(storage_error_status(&e))
+        }
+    }
+}
+
+/// GET /api/featured: the admin-curated packages, in their curated order
+async fn get_featured(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<PackageResponse>>, StatusCode> {
+    match package_storage::get_featured(&state.db).await {
+        Ok(packages) => Ok(Json(packages)),
+        Err(e) => {
+            eprintln!("Error fetching featured packages: {}", e);
+            Err(storage_error_status(&e))
+        }
+    }
+}
+
+/// PUT /api/admin/featured: replace the featured list with the given package
+/// names, first one ranked highest. An empty list clears it.
+pub async fn set_featured(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ApiJson(names): ApiJson<Vec<String>>,
+) -> Result<Json<Vec<PackageResponse>>, Response> {
+    require_admin(&headers).map_err(IntoResponse::into_response)?;
+
+    let mut seen = std::collections::HashSet::new();
+    if let Some(duplicate) = names.iter().find(|n| !seen.insert(n.as_str())) {
+        return Err(json_error(
+            StatusCode::BAD_REQUEST,
+            &format!("'{}' is listed more than once", duplicate),
+        ));
+    }
+
+    match package_storage::set_featured(&state.db, &names).await {
+        Ok(missing) if missing.is_empty() => {}
+        Ok(missing) => {
+            return Err(json_error(
+                StatusCode::BAD_REQUEST,
+                &format!("No such package(s): {}", missing.join(", ")),
+            ));
+        }
+        Err(e) => {
+            eprintln!("Error setting featured packages: {}", e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    }
+
+    match package_storage::get_featured(&state.db).await {
+        Ok(packages) => Ok(Json(packages)),
+        Err(e) => {
+            eprintln!("Error fetching featured packages: {}", e);
+            Err(storage_error_status(&e).into_response())
+        }
+    }
+}
+
+/// POST /api/packages/:name/download:increment download counter, and the
+/// version's with `?version=`
+async fn record_download(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Query(query): Query<DownloadQuery>,
+) -> StatusCode {
+    match package_storage::increment_downloads(&state.db, &name, query.version.as_deref()).await {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(e) => {
+            eprintln!("Error recording download for '{}': {}", name, e);
+            storage_error_status(&e)
+        }
+    }
+}
+
+/// GET /health:health check
+async fn health_check(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    match sqlx::raw_sql("SELECT 1").execute(&state.db).await {
+        Ok(_) => Ok(Json(serde_json::json!({
+            "status": "healthy",
+            "database": "connected",
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        }))),
+        Err(e) => {
+            eprintln!("Health check failed: {}", e);
+            Err(StatusCode::SERVICE_UNAVAILABLE)
+        }
+    }
+}
+
+/// GET /metrics: pool saturation, load-shedding and GitHub request counters
+/// in Prometheus text format
+async fn metrics(State(state): State<Arc<AppState>>) -> Response {
+    let pool_size = state.db.size();
+    let pool_idle = state.db.num_idle();
+    let pool_max = state.db.options().get_max_connections();
+    let breaker = package_storage::retry_breaker_state();
+    let body = format!(
+        "# TYPE registry_db_pool_connections gauge\n\
+         registry_db_pool_connections {}\n\
+         # TYPE registry_db_pool_idle_connections gauge\n\
+         registry_db_pool_idle_connections {}\n\
+         # TYPE registry_db_pool_max_connections gauge\n\
+         registry_db_pool_max_connections {}\n\
+         # TYPE registry_http_requests_in_flight gauge\n\
+         registry_http_requests_in_flight {}\n\
+         # TYPE registry_http_requests_shed_total counter\n\
+         registry_http_requests_shed_total {}\n\
+         # TYPE registry_http_requests_rate_limited_total counter\n\
+         registry_http_requests_rate_limited_total {}\n\
+         # TYPE registry_db_retry_breaker_open gauge\n\
+         registry_db_retry_breaker_open {}\n\
+         # TYPE registry_db_retry_consecutive_failures gauge\n\
+         registry_db_retry_consecutive_failures {}\n\
+         # TYPE registry_db_retry_breaker_trips_total counter\n\
+         registry_db_retry_breaker_trips_total {}\n\
+         # TYPE registry_github_requests_in_flight gauge\n\
+         registry_github_requests_in_flight {}\n\
+         # TYPE registry_github_max_concurrent_requests gauge\n\
+         registry_github_max_concurrent_requests {}\n",
+        pool_size,
+        pool_idle,
+        pool_max,
+        state.load_shed.in_flight(),
+        state.load_shed.shed_total(),
+        state.rate_limits.limited_total(),
+        u8::from(breaker.open),
+        breaker.consecutive_failures,
+        breaker.trips_total,
+        state.github.in_flight(),
+        state.github.max_concurrent(),
+    );
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4",
+        )],
+        body,
+    )
+        .into_response()
+}
+
+/// POST /api/auth/github:authenticate with GitHub token, return API key
+pub async fn github_auth(
+    State(state): State<Arc<AppState>>,
+    ApiJson(payload): ApiJson<GitHubAuthRequest>,
+) -> Result<Json<GitHubAuthResponse>, StatusCode> {
+    match auth::get_or_create_user_from_github(&state.db, &payload.github_token).await {
+        Ok((user, new_raw_key)) => {
+            let (message, api_key_prefix) = if let Some(ref key) = new_raw_key {
+                (
+                    "Account created. Save your api_key now, it will not be shown again.".to_string(),
+                    Some(key.chars().take(8).collect::<String>()),
+                )
+            } else {
+                (
+                    "Authenticated. Manage tokens via GET /api/tokens and POST /api/tokens.".to_string(),
+                    None,
+                )
+            };
+            Ok(Json(GitHubAuthResponse {
+                success: true,
+                api_key: new_raw_key,
+                api_key_prefix,
+                message,
+                github_username: Some(user.github_username.clone()),
+            }))
+        }
+        Err(e) => {
+            eprintln!("Error authenticating with Github: {}", e);
+            Ok(Json(GitHubAuthResponse {
+                success: false,
+                api_key: None,
+                api_key_prefix: None,
+                message: format!("Failed to authenticate with GitHub: {}", e),
+                github_username: None,
+            }))
+        }
+    }
+}
+
+/// Extract the Bearer token from Authorization header and resolve it to a user.
+/// Returns 401 if the header is missing/malformed or the token is invalid/revoked.
+async fn require_auth(pool: &PgPool, headers: &HeaderMap) -> Result<auth::User, StatusCode> {
+    let raw_token = headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    auth::validate_api_key(pool, raw_token)
+        .await
+        .map_err(|e| {
+            eprintln!("Error validating api_key: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::UNAUTHORIZED)
+}
+
+/// Check the X-Admin-Key header against the ADMIN_API_KEY env var.
+/// Admin endpoints are disabled (always 401) when ADMIN_API_KEY is unset.
+fn require_admin(headers: &HeaderMap) -> Result<(), StatusCode> {
+    let expected = match std::env::var("ADMIN_API_KEY") {
+        Ok(key) if !key.is_empty() => key,
+        _ => {
+            eprintln!("Admin request rejected: ADMIN_API_KEY is not configured");
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    };
+
+    let provided = headers
+        .get("X-Admin-Key")
+        .and_then(|h| h.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    // Compare digests so the check doesn't short-circuit on the first differing byte
+    if auth::hash_api_key(provided) == auth::hash_api_key(&expected) {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// POST /api/admin/scrape: start a scrape-and-enrich run in the background.
+/// Returns immediately with the run id; poll GET /api/admin/scrape/:id for progress.
+/// Only one run goes at a time: 409 while another is still running.
+pub async fn trigger_scrape(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<TriggerScrapeResponse>), Response> {
+    require_admin(&headers).map_err(IntoResponse::into_response)?;
+
+    let run_id = match package_storage::start_scrape_run(&state.db).await {
+        Ok(Some(run_id)) => run_id,
+        Ok(None) => {
+            return Err(json_error(
+                StatusCode::CONFLICT,
+                "A scrape run is already in progress",
+            ));
+        }
+        Err(e) => {
+            eprintln!("Error creating scrape run: {}", e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    };
+
+    let pool = state.db.clone();
+    let limiter = state.github.clone();
+    tokio::spawn(async move {
+        // Scraped in a task of its own, so that if it panics the run is still
+        // marked failed below instead of blocking new runs as `running`
+        let scrape = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                let github_token = std::env::var("GITHUB_TOKEN").ok();
+                let cache = github_metadata::ResponseCache::from_env();
+                scraper::run_scrape(
+                    &pool,
+                    &limiter,
+                    run_id,
+                    github_token.as_deref(),
+                    Some(&cache),
+                )
+                .await
+            }
+        });
+        let error = match scrape.await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(e) => Some(format!("scrape task stopped unexpectedly: {}", e)),
+        };
+        if let Some(ref e) = error {
+            eprintln!("Scrape run {} failed: {}", run_id, e);
+        }
+        if let Err(e) = package_storage::finish_scrape_run(&pool, run_id, error.as_deref()).await {
+            eprintln!("Error finalizing scrape run {}: {}", run_id, e);
+        }
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(TriggerScrapeResponse {
+            run_id,
+            status: "running".to_string(),
+        }),
+    ))
+}
+
+/// GET /api/admin/scrape/:id: poll the progress of a scrape run
+pub async fn get_scrape_run(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(run_id): Path<i32>,
+) -> Result<Json<ScrapeRun>, StatusCode> {
+    require_admin(&headers)?;
+
+    match package_storage::get_scrape_run(&state.db, run_id).await {
+        Ok(Some(run)) => Ok(Json(run)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error fetching scrape run {}: {}", run_id, e);
+            Err(storage_error_status(&e))
+        }
+    }
+}
+
+/// GET /api/admin/packages/:name/owner: how to reach a package's owner, for
+/// abuse and DMCA reports. The email is only ever served here.
+pub async fn get_owner_contact(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<Json<OwnerContact>, StatusCode> {
+    require_admin(&headers)?;
+
+    match package_storage::get_owner_contact(&state.db, &name).await {
+        Ok(Some(contact)) => Ok(Json(contact)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error fetching owner contact for {}: {}", name, e);
+            Err(storage_error_status(&e))
+        }
+    }
+}
+
+/// GET /api/admin/blocked-names: every name nobody may publish under
+pub async fn list_blocked_names(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<BlockedName>>, StatusCode> {
+    require_admin(&headers)?;
+
+    match package_storage::list_blocked_names(&state.db).await {
+        Ok(names) => Ok(Json(names)),
+        Err(e) => {
+            eprintln!("Error fetching blocked names: {}", e);
+            Err(storage_error_status(&e))
+        }
+    }
+}
+
+/// PUT /api/admin/blocked-names/:name: block a name (every spelling of it)
+/// with the reason given to would-be publishers
+pub async fn block_name(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    ApiJson(payload): ApiJson<BlockNameRequest>,
+) -> Result<Json<BlockedName>, Response> {
+    require_admin(&headers).map_err(IntoResponse::into_response)?;
+
+    let reason = payload.reason.trim();
+    if reason.is_empty() {
+        return Err(json_error(StatusCode::BAD_REQUEST, "reason is required"));
+    }
+    match package_storage::block_name(&state.db, &name, reason).await {
+        Ok(blocked) => Ok(Json(blocked)),
+        Err(e) => {
+            eprintln!("Error blocking name '{}': {}", name, e);
+            Err(storage_error_status(&e).into_response())
+        }
+    }
+}
+
+/// DELETE /api/admin/blocked-names/:name: allow a name again
+pub async fn unblock_name(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> StatusCode {
+    if let Err(status) = require_admin(&headers) {
+        return status;
+    }
+
+    match package_storage::unblock_name(&state.db, &name).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            eprintln!("Error unblocking name '{}': {}", name, e);
+            storage_error_status(&e)
+        }
+    }
+}
+
+/// GET /api/auth/validate: cheap pre-flight check that the Bearer key is
+/// valid and not revoked (401 otherwise). Read-only; nothing is recorded.
+pub async fn validate_key(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ValidateKeyResponse>, StatusCode> {
+    let user = require_auth(&state.db, &headers).await?;
+    Ok(Json(ValidateKeyResponse {
+        valid: true,
+        username: user.github_username,
+    }))
+}
+
+/// GET /api/tokens: list every token belonging to the authenticated user, newest first.
+pub async fn list_tokens(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<auth::ApiToken>>, StatusCode> {
+    let user = require_auth(&state.db, &headers).await?;
+    auth::list_tokens_for_user(&state.db, user.id)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("Error listing tokens: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// POST /api/tokens: create a new named token for the authenticated user.
+/// The raw token is returned exactly once.
+pub async fn create_token(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ApiJson(payload): ApiJson<CreateTokenRequest>,
+) -> Result<Json<CreateTokenResponse>, StatusCode> {
+    let user = require_auth(&state.db, &headers).await?;
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let (token, raw) = auth::create_token_for_user(&state.db, user.id, name)
+        .await
+        .map_err(|e| {
+            eprintln!("Error creating token: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
     Ok(Json(CreateTokenResponse {
         token,
         raw,
@@ -327,6 +1429,63 @@ pub async fn create_token(
     }))
 }
 
+/// GET /api/webhook: the authenticated user's webhook URL (never the secret)
+pub async fn get_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<WebhookResponse>, StatusCode> {
+    let user = require_auth(&state.db, &headers).await?;
+    let hook = auth::get_webhook(&state.db, user.id).await.map_err(|e| {
+        eprintln!("Error reading webhook: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(WebhookResponse {
+        url: hook.map(|h| h.url),
+        secret: None,
+    }))
+}
+
+/// PUT /api/webhook: set the URL notified when the user's packages are
+/// published. Returns a new signing secret, shown only this once.
+pub async fn set_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ApiJson(payload): ApiJson<SetWebhookRequest>,
+) -> Result<Json<WebhookResponse>, Response> {
+    let user = require_auth(&state.db, &headers)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    let url = payload.url.trim();
+    if let Err(reason) = webhook::validate_url(url) {
+        return Err(json_error(StatusCode::BAD_REQUEST, &reason));
+    }
+    let secret = auth::set_webhook(&state.db, user.id, Some(url))
+        .await
+        .map_err(|e| {
+            eprintln!("Error setting webhook: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        })?;
+    Ok(Json(WebhookResponse {
+        url: Some(url.to_string()),
+        secret,
+    }))
+}
+
+/// DELETE /api/webhook: stop notifying the user's webhook
+pub async fn delete_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    let user = require_auth(&state.db, &headers).await?;
+    auth::set_webhook(&state.db, user.id, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Error clearing webhook: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// DELETE /api/tokens/:id: revoke one of the authenticated user's tokens.
 /// Idempotent: revoking twice returns 404 the second time.
 pub async fn revoke_token(
@@ -349,47 +1508,90 @@ pub async fn revoke_token(
 }
 
 /// POST /api/packages/publish:publish a package (requires Bearer API key)
+/// Stars, avatar, license and compiler version are then fetched from GitHub,
+/// unless `?enrich=false` is passed for a package that already exists.
 pub async fn publish_package(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    Json(payload): Json<PublishRequest>,
-) -> Result<Json<PublishResponse>, StatusCode> {
+    Query(query): Query<PublishQuery>,
+    ApiJson(mut payload): ApiJson<PublishRequest>,
+) -> Result<Json<PublishResponse>, Response> {
     let api_key = headers
         .get("Authorization")
         .and_then(|h| h.to_str().ok())
         .and_then(|s| s.strip_prefix("Bearer "))
         .ok_or_else(|| {
             eprintln!("Missing Authorization header");
-            StatusCode::UNAUTHORIZED
+            StatusCode::UNAUTHORIZED.into_response()
         })?;
 
     let user = auth::validate_api_key(&state.db, api_key)
         .await
         .map_err(|e| {
             eprintln!("Error validating API key: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
         })?
         .ok_or_else(|| {
             eprintln!("Invalid API key");
-            StatusCode::UNAUTHORIZED
+            StatusCode::UNAUTHORIZED.into_response()
         })?;
 
-    let (owner, repo) =
-        parse_github_url(&payload.github_repository_url).map_err(|_| StatusCode::BAD_REQUEST)?;
-
-    match verify_github_ownership(&owner, &repo, &user.github_username).await {
-        Ok(true) => {}
-        Ok(false) => {
-            return Ok(Json(PublishResponse {
+    // Before anything is fetched for the URL, so it can't aim the server's
+    // requests at an internal service
+    if let Err(reason) = github_metadata::validate_github_url(&payload.github_repository_url) {
+        let rejection = PublishResponse {
+            success: false,
+            message: reason,
+            package_id: None,
+        };
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(rejection)).into_response());
+    }
+    match package_storage::blocked_name_reason(&state.db, &payload.name).await {
+        Ok(None) => {}
+        Ok(Some(reason)) => {
+            let rejection = PublishResponse {
+                success: false,
+                message: format!("The name '{}' is blocked: {}", payload.name, reason),
+                package_id: None,
+            };
+            return Err((StatusCode::FORBIDDEN, Json(rejection)).into_response());
+        }
+        Err(e) => {
+            eprintln!("Error checking blocked names for '{}': {}", payload.name, e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    }
+    // Once published (or transferred), a package belongs to its registry
+    // account; owning the GitHub repository no longer decides who may publish
+    let owns_package = match package_storage::get_publisher(&state.db, &payload.name).await {
+        Ok(None) => false,
+        Ok(Some(publisher)) if publisher == user.id => true,
+        Ok(Some(_)) => {
+            let rejection = PublishResponse {
                 success: false,
                 message: format!(
-                    "You don't have permission to publish this package. \
-                     The repository owner '{}' doesn't match your GitHub username '{}'",
-                    owner, user.github_username
+                    "'{}' belongs to another registry account. Ask its owner to transfer it to you.",
+                    payload.name
                 ),
                 package_id: None,
-            }));
+            };
+            return Err((StatusCode::FORBIDDEN, Json(rejection)).into_response());
+        }
+        Err(e) => {
+            eprintln!("Error checking the owner of '{}': {}", payload.name, e);
+            return Err(storage_error_status(&e).into_response());
         }
+    };
+    let (owner, repo) = parse_github_url(&payload.github_repository_url)
+        .map_err(|_| StatusCode::BAD_REQUEST.into_response())?;
+
+    eprintln!(
+        "🔍 Verifying ownership: repo={}/{}, user={}",
+        owner, repo, user.github_username
+    );
+    let github_repo = state.github.run(|| fetch_publish_repo(&owner, &repo)).await;
+    let github_repo = match github_repo {
+        Ok(github_repo) => github_repo,
         Err(e) => {
             eprintln!("Error verifying GitHub ownership: {}", e);
             return Ok(Json(PublishResponse {
@@ -398,6 +1600,54 @@ pub async fn publish_package(
                 package_id: None,
             }));
         }
+    };
+    if !owns_package
+        && !github_repo
+            .owner
+            .login
+            .eq_ignore_ascii_case(&user.github_username)
+    {
+        return Ok(Json(PublishResponse {
+            success: false,
+            message: format!(
+                "You don't have permission to publish this package. \
+                 The repository owner '{}' doesn't match your GitHub username '{}'",
+                github_repo.owner.login, user.github_username
+            ),
+            package_id: None,
+        }));
+    }
+
+    // A fork published under the original's name would look like the real
+    // thing, so it needs the publisher's confirmation, or is refused outright
+    if github_repo.fork {
+        let original = github_repo
+            .parent
+            .as_ref()
+            .map_or("another repository".to_string(), |p| p.full_name.clone());
+        let message = if forks_rejected() {
+            Some(format!(
+                "{}/{} is a fork of {}, and this registry doesn't accept forks. \
+                 Publish from the original repository instead.",
+                owner, repo, original
+            ))
+        } else if !payload.allow_fork {
+            Some(format!(
+                "{}/{} is a fork of {}. Publish with allow_fork (nargo publish --allow-fork) \
+                 to confirm this isn't the original package.",
+                owner, repo, original
+            ))
+        } else {
+            None
+        };
+        if let Some(message) = message {
+            let rejection = PublishResponse {
+                success: false,
+                message,
+                package_id: None,
+            };
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(rejection)).into_response());
+        }
     }
 
     if !is_valid_package_name(&payload.name) {
@@ -409,38 +1659,504 @@ pub async fn publish_package(
         }));
     }
 
-    match insert_or_update_package(&state.db, &payload, user.id, &owner).await {
-        Ok(package_id) => Ok(Json(PublishResponse {
-            success: true,
-            message: "Package published successfully".to_string(),
-            package_id: Some(package_id),
-        })),
+    // An untagged package can be pinned to a commit instead; its full SHA is
+    // recorded as both the version and the commit
+    let rev_commit = match payload.rev.take() {
+        Some(rev) => {
+            if payload.version.is_some() {
+                return Err(json_error(
+                    StatusCode::BAD_REQUEST,
+                    "Specify either a version or a rev, not both",
+                ));
+            }
+            if !is_commit_rev(&rev) {
+                return Err(json_error(
+                    StatusCode::BAD_REQUEST,
+                    "rev must be a commit SHA (7 to 40 hex characters)",
+                ));
+            }
+            let client = http::build_client();
+            let github_token = std::env::var("GITHUB_TOKEN").ok();
+            let commit = state
+                .github
+                .run(|| {
+                    github_metadata::fetch_commit_sha(
+                        &client,
+                        &owner,
+                        &repo,
+                        &rev,
+                        github_token.as_deref(),
+                    )
+                })
+                .await;
+            match commit {
+                Ok(Some(sha)) => {
+                    payload.version = Some(sha.clone());
+                    Some(sha)
+                }
+                Ok(None) => {
+                    let rejection = PublishResponse {
+                        success: false,
+                        message: format!(
+                            "Commit '{}' does not exist in {}/{}. Push it before publishing.",
+                            rev, owner, repo
+                        ),
+                        package_id: None,
+                    };
+                    return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(rejection)).into_response());
+                }
+                Err(e) => {
+                    return Ok(Json(PublishResponse {
+                        success: false,
+                        message: format!("Failed to resolve commit '{}' on GitHub: {}", rev, e),
+                        package_id: None,
+                    }));
+                }
+            }
+        }
+        None => None,
+    };
+
+    // The version must be a real tag (so `nargo add` can resolve it), and is
+    // pinned to the commit that tag points to right now
+    let commit_sha = match &payload.version {
+        Some(_) if rev_commit.is_some() => rev_commit,
+        Some(version) => match resolve_tag_commit(&state.github, &owner, &repo, version).await {
+            Ok(Some(sha)) => Some(sha),
+            Ok(None) => {
+                let rejection = PublishResponse {
+                    success: false,
+                    message: format!(
+                        "Version '{}' is not a tag in {}/{}. Push the tag before publishing.",
+                        version, owner, repo
+                    ),
+                    package_id: None,
+                };
+                return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(rejection)).into_response());
+            }
+            Err(e) => {
+                return Ok(Json(PublishResponse {
+                    success: false,
+                    message: format!("Failed to resolve tag '{}' on GitHub: {}", version, e),
+                    package_id: None,
+                }));
+            }
+        },
+        None => None,
+    };
+
+    if let (Some(version), Some(sha)) = (&payload.version, &commit_sha) {
+        match package_storage::get_version_commit_sha(&state.db, &payload.name, version).await {
+            Ok(Some(existing)) if existing != *sha => {
+                return Ok(Json(PublishResponse {
+                    success: false,
+                    message: format!(
+                        "Version {} was already published at commit {}, but its tag now points to {}. \
+                         Publish a new version instead of moving the tag.",
+                        version, existing, sha
+                    ),
+                    package_id: None,
+                }));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Error checking existing version: {}", e);
+                return Err(storage_error_status(&e).into_response());
+            }
+        }
+    }
+
+    // A first publish is always enriched; it has no metadata to keep
+    let enrich = match query.enrich {
+        Some(false) => match package_storage::package_exists(&state.db, &payload.name).await {
+            Ok(exists) => !exists,
+            Err(e) => {
+                eprintln!("Error checking package '{}': {}", payload.name, e);
+                return Err(storage_error_status(&e).into_response());
+            }
+        },
+        _ => true,
+    };
+
+    match insert_or_update_package(
+        &state.db,
+        &payload,
+        user.id,
+        &owner,
+        commit_sha.as_deref(),
+        github_repo.fork,
+    )
+    .await
+    {
+        Ok(package_id) => {
+            if enrich {
+                enrich_published_package(
+                    &state.db,
+                    &state.github,
+                    package_id,
+                    &payload.github_repository_url,
+                )
+                .await;
+            }
+            match auth::get_webhook(&state.db, user.id).await {
+                Ok(Some(hook)) => webhook::notify(
+                    hook,
+                    webhook::PublishedEvent::new(
+                        &payload.name,
+                        payload.version.as_deref(),
+                        commit_sha.as_deref(),
+                    ),
+                ),
+                Ok(None) => {}
+                Err(e) => eprintln!("Error reading webhook for '{}': {}", payload.name, e),
+            }
+            Ok(Json(PublishResponse {
+                success: true,
+                message: "Package published successfully".to_string(),
+                package_id: Some(package_id),
+            }))
+        }
         Err(e) => {
             eprintln!("Error publishing package: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
+
+/// POST /api/packages/:name/transfer: the current owner offers the package to
+/// another registered user. Ownership only moves once they accept.
+pub async fn transfer_package(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    ApiJson(payload): ApiJson<TransferRequest>,
+) -> Result<(StatusCode, Json<PackageTransfer>), Response> {
+    let user = require_auth(&state.db, &headers)
+        .await
+        .map_err(IntoResponse::into_response)?;
+
+    let package = match package_storage::get_package_by_name(&state.db, &name).await {
+        Ok(Some(package)) => package,
+        Ok(None) => return Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => {
+            eprintln!("Error fetching package '{}': {}", name, e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    };
+
+    if !package
+        .owner_github_username
+        .eq_ignore_ascii_case(&user.github_username)
+    {
+        return Err(json_error(
+            StatusCode::FORBIDDEN,
+            "Only the current owner can transfer this package",
+        ));
+    }
+
+    let recipient = match auth::get_user_by_github_username(
+        &state.db,
+        &payload.new_owner_github_username,
+    )
+    .await
+    {
+        Ok(Some(recipient)) => recipient,
+        Ok(None) => {
+            return Err(json_error(
+                StatusCode::BAD_REQUEST,
+                "new_owner_github_username must belong to a registered user",
+            ));
+        }
+        Err(e) => {
+            eprintln!("Error looking up transfer recipient: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+
+    if recipient.id == user.id {
+        return Err(json_error(
+            StatusCode::BAD_REQUEST,
+            "You already own this package",
+        ));
+    }
+
+    match package_storage::create_package_transfer(&state.db, package.id, user.id, recipient.id)
+        .await
+    {
+        Ok(transfer) => Ok((StatusCode::ACCEPTED, Json(transfer))),
+        Err(e) => {
+            eprintln!("Error creating transfer for '{}': {}", name, e);
+            Err(storage_error_status(&e).into_response())
+        }
+    }
+}
+
+/// POST /api/packages/:name/transfer/accept: the recipient of a pending
+/// transfer confirms it and becomes the package owner.
+pub async fn accept_package_transfer(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<Json<PackageTransfer>, StatusCode> {
+    let user = require_auth(&state.db, &headers).await?;
+
+    match package_storage::accept_package_transfer(&state.db, &name, &user).await {
+        Ok(Some(transfer)) => Ok(Json(transfer)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Error accepting transfer for '{}': {}", name, e);
+            Err(storage_error_status(&e))
         }
     }
 }
 
-/// Verify that a user owns a GitHub repository
-async fn verify_github_ownership(
+/// Longest deprecation message accepted, so it stays a one-line CLI warning
+const MAX_DEPRECATION_MESSAGE_LEN: usize = 500;
+
+/// Looks a package up for a change only its owner may make
+async fn require_owned_package(
+    state: &AppState,
+    headers: &HeaderMap,
+    name: &str,
+) -> Result<PackageResponse, Response> {
+    let user = require_auth(&state.db, headers)
+        .await
+        .map_err(IntoResponse::into_response)?;
+
+    let package = match package_storage::get_package_by_name(&state.db, name).await {
+        Ok(Some(package)) => package,
+        Ok(None) => return Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => {
+            eprintln!("Error fetching package '{}': {}", name, e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    };
+
+    if !package
+        .owner_github_username
+        .eq_ignore_ascii_case(&user.github_username)
+    {
+        return Err(json_error(
+            StatusCode::FORBIDDEN,
+            "Only the package owner can change its deprecation",
+        ));
+    }
+    Ok(package)
+}
+
+/// Sets or clears a package's deprecation and returns the updated package
+async fn update_deprecation(
+    state: &AppState,
+    name: &str,
+    package_id: i32,
+    message: Option<&str>,
+) -> Result<Json<PackageResponse>, Response> {
+    if let Err(e) = package_storage::set_deprecation(&state.db, package_id, message).await {
+        eprintln!("Error updating deprecation of '{}': {}", name, e);
+        return Err(storage_error_status(&e).into_response());
+    }
+    match package_storage::get_package_by_name(&state.db, name).await {
+        Ok(Some(package)) => Ok(Json(package)),
+        Ok(None) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => {
+            eprintln!("Error fetching package '{}': {}", name, e);
+            Err(storage_error_status(&e).into_response())
+        }
+    }
+}
+
+/// PUT /api/packages/:name/deprecation: the owner marks the package
+/// deprecated with a message pointing users elsewhere. It stays installable.
+pub async fn deprecate_package(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    ApiJson(payload): ApiJson<DeprecateRequest>,
+) -> Result<Json<PackageResponse>, Response> {
+    let package = require_owned_package(&state, &headers, &name).await?;
+    let message = payload.message.trim();
+    if message.is_empty() || message.len() > MAX_DEPRECATION_MESSAGE_LEN {
+        return Err(json_error(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "message must be between 1 and {} characters",
+                MAX_DEPRECATION_MESSAGE_LEN
+            ),
+        ));
+    }
+    update_deprecation(&state, &name, package.id, Some(message)).await
+}
+
+/// DELETE /api/packages/:name/deprecation: the owner lifts a deprecation
+pub async fn undeprecate_package(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<Json<PackageResponse>, Response> {
+    let package = require_owned_package(&state, &headers, &name).await?;
+    update_deprecation(&state, &name, package.id, None).await
+}
+
+/// POST /api/packages/:name/claim: links a scraped package to the registry
+/// account of its GitHub owner, so they can manage it like one they published.
+/// The account's username was verified with GitHub when it logged in.
+pub async fn claim_package(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<Json<PackageResponse>, Response> {
+    let user = require_auth(&state.db, &headers)
+        .await
+        .map_err(IntoResponse::into_response)?;
+
+    let package = match package_storage::get_package_by_name(&state.db, &name).await {
+        Ok(Some(package)) => package,
+        Ok(None) => return Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => {
+            eprintln!("Error fetching package '{}': {}", name, e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    };
+
+    if !package
+        .owner_github_username
+        .eq_ignore_ascii_case(&user.github_username)
+    {
+        return Err(json_error(
+            StatusCode::FORBIDDEN,
+            &format!(
+                "This package belongs to GitHub user '{}', but you are logged in as '{}'",
+                package.owner_github_username, user.github_username
+            ),
+        ));
+    }
+
+    match package_storage::claim_package(&state.db, package.id, &user).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err(json_error(
+                StatusCode::CONFLICT,
+                "This package is already linked to another registry account",
+            ));
+        }
+        Err(e) => {
+            eprintln!("Error claiming package '{}': {}", name, e);
+            return Err(storage_error_status(&e).into_response());
+        }
+    }
+
+    match package_storage::get_package_by_name(&state.db, &name).await {
+        Ok(Some(package)) => Ok(Json(package)),
+        Ok(None) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => {
+            eprintln!("Error fetching package '{}': {}", name, e);
+            Err(storage_error_status(&e).into_response())
+        }
+    }
+}
+
+/// Fetches stars, avatar, license, compiler version and dependencies for a
+/// just-published package. Failures are logged, not returned: the publish itself succeeded.
+async fn enrich_published_package(
+    pool: &PgPool,
+    github: &github_metadata::GithubLimiter,
+    package_id: i32,
+    github_url: &str,
+) {
+    let client = http::build_client();
+    let github_token = std::env::var("GITHUB_TOKEN").ok();
+    let repo = github
+        .run(|| {
+            github_metadata::fetch_github_metadata(
+                &client,
+                github_url,
+                github_token.as_deref(),
+                None,
+            )
+        })
+        .await;
+    let repo = match repo {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Error enriching published package {}: {}", package_id, e);
+            return;
+        }
+    };
+    let manifest =
+        github
+            .run(|| async {
+                Ok(github_metadata::fetch_manifest(
+                    &client,
+                    github_url,
+                    repo.default_branch.as_deref(),
+                )
+                .await)
+            })
+            .await
+            .ok()
+            .flatten();
+    let compiler_version = manifest
+        .as_deref()
+        .and_then(compiler_version::parse_compiler_version);
+
+    if let Err(e) = package_storage::update_github_metadata(
+        pool,
+        package_id,
+        &repo,
+        compiler_version.as_deref(),
+    )
+    .await
+    {
+        eprintln!("Error saving metadata for package {}: {}", package_id, e);
+    }
+
+    if let Some(dependencies) = manifest
+        .as_deref()
+        .and_then(dependencies::parse_dependencies)
+        && let Err(e) = package_storage::save_dependencies(pool, package_id, &dependencies).await
+    {
+        eprintln!(
+            "Error indexing dependencies of package {}: {}",
+            package_id, e
+        );
+    }
+}
+
+/// Resolve a tag to the commit SHA it currently points to, or None if the
+/// repository has no such tag
+async fn resolve_tag_commit(
+    github: &github_metadata::GithubLimiter,
     owner: &str,
     repo: &str,
-    user_github_username: &str,
-) -> Result<bool> {
-    let client = reqwest::Client::new();
+    tag: &str,
+) -> Result<Option<String>> {
+    let client = http::build_client();
+    let github_token = std::env::var("GITHUB_TOKEN").ok();
+    let tags = github
+        .run(|| github_metadata::fetch_repo_tags(&client, owner, repo, github_token.as_deref()))
+        .await?;
+    Ok(tags
+        .into_iter()
+        .find(|t| t.name == tag)
+        .map(|t| t.commit.sha))
+}
+
+/// Fetches the repository being published, to check who owns it and whether
+/// it is a fork
+async fn fetch_publish_repo(owner: &str, repo: &str) -> Result<GitHubRepo> {
+    let client = http::build_client();
     let api_url = format!("https://api.github.com/repos/{}/{}", owner, repo);
-    eprintln!(
-        "🔍 Verifying ownership: repo={}/{}, user={}",
-        owner, repo, user_github_username
-    );
     let response = client
         .get(&api_url)
-        .header("User-Agent", "noir-registry")
         .header("Accept", "application/vnd.github.v3+json")
         .send()
         .await?;
 
+    if let Some(limit) =
+        github_metadata::SecondaryRateLimit::from_response(response.status(), response.headers())
+    {
+        return Err(limit.into());
+    }
     if !response.status().is_success() {
         if response.status() == 404 {
             return Err(anyhow::anyhow!("Repository not found: {}/{}", owner, repo));
@@ -448,20 +2164,67 @@ async fn verify_github_ownership(
         return Err(anyhow::anyhow!("GitHub API error: {}", response.status()));
     }
 
-    let repo_data: serde_json::Value = response.json().await?;
-    let repo_owner = repo_data
-        .get("owner")
-        .and_then(|o| o.get("login"))
-        .and_then(|l| l.as_str())
-        .ok_or_else(|| anyhow::anyhow!("Failed to parse repository owner"))?;
-    eprintln!(
-        "🔍 Repo owner: '{}', User: '{}', Match: {}",
-        repo_owner,
-        user_github_username,
-        repo_owner.eq_ignore_ascii_case(user_github_username)
-    );
+    Ok(response.json().await?)
+}
 
-    Ok(repo_owner.eq_ignore_ascii_case(user_github_username))
+/// Whether `PUBLISH_REJECT_FORKS` refuses every fork, rather than letting the
+/// publisher confirm with `allow_fork`
+pub fn forks_rejected() -> bool {
+    std::env::var("PUBLISH_REJECT_FORKS")
+        .is_ok_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+}
+
+/// POST /api/admin/packages/import: seed a registry from an export. Records
+/// are stored as given (no GitHub enrichment) in one transaction, and the
+/// response reports success or failure per record, in request order.
+pub async fn import_packages(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ApiJson(packages): ApiJson<Vec<ImportPackage>>,
+) -> Result<Json<Vec<ImportResult>>, StatusCode> {
+    require_admin(&headers)?;
+
+    let mut results: Vec<Option<ImportResult>> = Vec::with_capacity(packages.len());
+    let mut valid = Vec::new();
+    for pkg in packages {
+        let problem = if !is_valid_package_name(&pkg.name) {
+            Some(
+                "Invalid package name. Must be alphanumeric with hyphens/underscores, max 50 chars",
+            )
+        } else if parse_github_url(&pkg.github_repository_url).is_err() {
+            Some("Invalid GitHub URL")
+        } else {
+            None
+        };
+        match problem {
+            Some(error) => results.push(Some(ImportResult {
+                name: pkg.name,
+                success: false,
+                package_id: None,
+                error: Some(error.to_string()),
+            })),
+            None => {
+                results.push(None);
+                valid.push(pkg);
+            }
+        }
+    }
+
+    let mut imported = package_storage::import_packages(&state.db, &valid)
+        .await
+        .map_err(|e| {
+            eprintln!("Error importing packages: {}", e);
+            storage_error_status(&e)
+        })?
+        .into_iter();
+
+    Ok(Json(
+        results
+            .into_iter()
+            .map(|r| r.or_else(|| imported.next()))
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default(),
+    ))
 }
 
 fn is_valid_package_name(name: &str) -> bool {
@@ -472,6 +2235,11 @@ fn is_valid_package_name(name: &str) -> bool {
             .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
 }
 
+/// An abbreviated or full hex commit SHA
+fn is_commit_rev(rev: &str) -> bool {
+    (7..=40).contains(&rev.len()) && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
 fn parse_github_url(url: &str) -> Result<(String, String)> {
     let parts: Vec<&str> = url.split('/').collect();
     if parts.len() >= 5 && url.contains("github.com") {
@@ -484,53 +2252,64 @@ fn parse_github_url(url: &str) -> Result<(String, String)> {
     }
 }
 
-/// Insert or update package, then save keywords
+/// Insert or update package, then save keywords and the published version
 async fn insert_or_update_package(
     pool: &PgPool,
     payload: &PublishRequest,
     user_id: i32,
     owner: &str,
+    commit_sha: Option<&str>,
+    fork: bool,
 ) -> Result<i32> {
     use sqlx::Row;
-    use crate::package_storage::escape_sql_string;
 
-    fn sql_opt(opt: &Option<String>) -> String {
-        match opt {
-            None => "NULL".to_string(),
-            Some(s) => format!("'{}'", escape_sql_string(s)),
-        }
-    }
+    // Monorepo packages are published as .../tree/<branch>/<path>
+    let (repo_url, directory) = github_metadata::split_github_url(&payload.github_repository_url);
 
-    let sql = format!(
+    let row = sqlx::query(
         r#"INSERT INTO packages (
             name, description, github_repository_url, homepage, license,
-            owner_github_username, published_by, source
-        ) VALUES ('{}', {}, '{}', {}, {}, '{}', {}, 'user-published')
+            owner_github_username, published_by, source, latest_version, directory, is_fork
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, 'user-published', $8, $9, $10)
         ON CONFLICT (name) DO UPDATE SET
             description = EXCLUDED.description,
             github_repository_url = EXCLUDED.github_repository_url,
+            directory = EXCLUDED.directory,
             homepage = EXCLUDED.homepage,
             license = EXCLUDED.license,
             updated_at = CURRENT_TIMESTAMP,
-            published_by = EXCLUDED.published_by
+            published_by = COALESCE(packages.published_by, EXCLUDED.published_by),
+            is_fork = EXCLUDED.is_fork,
+            latest_version = COALESCE(EXCLUDED.latest_version, packages.latest_version)
         RETURNING id"#,
-        escape_sql_string(&payload.name),
-        sql_opt(&payload.description),
-        escape_sql_string(&payload.github_repository_url),
-        sql_opt(&payload.homepage),
-        sql_opt(&payload.license),
-        escape_sql_string(owner),
-        user_id,
-    );
-    let row = sqlx::raw_sql(&sql).fetch_one(pool).await?;
+    )
+    .bind(&payload.name)
+    .bind(package_storage::normalize_description(
+        payload.description.as_deref(),
+    ))
+    .bind(&repo_url)
+    .bind(&payload.homepage)
+    .bind(&payload.license)
+    .bind(owner)
+    .bind(user_id)
+    .bind(&payload.version)
+    .bind(&directory)
+    .bind(fork)
+    .persistent(false)
+    .fetch_one(pool)
+    .await?;
 
     let package_id: i32 = row.try_get("id")?;
 
     // Save keywords if provided
-    if let Some(keywords) = &payload.keywords {
-        if !keywords.is_empty() {
-            package_storage::save_keywords(pool, package_id, keywords).await?;
-        }
+    if let Some(keywords) = &payload.keywords
+        && !keywords.is_empty()
+    {
+        package_storage::save_keywords(pool, package_id, keywords).await?;
+    }
+
+    if let (Some(version), Some(sha)) = (&payload.version, commit_sha) {
+        package_storage::record_package_version(pool, package_id, version, sha).await?;
     }
 
     Ok(package_id)
@@ -0,0 +1,68 @@
+use crate::compiler_version;
+use crate::models::PackageResponse;
+use anyhow::Result;
+use axum::{
+    body::{Body, Bytes},
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::stream;
+use std::convert::Infallible;
+use tokio::sync::mpsc::Receiver;
+
+/// Whether the client sent `Accept: application/x-ndjson`
+pub fn wants_ndjson(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| {
+            accept
+                .split(',')
+                .any(|media| media.trim().starts_with("application/x-ndjson"))
+        })
+}
+
+/// Writes each package as one JSON line as it arrives. Headers are already
+/// sent by then, so a DB error mid-stream is logged and ends the body early.
+pub fn ndjson_response(
+    packages: Receiver<Result<PackageResponse>>,
+    compiler: Option<String>,
+) -> Response {
+    let lines = stream::unfold(packages, move |mut packages| {
+        let compiler = compiler.clone();
+        async move {
+            loop {
+                let package = match packages.recv().await? {
+                    Ok(package) => package,
+                    Err(e) => {
+                        eprintln!("Error streaming packages: {}", e);
+                        return None;
+                    }
+                };
+                if !accepts_compiler(&package, compiler.as_deref()) {
+                    continue;
+                }
+                let mut line = serde_json::to_vec(&package).ok()?;
+                line.push(b'\n');
+                return Some((Ok::<_, Infallible>(Bytes::from(line)), packages));
+            }
+        }
+    });
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+/// Whether a streamed package passes the `?compiler=` filter: always when no
+/// compiler was given or the package declares no requirement
+pub fn accepts_compiler(package: &PackageResponse, compiler: Option<&str>) -> bool {
+    match (compiler, &package.compiler_version) {
+        (Some(compiler), Some(requirement)) => {
+            compiler_version::requirement_satisfied(requirement, compiler).unwrap_or(false)
+        }
+        _ => true,
+    }
+}
@@ -0,0 +1,186 @@
+use super::AppState;
+use crate::auth;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(60);
+const DEFAULT_PER_MINUTE: u32 = 10;
+/// Expired windows are swept once the table grows past this many clients
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Per-client fixed-window limiter for one class of expensive endpoints.
+/// `per_minute` is None when the limit is configured as `0` (unlimited).
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_minute: Option<u32>,
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    fn from_env(env_var: &str) -> Self {
+        let per_minute = match std::env::var(env_var) {
+            Ok(v) => v.trim().parse::<u32>().ok().unwrap_or(DEFAULT_PER_MINUTE),
+            Err(_) => DEFAULT_PER_MINUTE,
+        };
+        RateLimiter {
+            per_minute: (per_minute > 0).then_some(per_minute),
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts a request from `client`; `Err` carries the seconds until its
+    /// window resets when it is over the limit.
+    fn check(&self, client: String) -> Result<(), u64> {
+        let Some(limit) = self.per_minute else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() > PRUNE_THRESHOLD {
+            windows.retain(|_, (start, _)| now.duration_since(*start) < WINDOW);
+        }
+
+        let (start, count) = windows.entry(client).or_insert((now, 0));
+        if now.duration_since(*start) >= WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= limit {
+            let reset = WINDOW.saturating_sub(now.duration_since(*start));
+            return Err(reset.as_secs().max(1));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+/// Stricter limits for the endpoints that call GitHub on every request:
+/// `AUTH_RATE_LIMIT_PER_MIN` for `/api/auth/github` and
+/// `PUBLISH_RATE_LIMIT_PER_MIN` for `/api/packages/publish` (10 each by default).
+#[derive(Debug)]
+pub struct RateLimits {
+    auth: RateLimiter,
+    publish: RateLimiter,
+    /// Take the client address from `Fly-Client-IP` / `X-Forwarded-For`.
+    /// Only safe behind a proxy that sets them, so opt-in via
+    /// `RATE_LIMIT_TRUST_FORWARDED`.
+    trust_forwarded: bool,
+    limited_total: AtomicU64,
+}
+
+impl RateLimits {
+    pub fn from_env() -> Self {
+        let trust_forwarded = std::env::var("RATE_LIMIT_TRUST_FORWARDED")
+            .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+        RateLimits {
+            auth: RateLimiter::from_env("AUTH_RATE_LIMIT_PER_MIN"),
+            publish: RateLimiter::from_env("PUBLISH_RATE_LIMIT_PER_MIN"),
+            trust_forwarded,
+            limited_total: AtomicU64::new(0),
+        }
+    }
+
+    /// Auth requests allowed per client a minute; None when unlimited
+    pub fn auth_per_minute(&self) -> Option<u32> {
+        self.auth.per_minute
+    }
+
+    /// Publish requests allowed per account or client a minute; None when unlimited
+    pub fn publish_per_minute(&self) -> Option<u32> {
+        self.publish.per_minute
+    }
+
+    pub fn trust_forwarded(&self) -> bool {
+        self.trust_forwarded
+    }
+
+    pub fn limited_total(&self) -> u64 {
+        self.limited_total.load(Ordering::Relaxed)
+    }
+
+    fn client_ip(&self, req: &Request) -> String {
+        if self.trust_forwarded {
+            let headers = req.headers();
+            let forwarded = header_str(headers, "fly-client-ip").or_else(|| {
+                header_str(headers, "x-forwarded-for")
+                    .and_then(|v| v.split(',').next())
+                    .map(str::trim)
+            });
+            if let Some(ip) = forwarded.filter(|ip| !ip.is_empty()) {
+                return ip.to_string();
+            }
+        }
+        req.extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|h| h.to_str().ok())
+}
+
+fn too_many_requests(limits: &RateLimits, retry_after: u64) -> Response {
+    limits.limited_total.fetch_add(1, Ordering::Relaxed);
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        "Too many requests, retry later",
+    )
+        .into_response();
+    if let Ok(value) = HeaderValue::from_str(&retry_after.to_string()) {
+        response.headers_mut().insert(RETRY_AFTER, value);
+    }
+    response
+}
+
+/// Limits `/api/auth/github` per client address, against token brute forcing
+pub async fn limit_auth(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    let limits = &state.rate_limits;
+    if let Err(retry_after) = limits.auth.check(limits.client_ip(&req)) {
+        return too_many_requests(limits, retry_after);
+    }
+    next.run(req).await
+}
+
+/// Limits publishing per account, so one account can't spam the registry
+/// from many addresses. Requests without a valid API key are limited per
+/// client address instead: keying on an unchecked key would let every made-up
+/// key start a fresh window.
+pub async fn limit_publish(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let limits = &state.rate_limits;
+    let key = header_str(req.headers(), "authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim);
+    let user = match key {
+        Some(key) => auth::validate_api_key(&state.db, key)
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Error validating API key for rate limiting: {}", e);
+                None
+            }),
+        None => None,
+    };
+    let client = match user {
+        Some(user) => format!("user:{}", user.id),
+        None => limits.client_ip(&req),
+    };
+    if let Err(retry_after) = limits.publish.check(client) {
+        return too_many_requests(limits, retry_after);
+    }
+    next.run(req).await
+}
@@ -0,0 +1,443 @@
+use crate::github_metadata::{
+    GithubLimiter, ResponseCache, enrich_package, with_secondary_limit_backoff,
+};
+use crate::http;
+use crate::models::{Category, Package};
+use crate::package_storage;
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// Source list the registry is seeded from
+pub const README_URL: &str =
+    "https://raw.githubusercontent.com/noir-lang/awesome-noir/main/README.md";
+
+/// This function should be fetching the raw readme content from github
+pub async fn fetch_readme(url: &str) -> Result<String> {
+    let client = http::build_client();
+    let response = client.get(url).send().await?;
+    let content = response.text().await?;
+    Ok(content)
+}
+
+/// Collects reference-style link definitions (`[ref]: url`) from the README.
+/// Labels are matched case-insensitively with whitespace collapsed, as in markdown.
+fn collect_link_references(readme: &str) -> Result<HashMap<String, String>> {
+    let re = Regex::new(r"^\s{0,3}\[([^\]]+)\]:\s*<?([^\s>]+)>?")?;
+    let mut references = HashMap::new();
+    for line in readme.lines() {
+        if let Some(caps) = re.captures(line) {
+            // The first definition of a label wins
+            references
+                .entry(normalize_link_label(&caps[1]))
+                .or_insert_with(|| caps[2].to_string());
+        }
+    }
+    Ok(references)
+}
+
+fn normalize_link_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Parses the README to extract package information
+pub fn parse_packages(readme: &str) -> Result<Vec<Package>> {
+    parse_packages_in_section(readme, None)
+}
+
+/// Level and text of a markdown ATX heading (`## Libraries` -> (2, "Libraries"))
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &trimmed[level..];
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim()))
+}
+
+/// Like `parse_packages`, but with `section` set only links under the heading
+/// with that text (case-insensitive) are kept, including its subsections.
+/// The section ends at the next heading of the same or a higher level.
+///
+/// Each package's category is the innermost `##`/`###` heading it's listed
+/// under; deeper headings stay within their parent's category.
+pub fn parse_packages_in_section(readme: &str, section: Option<&str>) -> Result<Vec<Package>> {
+    let mut packages = Vec::new();
+    // Level of the matched heading while inside the section
+    let mut section_level: Option<usize> = None;
+    let mut category: Option<Category> = None;
+    let references = collect_link_references(readme)?;
+    // Reference-style entries: - [Name][ref] - description ([Name][] uses Name as the ref)
+    let ref_re = Regex::new(r"-\s*\[([^\]]+)\]\[([^\]]*)\]\s*-\s*(.+)")?;
+    // Regex pattern to match: - [Name](url) - description
+    // Pattern explanation:
+    // - \[([^\]]+)\]  -> matches [Name] and captures "Name"
+    // - \(([^)]+)\)   -> matches (url) and captures "url"
+    // - \s*-\s*(.+)   -> matches " - description" and captures "description"
+    let re = Regex::new(r"-\s*\[([^\]]+)\]\(([^)]+)\)\s*-\s*(.+)")?;
+    for line in readme.lines() {
+        if let Some((level, text)) = parse_heading(line) {
+            match level {
+                // The document title
+                1 => category = None,
+                2 | 3 => category = Category::from_heading(text),
+                _ => {}
+            }
+            if let Some(wanted) = section {
+                if section_level.is_some_and(|open| level <= open) {
+                    section_level = None;
+                }
+                if section_level.is_none() && text.eq_ignore_ascii_case(wanted.trim()) {
+                    section_level = Some(level);
+                }
+            }
+            continue;
+        }
+        if section.is_some() && section_level.is_none() {
+            continue;
+        }
+
+        if let Some(caps) = ref_re.captures(line) {
+            let name = caps[1].trim().to_string();
+            let label = if caps[2].trim().is_empty() {
+                &caps[1]
+            } else {
+                &caps[2]
+            };
+            let Some(url) = references.get(&normalize_link_label(label)) else {
+                continue;
+            };
+            if url.contains("github.com") {
+                packages.push(Package {
+                    name,
+                    github_url: url.clone(),
+                    description: caps[3].trim().to_string(),
+                    category: category.clone(),
+                });
+            }
+        } else if let Some(caps) = re.captures(line) {
+            let name = caps
+                .get(1)
+                .map(|m| m.as_str().trim().to_string())
+                .unwrap_or_default();
+            let url = caps
+                .get(2)
+                .map(|m| m.as_str().trim().to_string())
+                .unwrap_or_default();
+            let description = caps
+                .get(3)
+                .map(|m| m.as_str().trim().to_string())
+                .unwrap_or_default();
+
+            // Only include if it's a GitHub URL
+            if url.contains("github.com") {
+                packages.push(Package {
+                    name,
+                    github_url: url,
+                    description,
+                    category: category.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(packages)
+}
+
+/// Scraper phase a package failed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrapePhase {
+    Enrich,
+    Insert,
+}
+
+/// A package the scraper couldn't index, and why
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrapeFailure {
+    pub package: String,
+    pub phase: ScrapePhase,
+    pub error: String,
+}
+
+impl ScrapeFailure {
+    pub fn new(package: &str, phase: ScrapePhase, error: &anyhow::Error) -> Self {
+        ScrapeFailure {
+            package: package.to_string(),
+            phase,
+            // `{:#}` keeps the context chain on one line
+            error: format!("{:#}", error),
+        }
+    }
+}
+
+/// One line per failure, grouped by phase, for the end-of-run summary
+pub fn failure_summary(failures: &[ScrapeFailure]) -> String {
+    let mut lines = Vec::new();
+    for (phase, heading) in [
+        (ScrapePhase::Enrich, "Failed to fetch from GitHub"),
+        (ScrapePhase::Insert, "Failed to insert"),
+    ] {
+        let failed: Vec<_> = failures.iter().filter(|f| f.phase == phase).collect();
+        if failed.is_empty() {
+            continue;
+        }
+        lines.push(format!("{} ({}):", heading, failed.len()));
+        lines.extend(
+            failed
+                .iter()
+                .map(|f| format!("  - {}: {}", f.package, f.error)),
+        );
+    }
+    lines.join("\n")
+}
+
+/// Writes the failures as a JSON array of `{package, phase, error}`
+pub fn write_failures(path: &Path, failures: &[ScrapeFailure]) -> Result<()> {
+    let json = serde_json::to_string_pretty(failures)?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+/// Packages a scraper run has finished, saved after each one so `--resume`
+/// can pick up where a crashed or rate-limited run stopped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScrapeCheckpoint {
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    /// Names of packages that were inserted (or found already fresher)
+    pub done: BTreeSet<String>,
+}
+
+impl ScrapeCheckpoint {
+    pub fn new() -> Self {
+        ScrapeCheckpoint {
+            started_at: chrono::Utc::now(),
+            done: BTreeSet::new(),
+        }
+    }
+
+    /// Reads a checkpoint; None if there is no file yet
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&json)?))
+    }
+
+    /// Writes the checkpoint through a temporary file, so a crash mid-write
+    /// leaves the previous one intact
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn is_done(&self, package: &str) -> bool {
+        self.done.contains(package)
+    }
+
+    pub fn mark_done(&mut self, package: &str) {
+        self.done.insert(package.to_string());
+    }
+}
+
+impl Default for ScrapeCheckpoint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs the full scrape-and-enrich pipeline, recording progress on the given
+/// `scrape_runs` row so it can be polled while the run is in flight. Its
+/// GitHub requests count against `limiter`, shared with the API handlers.
+pub async fn run_scrape(
+    pool: &PgPool,
+    limiter: &GithubLimiter,
+    run_id: i32,
+    github_token: Option<&str>,
+    cache: Option<&ResponseCache>,
+) -> Result<()> {
+    let readme_content = fetch_readme(README_URL).await?;
+    let packages = parse_packages(&readme_content)?;
+    package_storage::update_scrape_run_counts(pool, run_id, packages.len() as i32, 0, 0, 0).await?;
+
+    let client = http::build_client();
+    let mut enriched_count = 0;
+    let mut inserted_count = 0;
+    let mut failed_count = 0;
+
+    for pkg in packages.iter() {
+        let enriched = with_secondary_limit_backoff(|| {
+            enrich_package(&client, limiter, pkg, github_token, cache)
+        })
+        .await;
+        match enriched {
+            Ok(enriched) => {
+                enriched_count += 1;
+                match package_storage::insert_package(pool, &enriched).await {
+                    Ok(true) => inserted_count += 1,
+                    Ok(false) => eprintln!(
+                        "Scrape run {}: kept {}, which a newer fetch already updated",
+                        run_id, pkg.name
+                    ),
+                    Err(e) => {
+                        failed_count += 1;
+                        eprintln!(
+                            "Scrape run {}: failed to insert {}: {}",
+                            run_id, pkg.name, e
+                        );
+                    }
+                }
+            }
+            Err(e) => {
+                failed_count += 1;
+                eprintln!(
+                    "Scrape run {}: failed to enrich {}: {}",
+                    run_id, pkg.name, e
+                );
+            }
+        }
+
+        package_storage::update_scrape_run_counts(
+            pool,
+            run_id,
+            packages.len() as i32,
+            enriched_count,
+            inserted_count,
+            failed_count,
+        )
+        .await?;
+
+        // Be nice to GitHub API - add small delay
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const README: &str = "\
+# Awesome Noir
+
+## Libraries
+
+- [Inline Lib](https://github.com/owner/inline-lib) - Linked inline
+- [Bignum][bignum] - Linked by reference
+- [Poseidon][] - Collapsed reference, the name is the label
+- [Merkle][MERKLE   Ref] - Label matched case- and space-insensitively
+- [Docs][docs] - Not on GitHub, skipped
+- [Missing][nowhere] - Undefined reference, skipped
+
+### Merkle Trees
+
+- [Trees][trees] - Under a subheading
+
+[bignum]: https://github.com/noir-lang/noir-bignum
+[poseidon]: <https://github.com/noir-lang/poseidon>
+[merkle ref]: https://github.com/owner/merkle
+[docs]: https://noir-lang.org/docs
+[trees]: https://github.com/owner/trees \"Optional title\"
+[bignum]: https://github.com/someone/else
+";
+
+    fn parsed() -> Vec<(String, String, String)> {
+        parse_packages(README)
+            .unwrap()
+            .into_iter()
+            .map(|p| (p.name, p.github_url, p.description))
+            .collect()
+    }
+
+    #[test]
+    fn reference_style_links_are_resolved() {
+        let packages = parsed();
+        let names: Vec<&str> = packages.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["Inline Lib", "Bignum", "Poseidon", "Merkle", "Trees"]
+        );
+        assert_eq!(
+            packages[1],
+            (
+                "Bignum".to_string(),
+                // The first definition of a label wins
+                "https://github.com/noir-lang/noir-bignum".to_string(),
+                "Linked by reference".to_string()
+            )
+        );
+        assert_eq!(packages[2].1, "https://github.com/noir-lang/poseidon");
+        assert_eq!(packages[3].1, "https://github.com/owner/merkle");
+        assert_eq!(packages[4].1, "https://github.com/owner/trees");
+    }
+
+    #[test]
+    fn failures_are_summarized_by_phase() {
+        let failures = [
+            ScrapeFailure::new(
+                "merkle",
+                ScrapePhase::Insert,
+                &anyhow::anyhow!("name collision"),
+            ),
+            ScrapeFailure::new(
+                "bignum",
+                ScrapePhase::Enrich,
+                &anyhow::anyhow!("404").context("GitHub API error"),
+            ),
+            ScrapeFailure::new("trees", ScrapePhase::Insert, &anyhow::anyhow!("timeout")),
+        ];
+        assert_eq!(
+            failure_summary(&failures),
+            "Failed to fetch from GitHub (1):\n  - bignum: GitHub API error: 404\n\
+             Failed to insert (2):\n  - merkle: name collision\n  - trees: timeout"
+        );
+        assert_eq!(failure_summary(&[]), "");
+    }
+
+    #[test]
+    fn reference_entries_keep_their_category() {
+        let packages = parse_packages(README).unwrap();
+        let category = |name: &str| {
+            packages
+                .iter()
+                .find(|p| p.name == name)
+                .and_then(|p| p.category.as_ref())
+                .map(|c| c.slug.clone())
+        };
+        assert_eq!(category("Bignum").as_deref(), Some("libraries"));
+        assert_eq!(category("Trees").as_deref(), Some("merkle-trees"));
+    }
+
+    #[test]
+    fn checkpoint_survives_a_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("scrape-checkpoint-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("checkpoint.json");
+        assert_eq!(ScrapeCheckpoint::load(&path).unwrap(), None);
+
+        let mut checkpoint = ScrapeCheckpoint::new();
+        checkpoint.mark_done("Bignum");
+        checkpoint.save(&path).unwrap();
+
+        let loaded = ScrapeCheckpoint::load(&path).unwrap().unwrap();
+        assert!(loaded.is_done("Bignum"));
+        assert!(!loaded.is_done("Trees"));
+        assert_eq!(loaded, checkpoint);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,159 @@
+//! Notifications POSTed to an owner's webhook when their package is published.
+
+use crate::github_metadata::{is_non_public_host, is_non_public_ip};
+use crate::http;
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Header carrying `sha256=<hex HMAC of the body>`, as GitHub's webhooks do
+pub const SIGNATURE_HEADER: &str = "X-Registry-Signature-256";
+/// Header naming the event, so a receiver can route before parsing the body
+pub const EVENT_HEADER: &str = "X-Registry-Event";
+
+/// A slow receiver must not pile up tasks
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a user's notifications go, and the secret they're signed with
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub url: String,
+    pub secret: String,
+}
+
+/// Body of a `package.published` notification
+#[derive(Debug, Serialize)]
+pub struct PublishedEvent {
+    pub event: &'static str,
+    pub package: String,
+    /// None when published without a version
+    pub version: Option<String>,
+    pub commit_sha: Option<String>,
+    pub published_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl PublishedEvent {
+    pub fn new(package: &str, version: Option<&str>, commit_sha: Option<&str>) -> Self {
+        PublishedEvent {
+            event: "package.published",
+            package: package.to_string(),
+            version: version.map(str::to_string),
+            commit_sha: commit_sha.map(str::to_string),
+            published_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// `sha256=<hex>` HMAC-SHA256 of `body` under `secret`
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Webhook URLs must be https (plain http would send the signed payload in
+/// the clear) on a public host, since the server POSTs to them from inside
+/// its own network. The error is the reason, for the user.
+pub fn validate_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| "url must be an https URL".to_string())?;
+    let Some(host) = parsed.host_str() else {
+        return Err("url must be an https URL".to_string());
+    };
+    if parsed.scheme() != "https" {
+        return Err("url must be an https URL".to_string());
+    }
+    if is_non_public_host(host) {
+        return Err("url must not point at a local or private network address".to_string());
+    }
+    Ok(())
+}
+
+/// Resolves the webhook's host and checks every address again: a name that
+/// passed [`validate_url`] may resolve to an internal address by now
+async fn public_addrs(host: &str, port: u16) -> anyhow::Result<Vec<SocketAddr>> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .with_context(|| format!("could not resolve {}", host))?
+        .collect();
+    if addrs.is_empty() || addrs.iter().any(|a| is_non_public_ip(a.ip())) {
+        anyhow::bail!("{} resolves to a local or private network address", host);
+    }
+    Ok(addrs)
+}
+
+/// Sends the notification in the background. Delivery is best effort: the
+/// publish has already succeeded, so failures are only logged.
+pub fn notify(hook: Webhook, event: PublishedEvent) {
+    tokio::spawn(async move {
+        if let Err(e) = deliver(&hook, &event).await {
+            eprintln!(
+                "Webhook for '{}' to {} failed: {}",
+                event.package, hook.url, e
+            );
+        }
+    });
+}
+
+async fn deliver(hook: &Webhook, event: &PublishedEvent) -> anyhow::Result<()> {
+    validate_url(&hook.url).map_err(anyhow::Error::msg)?;
+    let url = reqwest::Url::parse(&hook.url)?;
+    let host = url.host_str().context("webhook URL has no host")?;
+    let addrs = public_addrs(host, url.port_or_known_default().unwrap_or(443)).await?;
+    // Pinned to the addresses just checked, and never following a redirect,
+    // which could lead anywhere
+    let client = reqwest::Client::builder()
+        .user_agent(http::USER_AGENT)
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(host, &addrs)
+        .build()?;
+
+    let body = serde_json::to_vec(event)?;
+    let response = client
+        .post(url)
+        .timeout(DELIVERY_TIMEOUT)
+        .header("Content-Type", "application/json")
+        .header(EVENT_HEADER, event.event)
+        .header(SIGNATURE_HEADER, sign(&hook.secret, &body))
+        .body(body)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        anyhow::bail!("receiver answered {}", response.status());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_is_hmac_sha256() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn only_https_urls_are_accepted() {
+        assert!(validate_url("https://ci.example.com/hooks/noir").is_ok());
+        assert!(validate_url("http://ci.example.com/hooks/noir").is_err());
+        assert!(validate_url("ftp://ci.example.com").is_err());
+        assert!(validate_url("not a url").is_err());
+    }
+
+    #[tokio::test]
+    async fn names_resolving_to_internal_addresses_are_not_delivered_to() {
+        let err = public_addrs("localhost", 443).await.unwrap_err();
+        assert!(err.to_string().contains("private network"));
+        assert!(public_addrs("127.0.0.1", 443).await.is_err());
+        assert!(public_addrs("[::1]", 443).await.is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! Shared setup for the database integration tests.
+//!
+//! Each test gets its own freshly migrated database. With `TEST_DATABASE_URL`
+//! set (any database on a server the tests may create databases on), that is
+//! a new database on that server, dropped again when the test ends. Otherwise
+//! a throwaway Postgres container is started, which needs Docker.
+//!
+//! The tests are `#[ignore]`d so `cargo test` passes without either; run them
+//! with `cargo test -p noir-registry-server -- --ignored`.
+
+#![allow(dead_code)]
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use noir_registry_server::auth::{self, User};
+use noir_registry_server::models::EnrichedPackage;
+use noir_registry_server::rest_apis;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::{ConnectOptions, Connection, PgPool, Row};
+use std::str::FromStr;
+use testcontainers_modules::postgres::Postgres;
+use testcontainers_modules::testcontainers::ContainerAsync;
+use testcontainers_modules::testcontainers::runners::AsyncRunner;
+use tower::ServiceExt;
+
+pub struct TestDb {
+    pub pool: PgPool,
+    /// Database created on the `TEST_DATABASE_URL` server, dropped on drop
+    created: Option<(PgConnectOptions, String)>,
+    _container: Option<ContainerAsync<Postgres>>,
+}
+
+impl TestDb {
+    pub async fn new() -> TestDb {
+        let (options, created, container) = match std::env::var("TEST_DATABASE_URL") {
+            Ok(url) => {
+                let server = PgConnectOptions::from_str(&url).expect("invalid TEST_DATABASE_URL");
+                let name = format!("noir_registry_test_{:016x}", rand::random::<u64>());
+                let mut conn = server
+                    .connect()
+                    .await
+                    .expect("connect to TEST_DATABASE_URL");
+                sqlx::query(&format!("CREATE DATABASE {}", name))
+                    .execute(&mut conn)
+                    .await
+                    .expect("create test database");
+                conn.close().await.ok();
+                (server.clone().database(&name), Some((server, name)), None)
+            }
+            Err(_) => {
+                let container = Postgres::default()
+                    .start()
+                    .await
+                    .expect("start Postgres container (is Docker running?)");
+                let host = container.get_host().await.expect("container host");
+                let port = container
+                    .get_host_port_ipv4(5432)
+                    .await
+                    .expect("container port");
+                let options = PgConnectOptions::new()
+                    .host(&host.to_string())
+                    .port(port)
+                    .username("postgres")
+                    .password("postgres")
+                    .database("postgres");
+                (options, None, Some(container))
+            }
+        };
+
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_with(options.statement_cache_capacity(0))
+            .await
+            .expect("connect to test database");
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("run migrations");
+
+        TestDb {
+            pool,
+            created,
+            _container: container,
+        }
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        let Some((server, name)) = self.created.take() else {
+            return;
+        };
+        // Drop runs inside the test's runtime, which can't be blocked on,
+        // so the database is dropped from a runtime of its own
+        let _ = std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("build runtime");
+            runtime.block_on(async move {
+                if let Ok(mut conn) = server.connect().await {
+                    let _ = sqlx::query(&format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", name))
+                        .execute(&mut conn)
+                        .await;
+                }
+            });
+        })
+        .join();
+    }
+}
+
+/// A scraped package with the given name, owner and star count
+pub fn package(name: &str, owner: &str, stars: i32) -> EnrichedPackage {
+    EnrichedPackage {
+        name: name.to_string(),
+        description: format!("The {} package", name),
+        github_url: format!("https://github.com/{}/{}", owner, name),
+        directory: None,
+        owner_username: owner.to_string(),
+        owner_avatar: format!("https://avatars.example.com/{}", owner),
+        stars,
+        fork: false,
+        license: Some("MIT".to_string()),
+        homepage: None,
+        last_commit_at: None,
+        compiler_version: None,
+        dependencies: None,
+        category: None,
+        fetched_at: chrono::Utc::now(),
+    }
+}
+
+/// Registers a user and returns them with a raw API token of theirs
+pub async fn user(pool: &PgPool, github_id: i32, username: &str) -> (User, String) {
+    let row = sqlx::query(
+        "INSERT INTO users (github_id, github_username, github_avatar_url)
+         VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(github_id)
+    .bind(username)
+    .bind(format!("https://avatars.example.com/{}", username))
+    .fetch_one(pool)
+    .await
+    .expect("insert user");
+    let id: i32 = row.get("id");
+    let (_token, raw) = auth::create_token_for_user(pool, id, "test")
+        .await
+        .expect("create token");
+    let user = auth::validate_api_key(pool, &raw)
+        .await
+        .expect("validate token")
+        .expect("token is valid");
+    (user, raw)
+}
+
+/// Marks `user` as the registry account that published `package`
+pub async fn set_publisher(pool: &PgPool, package: &str, user: &User) {
+    sqlx::query(
+        "UPDATE packages SET published_by = $1, owner_github_username = $2 WHERE name = $3",
+    )
+    .bind(user.id)
+    .bind(&user.github_username)
+    .bind(package)
+    .execute(pool)
+    .await
+    .expect("set publisher");
+}
+
+/// Sends one request through the full router, returning the status and the
+/// body parsed as JSON (`Null` if it isn't)
+pub async fn send(db: &TestDb, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+    let response = rest_apis::create_router(db.pool.clone())
+        .oneshot(request)
+        .await
+        .unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null),
+    )
+}
+
+/// `GET uri` through the full router
+pub async fn get(db: &TestDb, uri: &str) -> (StatusCode, serde_json::Value) {
+    send(db, Request::get(uri).body(Body::empty()).unwrap()).await
+}
+
+/// `GET uri` through the full router, returning the body as text
+pub async fn get_text(db: &TestDb, uri: &str) -> (StatusCode, String) {
+    let response = rest_apis::create_router(db.pool.clone())
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
@@ -0,0 +1,73 @@
+//! Package deprecation notices through the HTTP API, against a real Postgres
+//! (see `common` for how to run).
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use common::{TestDb, get, package, send, user};
+use noir_registry_server::package_storage;
+use serde_json::{Value, json};
+
+async fn call(db: &TestDb, method: Method, token: &str, body: Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri("/api/packages/lib/deprecation")
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    send(db, request).await
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn owner_deprecates_and_lifts_the_notice() {
+    let db = TestDb::new().await;
+    let (_alice, alice_token) = user(&db.pool, 1, "alice").await;
+    package_storage::insert_package(&db.pool, &package("lib", "alice", 1))
+        .await
+        .unwrap();
+
+    let (_, fresh) = get(&db, "/api/packages/lib").await;
+    assert_eq!(fresh["deprecated"], false);
+
+    let (status, deprecated) = call(
+        &db,
+        Method::PUT,
+        &alice_token,
+        json!({ "message": "  use noir-bignum instead " }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(deprecated["deprecated"], true);
+    assert_eq!(deprecated["deprecation_message"], "use noir-bignum instead");
+
+    let (_, fetched) = get(&db, "/api/packages/lib").await;
+    assert_eq!(fetched["deprecation_message"], "use noir-bignum instead");
+
+    let (status, lifted) = call(&db, Method::DELETE, &alice_token, json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(lifted["deprecated"], false);
+    assert_eq!(lifted["deprecation_message"], Value::Null);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn only_the_owner_can_deprecate() {
+    let db = TestDb::new().await;
+    let (_alice, alice_token) = user(&db.pool, 1, "alice").await;
+    let (_bob, bob_token) = user(&db.pool, 2, "bob").await;
+    package_storage::insert_package(&db.pool, &package("lib", "alice", 1))
+        .await
+        .unwrap();
+
+    let (status, _) = call(&db, Method::PUT, &bob_token, json!({ "message": "gone" })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = call(&db, Method::PUT, &alice_token, json!({ "message": " " })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, fetched) = get(&db, "/api/packages/lib").await;
+    assert_eq!(fetched["deprecated"], false);
+}
@@ -0,0 +1,115 @@
+//! Download counting against a real Postgres (see `common` for how to run).
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::{TestDb, get, package, send};
+use noir_registry_server::package_storage;
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn concurrent_downloads_are_all_counted() {
+    let db = TestDb::new().await;
+    package_storage::insert_package(&db.pool, &package("noir-bignum", "noir-lang", 1))
+        .await
+        .unwrap();
+
+    let pings: Vec<_> = (0..50)
+        .map(|_| {
+            let pool = db.pool.clone();
+            tokio::spawn(async move {
+                package_storage::increment_downloads(&pool, "noir-bignum", None).await
+            })
+        })
+        .collect();
+    for ping in pings {
+        ping.await.unwrap().unwrap();
+    }
+
+    let stored = package_storage::get_package_by_name(&db.pool, "noir-bignum")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.total_downloads, 50);
+
+    let today: i32 = sqlx::query_scalar(
+        "SELECT count FROM package_daily_downloads d
+         JOIN packages p ON p.id = d.package_id
+         WHERE p.name = 'noir-bignum'",
+    )
+    .fetch_one(&db.pool)
+    .await
+    .unwrap();
+    assert_eq!(today, 50);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn downloads_of_unknown_packages_are_ignored() {
+    let db = TestDb::new().await;
+    package_storage::increment_downloads(&db.pool, "missing", None)
+        .await
+        .unwrap();
+
+    let buckets: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM package_daily_downloads")
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+    assert_eq!(buckets, 0);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn versioned_downloads_are_counted_per_version() {
+    let db = TestDb::new().await;
+    package_storage::insert_package(&db.pool, &package("noir-bignum", "noir-lang", 1))
+        .await
+        .unwrap();
+    let id = package_storage::get_package_by_name(&db.pool, "noir-bignum")
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+    for (version, sha) in [("v0.1.0", "a".repeat(40)), ("v0.2.0", "b".repeat(40))] {
+        package_storage::record_package_version(&db.pool, id, version, &sha)
+            .await
+            .unwrap();
+    }
+
+    // An unknown version still counts towards the total
+    for query in [
+        "?version=v0.2.0",
+        "?version=v0.2.0",
+        "?version=v0.1.0",
+        "?version=v9",
+        "",
+    ] {
+        let request = Request::post(format!("/api/packages/noir-bignum/download{}", query))
+            .body(Body::empty())
+            .unwrap();
+        let (status, _) = send(&db, request).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    let (status, versions) = get(&db, "/api/packages/noir-bignum/versions").await;
+    assert_eq!(status, StatusCode::OK);
+    let counts: Vec<(&str, i64)> = versions
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| {
+            (
+                v["version"].as_str().unwrap(),
+                v["downloads"].as_i64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(counts, [("v0.1.0", 1), ("v0.2.0", 2)]);
+
+    let (_, package) = get(&db, "/api/packages/noir-bignum").await;
+    assert_eq!(package["total_downloads"], 5);
+
+    let (status, _) = get(&db, "/api/packages/missing/versions").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,102 @@
+//! Exports of `/api/packages` (CSV, and the NDJSON stream `nargo registry
+//! export` reads), and importing them back.
+
+mod common;
+
+use axum::http::StatusCode;
+use common::{TestDb, get_text, package};
+use noir_registry_server::models::ImportPackage;
+use noir_registry_server::package_storage;
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn csv_export_streams_every_matching_package() {
+    let db = TestDb::new().await;
+    for (name, stars, requirement) in [
+        ("quoted,name", 3, None),
+        ("old", 2, Some(">=0.10.0, <0.20.0")),
+        ("new", 1, Some(">=1.0.0-beta.1")),
+    ] {
+        let mut pkg = package(name, "owner", stars);
+        pkg.compiler_version = requirement.map(str::to_string);
+        package_storage::insert_package(&db.pool, &pkg)
+            .await
+            .unwrap();
+    }
+
+    let (status, body) = get_text(&db, "/api/packages?format=csv").await;
+    assert_eq!(status, StatusCode::OK);
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "name,stars,downloads,license,owner,repo",
+            "\"quoted,name\",3,0,MIT,owner,\"https://github.com/owner/quoted,name\"",
+            "old,2,0,MIT,owner,https://github.com/owner/old",
+            "new,1,0,MIT,owner,https://github.com/owner/new",
+        ]
+    );
+
+    let (_, body) = get_text(&db, "/api/packages?format=csv&compiler=1.0.0").await;
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("\"quoted,name\","));
+    assert!(lines[2].starts_with("new,"));
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn export_and_import_round_trip_the_version_history() {
+    let source = TestDb::new().await;
+    package_storage::insert_package(&source.pool, &package("noir-bignum", "noir-lang", 10))
+        .await
+        .unwrap();
+    let id = package_storage::get_package_by_name(&source.pool, "noir-bignum")
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+    package_storage::save_keywords(&source.pool, id, &["bigint".to_string()])
+        .await
+        .unwrap();
+    for (version, sha) in [("v0.1.0", "a".repeat(40)), ("v0.2.0", "b".repeat(40))] {
+        package_storage::record_package_version(&source.pool, id, version, &sha)
+            .await
+            .unwrap();
+    }
+
+    // What `nargo registry export` writes: the streamed records without ids
+    let mut stream = package_storage::stream_packages(source.pool.clone(), None, None);
+    let mut records = Vec::new();
+    while let Some(package) = stream.recv().await {
+        let mut record = serde_json::to_value(package.unwrap()).unwrap();
+        record.as_object_mut().unwrap().remove("id");
+        records.push(record);
+    }
+    assert_eq!(records.len(), 1);
+    let versions = &records[0]["versions"];
+    assert_eq!(versions[0]["version"], "v0.1.0");
+    assert_eq!(versions[1]["commit_sha"], "b".repeat(40));
+
+    let target = TestDb::new().await;
+    let packages: Vec<ImportPackage> =
+        serde_json::from_value(serde_json::Value::Array(records)).unwrap();
+    let results = package_storage::import_packages(&target.pool, &packages)
+        .await
+        .unwrap();
+    assert!(results.iter().all(|r| r.success), "{:?}", results);
+
+    for (version, sha) in [("v0.1.0", "a".repeat(40)), ("v0.2.0", "b".repeat(40))] {
+        assert_eq!(
+            package_storage::get_version_commit_sha(&target.pool, "noir-bignum", version)
+                .await
+                .unwrap(),
+            Some(sha)
+        );
+    }
+    let imported = package_storage::get_package_by_name(&target.pool, "noir-bignum")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(imported.keywords, ["bigint"]);
+}
@@ -0,0 +1,75 @@
+//! Owner contact lookup for moderators, against a real Postgres (see `common`
+//! for how to run).
+
+mod common;
+
+use common::{TestDb, package, set_publisher};
+use noir_registry_server::auth::{self, GithubUser};
+use noir_registry_server::package_storage;
+
+fn github_user(id: i32, login: &str, email: Option<&str>) -> GithubUser {
+    GithubUser {
+        id,
+        login: login.to_string(),
+        avatar_url: format!("https://avatars.example.com/{}", login),
+        email: email.map(str::to_string),
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn owner_contact_comes_from_the_publisher_or_the_owners_account() {
+    let db = TestDb::new().await;
+    for (name, owner) in [
+        ("published", "noir-lang"),
+        ("scraped", "Alice"),
+        ("orphan", "nobody"),
+    ] {
+        package_storage::insert_package(&db.pool, &package(name, owner, 1))
+            .await
+            .unwrap();
+    }
+    let publisher = auth::create_user(
+        &db.pool,
+        &github_user(1, "publisher", Some("publisher@example.com")),
+    )
+    .await
+    .unwrap();
+    set_publisher(&db.pool, "published", &publisher).await;
+    // A blank email is treated like a private one
+    auth::create_user(&db.pool, &github_user(2, "alice", Some("  ")))
+        .await
+        .unwrap();
+
+    let published = package_storage::get_owner_contact(&db.pool, "published")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        published.account_github_username.as_deref(),
+        Some("publisher")
+    );
+    assert_eq!(published.email.as_deref(), Some("publisher@example.com"));
+
+    // Matched case-insensitively on the GitHub owner
+    let scraped = package_storage::get_owner_contact(&db.pool, "scraped")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(scraped.account_github_username.as_deref(), Some("alice"));
+    assert_eq!(scraped.email, None);
+
+    let orphan = package_storage::get_owner_contact(&db.pool, "orphan")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(orphan.owner_github_username, "nobody");
+    assert_eq!(orphan.account_github_username, None);
+
+    assert!(
+        package_storage::get_owner_contact(&db.pool, "missing")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
@@ -0,0 +1,108 @@
+//! Publish checks that run before the server contacts GitHub, against a real
+//! Postgres (see `common` for how to run).
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use common::{TestDb, send, user};
+use noir_registry_server::package_storage;
+use serde_json::{Value, json};
+
+async fn publish(db: &TestDb, token: &str, name: &str, url: &str) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/packages/publish")
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!({ "name": name, "github_repository_url": url }).to_string(),
+        ))
+        .unwrap();
+    send(db, request).await
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn publish_rejects_repository_urls_on_internal_hosts() {
+    let db = TestDb::new().await;
+    let (_, token) = user(&db.pool, 1, "alice").await;
+
+    for url in [
+        "http://localhost/alice/lib",
+        "http://127.0.0.1:5432/alice/lib",
+        "http://169.254.169.254/latest/meta-data",
+        "http://192.168.0.10/github.com/alice/lib",
+        "http://[::1]/alice/lib",
+    ] {
+        let (status, body) = publish(&db, &token, "lib", url).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", url);
+        assert_eq!(body["success"], false);
+        assert!(
+            body["message"]
+                .as_str()
+                .unwrap()
+                .contains("private network"),
+            "{}: {}",
+            url,
+            body
+        );
+    }
+
+    assert!(
+        package_storage::get_package_by_name(&db.pool, "lib")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn publish_rejects_blocked_names_in_any_spelling() {
+    let db = TestDb::new().await;
+    let (_, token) = user(&db.pool, 1, "alice").await;
+    let url = "https://github.com/alice/lib";
+
+    // Seeded by the migration
+    let (status, body) = publish(&db, &token, "STD", url).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        body["message"],
+        "The name 'STD' is blocked: Reserved for the Noir standard library"
+    );
+
+    package_storage::block_name(&db.pool, "acme-lib", "Trademark of Acme")
+        .await
+        .unwrap();
+    for name in ["acme-lib", "Acme_Lib"] {
+        let (status, body) = publish(&db, &token, name, url).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}", name);
+        assert!(
+            body["message"]
+                .as_str()
+                .unwrap()
+                .ends_with("Trademark of Acme")
+        );
+    }
+
+    assert!(
+        package_storage::unblock_name(&db.pool, "ACME_LIB")
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        package_storage::blocked_name_reason(&db.pool, "acme-lib")
+            .await
+            .unwrap(),
+        None
+    );
+    let names: Vec<String> = package_storage::list_blocked_names(&db.pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|b| b.name)
+        .collect();
+    assert!(names.contains(&"nargo".to_string()));
+    assert!(!names.contains(&"acme_lib".to_string()));
+}
@@ -0,0 +1,64 @@
+//! `DB_QUERY_TIMEOUT_SECS` against a real Postgres (see `common` for how to
+//! run). A file of its own because the timeout is read once per process.
+
+mod common;
+
+use common::{TestDb, package};
+use noir_registry_server::package_storage::{self, QueryTimeout, with_query_timeout};
+use std::time::{Duration, Instant};
+
+fn set_one_second_timeout() {
+    // SAFETY: every test in this binary sets the same value before its first
+    // query, so a concurrent read sees either nothing or "1"
+    unsafe { std::env::set_var("DB_QUERY_TIMEOUT_SECS", "1") };
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn slow_query_fails_with_query_timeout() {
+    set_one_second_timeout();
+    let db = TestDb::new().await;
+
+    let started = Instant::now();
+    let err = with_query_timeout(sqlx::query("SELECT pg_sleep(5)").execute(&db.pool))
+        .await
+        .expect_err("pg_sleep(5) outlives a 1s timeout");
+    assert!(started.elapsed() < Duration::from_secs(4));
+    let timeout = err
+        .downcast_ref::<QueryTimeout>()
+        .expect("error is a QueryTimeout");
+    assert_eq!(timeout.0, Duration::from_secs(1));
+    assert_eq!(err.to_string(), "query timed out after 1s");
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn storage_call_blocked_on_a_lock_times_out() {
+    set_one_second_timeout();
+    let db = TestDb::new().await;
+    package_storage::insert_package(&db.pool, &package("locked", "owner", 1))
+        .await
+        .unwrap();
+
+    // Hold the row lock from another transaction, sleeping while it does
+    let pool = db.pool.clone();
+    let holder = tokio::spawn(async move {
+        let mut tx = pool.begin().await.unwrap();
+        sqlx::query("SELECT id FROM packages WHERE name = 'locked' FOR UPDATE")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        sqlx::query("SELECT pg_sleep(3)")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        tx.rollback().await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let err = package_storage::increment_downloads(&db.pool, "locked", None)
+        .await
+        .expect_err("the row stays locked past the timeout");
+    assert!(err.downcast_ref::<QueryTimeout>().is_some(), "{}", err);
+    holder.await.unwrap();
+}
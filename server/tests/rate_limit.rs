@@ -0,0 +1,67 @@
+//! The publish rate limit against a real Postgres (see `common` for how to
+//! run). A file of its own because the limit is read from the environment.
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::{TestDb, user};
+use noir_registry_server::rest_apis;
+use tower::ServiceExt;
+
+fn set_limit_of_two() {
+    // SAFETY: every test in this binary sets the same value before building
+    // its router, so a concurrent read sees either nothing or "2"
+    unsafe { std::env::set_var("PUBLISH_RATE_LIMIT_PER_MIN", "2") };
+}
+
+fn publish(bearer: &str) -> Request<Body> {
+    // An empty body is rejected by the handler, after the rate limit and
+    // before anything reaches GitHub
+    Request::post("/api/packages/publish")
+        .header("authorization", format!("Bearer {}", bearer))
+        .header("content-type", "application/json")
+        .body(Body::from("{}"))
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn made_up_keys_share_the_client_address_limit() {
+    set_limit_of_two();
+    let db = TestDb::new().await;
+    let router = rest_apis::create_router(db.pool.clone());
+
+    let mut statuses = Vec::new();
+    for i in 0..3 {
+        let response = router
+            .clone()
+            .oneshot(publish(&format!("made-up-key-{}", i)))
+            .await
+            .unwrap();
+        statuses.push(response.status());
+    }
+    assert_ne!(statuses[0], StatusCode::TOO_MANY_REQUESTS);
+    assert_ne!(statuses[1], StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(statuses[2], StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn valid_keys_are_limited_per_account() {
+    set_limit_of_two();
+    let db = TestDb::new().await;
+    let (_, alice) = user(&db.pool, 1, "alice").await;
+    let (_, bob) = user(&db.pool, 2, "bob").await;
+    let router = rest_apis::create_router(db.pool.clone());
+
+    let status = |bearer: String| {
+        let router = router.clone();
+        async move { router.oneshot(publish(&bearer)).await.unwrap().status() }
+    };
+    assert_ne!(status(alice.clone()).await, StatusCode::TOO_MANY_REQUESTS);
+    assert_ne!(status(alice.clone()).await, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(status(alice.clone()).await, StatusCode::TOO_MANY_REQUESTS);
+    // Another account from the same address has its own window
+    assert_ne!(status(bob).await, StatusCode::TOO_MANY_REQUESTS);
+}
@@ -0,0 +1,682 @@
+//! `package_storage` against a real Postgres (see `common` for how to run).
+
+mod common;
+
+use axum::http::StatusCode;
+use common::{TestDb, get, package};
+use noir_registry_server::auth::{self, GithubUser};
+use noir_registry_server::models::{CursorPage, UpdatedCursor};
+use noir_registry_server::package_storage::{self, SEARCH_RESULT_CAP, SearchFilters, SearchSort};
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn inserted_package_can_be_read_back() {
+    let db = TestDb::new().await;
+    let mut pkg = package("noir-bignum", "noir-lang", 42);
+    pkg.homepage = Some("https://noir-lang.org".to_string());
+    package_storage::insert_package(&db.pool, &pkg)
+        .await
+        .unwrap();
+
+    let stored = package_storage::get_package_by_name(&db.pool, "noir-bignum")
+        .await
+        .unwrap()
+        .expect("package was inserted");
+    assert_eq!(stored.name, "noir-bignum");
+    assert_eq!(
+        stored.description.as_deref(),
+        Some("The noir-bignum package")
+    );
+    assert_eq!(
+        stored.github_repository_url,
+        "https://github.com/noir-lang/noir-bignum"
+    );
+    assert_eq!(stored.owner_github_username, "noir-lang");
+    assert_eq!(stored.github_stars, 42);
+    assert_eq!(stored.license.as_deref(), Some("MIT"));
+    assert_eq!(stored.homepage.as_deref(), Some("https://noir-lang.org"));
+    assert_eq!(stored.total_downloads, 0);
+
+    assert!(
+        package_storage::get_package_by_name(&db.pool, "missing")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn all_packages_are_listed_by_stars_then_name() {
+    let db = TestDb::new().await;
+    for (name, stars) in [("b-lib", 5), ("a-lib", 5), ("popular", 50), ("new", 0)] {
+        package_storage::insert_package(&db.pool, &package(name, "owner", stars))
+            .await
+            .unwrap();
+    }
+
+    let names: Vec<String> = package_storage::get_all_packages(&db.pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    assert_eq!(names, ["popular", "a-lib", "b-lib", "new"]);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn search_ranks_name_matches_above_description_matches() {
+    let db = TestDb::new().await;
+    let mut described = package("merkle-tools", "owner", 100);
+    described.description = "Helpers for sha256 merkle trees".to_string();
+    package_storage::insert_package(&db.pool, &described)
+        .await
+        .unwrap();
+    package_storage::insert_package(&db.pool, &package("sha256", "owner", 1))
+        .await
+        .unwrap();
+    package_storage::insert_package(&db.pool, &package("unrelated", "owner", 10))
+        .await
+        .unwrap();
+
+    let names: Vec<String> = package_storage::search_packages(
+        &db.pool,
+        "sha256",
+        &SearchFilters::default(),
+        SearchSort::Relevance,
+    )
+    .await
+    .unwrap()
+    .packages
+    .into_iter()
+    .map(|p| p.name)
+    .collect();
+    assert_eq!(names, ["sha256", "merkle-tools"]);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn quotes_and_backslashes_are_stored_and_searched_literally() {
+    let db = TestDb::new().await;
+    let mut pkg = package("o'brien", "o'neil", 1);
+    pkg.description = r"It's a \ backslash'); DROP TABLE packages; --".to_string();
+    pkg.homepage = Some("https://example.com/?q='x'".to_string());
+    package_storage::insert_package(&db.pool, &pkg)
+        .await
+        .unwrap();
+
+    let stored = package_storage::get_package_by_name(&db.pool, "o'brien")
+        .await
+        .unwrap()
+        .expect("package with a quote in its name");
+    assert_eq!(stored.owner_github_username, "o'neil");
+    assert_eq!(
+        stored.description.as_deref(),
+        Some(pkg.description.as_str())
+    );
+    assert_eq!(stored.homepage, pkg.homepage);
+
+    for query in [
+        "o'brien",
+        "'",
+        r"\ backslash'",
+        "'); DROP TABLE packages; --",
+    ] {
+        let names: Vec<String> = package_storage::search_packages(
+            &db.pool,
+            query,
+            &SearchFilters::default(),
+            SearchSort::Relevance,
+        )
+        .await
+        .unwrap()
+        .packages
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+        assert_eq!(names, ["o'brien"], "searching {:?}", query);
+    }
+    assert_eq!(
+        package_storage::get_all_packages(&db.pool)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let id = stored.id;
+    let keyword = r"it's\".to_string();
+    package_storage::save_keywords(&db.pool, id, std::slice::from_ref(&keyword))
+        .await
+        .unwrap();
+    let by_keyword = package_storage::get_packages_by_keyword(&db.pool, &keyword)
+        .await
+        .unwrap();
+    assert_eq!(by_keyword.len(), 1);
+    assert_eq!(by_keyword[0].keywords, std::slice::from_ref(&keyword));
+    let filters = SearchFilters {
+        license: Some("MIT".to_string()),
+        min_stars: Some(1),
+        keywords: vec![keyword],
+    };
+    let found = package_storage::search_packages(&db.pool, "'", &filters, SearchSort::Stars)
+        .await
+        .unwrap();
+    assert_eq!(found.packages.len(), 1);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn github_login_with_quotes_and_backslashes_is_stored_literally() {
+    let db = TestDb::new().await;
+    let github_user = GithubUser {
+        id: 42,
+        login: r"o'brien\'); DROP TABLE users; --".to_string(),
+        avatar_url: r"https://avatars.example.com/u/42?v='4'\".to_string(),
+        email: None,
+    };
+    let user = auth::create_user(&db.pool, &github_user).await.unwrap();
+    assert_eq!(user.github_username, github_user.login);
+    assert_eq!(user.github_avatar_url, Some(github_user.avatar_url.clone()));
+
+    let found = auth::get_user_by_github_username(&db.pool, &github_user.login)
+        .await
+        .unwrap()
+        .expect("user with quotes in their login");
+    assert_eq!(found.id, user.id);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn search_matches_owner_usernames() {
+    let db = TestDb::new().await;
+    package_storage::insert_package(&db.pool, &package("bignum", "AztecProtocol", 5))
+        .await
+        .unwrap();
+    package_storage::insert_package(&db.pool, &package("aztec-types", "someone", 1))
+        .await
+        .unwrap();
+    package_storage::insert_package(&db.pool, &package("poseidon", "noir-lang", 50))
+        .await
+        .unwrap();
+
+    let names: Vec<String> = package_storage::search_packages(
+        &db.pool,
+        "aztec",
+        &SearchFilters::default(),
+        SearchSort::Relevance,
+    )
+    .await
+    .unwrap()
+    .packages
+    .into_iter()
+    .map(|p| p.name)
+    .collect();
+    // The name match ranks first; "bignum" is only found through its owner
+    assert_eq!(names, ["aztec-types", "bignum"]);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn only_one_scrape_run_starts_at_a_time() {
+    let db = TestDb::new().await;
+    let first = package_storage::start_scrape_run(&db.pool)
+        .await
+        .unwrap()
+        .expect("nothing else is running");
+    assert!(
+        package_storage::start_scrape_run(&db.pool)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    package_storage::finish_scrape_run(&db.pool, first, None)
+        .await
+        .unwrap();
+    assert!(
+        package_storage::start_scrape_run(&db.pool)
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn rescrape_keeps_downloads_version_and_keywords() {
+    let db = TestDb::new().await;
+    let mut pkg = package("noir-bignum", "noir-lang", 10);
+    pkg.homepage = Some("https://noir-lang.org".to_string());
+    package_storage::insert_package(&db.pool, &pkg)
+        .await
+        .unwrap();
+    let id = package_storage::get_package_by_name(&db.pool, "noir-bignum")
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+
+    for _ in 0..3 {
+        package_storage::increment_downloads(&db.pool, "noir-bignum", None)
+            .await
+            .unwrap();
+    }
+    sqlx::query("UPDATE packages SET latest_version = 'v0.4.0' WHERE id = $1")
+        .bind(id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    package_storage::save_keywords(&db.pool, id, &["bigint".to_string()])
+        .await
+        .unwrap();
+
+    // GitHub now reports more stars and a new description, but no homepage
+    let mut rescraped = package("noir-bignum", "noir-lang", 25);
+    rescraped.description = "Big integers for Noir".to_string();
+    package_storage::insert_package(&db.pool, &rescraped)
+        .await
+        .unwrap();
+
+    let stored = package_storage::get_package_by_name(&db.pool, "noir-bignum")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.id, id);
+    assert_eq!(stored.github_stars, 25);
+    assert_eq!(stored.description.as_deref(), Some("Big integers for Noir"));
+    assert_eq!(stored.total_downloads, 3);
+    assert_eq!(stored.latest_version.as_deref(), Some("v0.4.0"));
+    assert_eq!(stored.keywords, ["bigint"]);
+    assert_eq!(stored.homepage.as_deref(), Some("https://noir-lang.org"));
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn updated_since_pages_through_identical_timestamps() {
+    let db = TestDb::new().await;
+    for name in ["a", "b", "c", "d", "e"] {
+        package_storage::insert_package(&db.pool, &package(name, "owner", 1))
+            .await
+            .unwrap();
+    }
+    let updated_at = chrono::DateTime::parse_from_rfc3339("2026-05-01T12:00:00.123456Z")
+        .unwrap()
+        .with_timezone(&chrono::Utc);
+    sqlx::query("UPDATE packages SET updated_at = $1")
+        .bind(updated_at)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+    let mut cursor = UpdatedCursor::after(updated_at - chrono::Duration::seconds(1));
+    let mut seen = Vec::new();
+    loop {
+        let page = package_storage::get_updated_since(&db.pool, &cursor, None, 2)
+            .await
+            .unwrap();
+        let Some(next) = CursorPage::new(page.clone(), 2).next_cursor else {
+            break;
+        };
+        cursor = UpdatedCursor::parse(&next).expect("next_cursor parses");
+        seen.extend(page.into_iter().map(|p| p.name));
+    }
+    assert_eq!(seen, ["a", "b", "c", "d", "e"]);
+
+    let after_all = UpdatedCursor::after(updated_at);
+    assert!(
+        package_storage::get_updated_since(&db.pool, &after_all, None, 10)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn compiler_filter_applies_before_the_page_limit() {
+    let db = TestDb::new().await;
+    // The two incompatible packages sort first in the feed
+    for (name, requirement) in [
+        ("old-1", Some(">=0.10.0, <0.20.0")),
+        ("old-2", Some(">=0.10.0, <0.20.0")),
+        ("new", Some(">=1.0.0-beta.1")),
+        ("any", None),
+    ] {
+        let mut pkg = package(name, "owner", 1);
+        pkg.compiler_version = requirement.map(str::to_string);
+        package_storage::insert_package(&db.pool, &pkg)
+            .await
+            .unwrap();
+    }
+
+    let compatible = package_storage::compatible_requirements(&db.pool, "1.0.0-beta.3")
+        .await
+        .unwrap();
+    assert_eq!(compatible, [">=1.0.0-beta.1"]);
+
+    let since = chrono::Utc::now() - chrono::Duration::hours(1);
+    let names: Vec<String> = package_storage::get_updated_since(
+        &db.pool,
+        &UpdatedCursor::after(since),
+        Some(&compatible),
+        2,
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|p| p.name)
+    .collect();
+    assert_eq!(names, ["new", "any"]);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn search_sorts_before_applying_the_cap() {
+    let db = TestDb::new().await;
+    for i in 0..SEARCH_RESULT_CAP {
+        package_storage::insert_package(&db.pool, &package(&format!("lib-{:03}", i), "owner", 1))
+            .await
+            .unwrap();
+    }
+    // Ranks last by relevance (description match only) but has the most stars
+    let mut starred = package("starred", "owner", 1000);
+    starred.description = "Another lib".to_string();
+    package_storage::insert_package(&db.pool, &starred)
+        .await
+        .unwrap();
+
+    let by_relevance = package_storage::search_packages(
+        &db.pool,
+        "lib",
+        &SearchFilters::default(),
+        SearchSort::Relevance,
+    )
+    .await
+    .unwrap();
+    assert!(by_relevance.truncated);
+    assert_eq!(by_relevance.packages.len(), SEARCH_RESULT_CAP);
+    assert!(by_relevance.packages.iter().all(|p| p.name != "starred"));
+
+    let by_stars = package_storage::search_packages(
+        &db.pool,
+        "lib",
+        &SearchFilters::default(),
+        SearchSort::Stars,
+    )
+    .await
+    .unwrap();
+    assert!(by_stars.truncated);
+    assert_eq!(by_stars.packages[0].name, "starred");
+
+    let few = package_storage::search_packages(
+        &db.pool,
+        "starred",
+        &SearchFilters::default(),
+        SearchSort::Stars,
+    )
+    .await
+    .unwrap();
+    assert!(!few.truncated);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn search_filters_apply_before_the_cap() {
+    let db = TestDb::new().await;
+    for i in 0..SEARCH_RESULT_CAP {
+        package_storage::insert_package(&db.pool, &package(&format!("lib-{:03}", i), "owner", 1))
+            .await
+            .unwrap();
+    }
+    // Ranks below the cap by relevance, so only a filter in the query finds it
+    let mut apache = package("zz-apache", "owner", 1);
+    apache.description = "Another lib".to_string();
+    apache.license = Some("Apache-2.0".to_string());
+    package_storage::insert_package(&db.pool, &apache)
+        .await
+        .unwrap();
+    let id = package_storage::get_package_by_name(&db.pool, "zz-apache")
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+    package_storage::save_keywords(&db.pool, id, &["hash".to_string(), "merkle".to_string()])
+        .await
+        .unwrap();
+
+    let search = |filters: SearchFilters| {
+        let pool = db.pool.clone();
+        async move {
+            package_storage::search_packages(&pool, "lib", &filters, SearchSort::Relevance)
+                .await
+                .unwrap()
+                .packages
+                .into_iter()
+                .map(|p| p.name)
+                .collect::<Vec<_>>()
+        }
+    };
+    let by_license = search(SearchFilters {
+        license: Some(" apache-2.0 ".to_string()),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(by_license, ["zz-apache"]);
+
+    let by_keywords = search(SearchFilters {
+        keywords: vec!["Merkle".to_string(), "hash".to_string()],
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(by_keywords, ["zz-apache"]);
+    let missing_keyword = search(SearchFilters {
+        keywords: vec!["merkle".to_string(), "bigint".to_string()],
+        ..Default::default()
+    })
+    .await;
+    assert!(missing_keyword.is_empty());
+
+    let by_stars = search(SearchFilters {
+        min_stars: Some(2),
+        ..Default::default()
+    })
+    .await;
+    assert!(by_stars.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn packages_page_is_cut_and_counted_in_the_query() {
+    let db = TestDb::new().await;
+    for (name, stars, requirement) in [
+        ("a", 4, None),
+        ("b", 3, Some(">=0.10.0, <0.20.0")),
+        ("c", 2, Some(">=1.0.0-beta.1")),
+        ("d", 1, None),
+    ] {
+        let mut pkg = package(name, "owner", stars);
+        pkg.compiler_version = requirement.map(str::to_string);
+        package_storage::insert_package(&db.pool, &pkg)
+            .await
+            .unwrap();
+    }
+
+    let page = package_storage::get_packages_page(&db.pool, None, None, 2, 1)
+        .await
+        .unwrap();
+    let names: Vec<&str> = page.items.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["b", "c"]);
+    assert_eq!(page.total, 4);
+    assert_eq!(page.next_cursor.as_deref(), Some("3"));
+
+    let compatible = package_storage::compatible_requirements(&db.pool, "1.0.0")
+        .await
+        .unwrap();
+    let page = package_storage::get_packages_page(&db.pool, None, Some(&compatible), 2, 2)
+        .await
+        .unwrap();
+    let names: Vec<&str> = page.items.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["d"]);
+    assert_eq!(page.total, 3);
+    assert_eq!(page.next_cursor, None);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn stale_rescrape_does_not_regress_fresher_data() {
+    let db = TestDb::new().await;
+    let mut stale = package("noir-bignum", "noir-lang", 10);
+    stale.fetched_at = chrono::Utc::now() - chrono::Duration::hours(1);
+    let mut fresh = package("noir-bignum", "noir-lang", 25);
+    fresh.description = "Big integers for Noir".to_string();
+
+    // The fresher fetch lands first, then a run that fetched earlier finishes
+    assert!(
+        package_storage::insert_package(&db.pool, &fresh)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !package_storage::insert_package(&db.pool, &stale)
+            .await
+            .unwrap()
+    );
+
+    let stored = package_storage::get_package_by_name(&db.pool, "noir-bignum")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.github_stars, 25);
+    assert_eq!(stored.description.as_deref(), Some("Big integers for Noir"));
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn rescrape_with_zero_stars_keeps_the_stored_count() {
+    let db = TestDb::new().await;
+    package_storage::insert_package(&db.pool, &package("noir-bignum", "noir-lang", 25))
+        .await
+        .unwrap();
+
+    // A partial GitHub response reads as 0 stars
+    let mut partial = package("noir-bignum", "noir-lang", 0);
+    partial.description = "Big integers for Noir".to_string();
+    assert!(
+        package_storage::insert_package(&db.pool, &partial)
+            .await
+            .unwrap()
+    );
+
+    let stored = package_storage::get_package_by_name(&db.pool, "noir-bignum")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.github_stars, 25);
+    assert_eq!(stored.description.as_deref(), Some("Big integers for Noir"));
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn blank_scraped_description_is_served_as_null() {
+    let db = TestDb::new().await;
+    let mut pkg = package("noir-bignum", "noir-lang", 25);
+    pkg.description = "  \n".to_string();
+    package_storage::insert_package(&db.pool, &pkg)
+        .await
+        .unwrap();
+
+    let (_, body) = get(&db, "/api/packages/noir-bignum").await;
+    assert_eq!(body["description"], serde_json::Value::Null);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn exact_search_matches_only_the_normalized_name() {
+    let db = TestDb::new().await;
+    for name in ["noir-bignum", "noir-bignum-utils", "Other_Lib"] {
+        package_storage::insert_package(&db.pool, &package(name, "noir-lang", 1))
+            .await
+            .unwrap();
+    }
+    let names = |body: &serde_json::Value| -> Vec<String> {
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let (_, fuzzy) = get(&db, "/api/search?q=noir-bignum").await;
+    assert_eq!(names(&fuzzy).len(), 2);
+
+    for (query, expected) in [
+        ("noir-bignum", vec!["noir-bignum"]),
+        ("NOIR_bignum", vec!["noir-bignum"]),
+        ("other-lib", vec!["Other_Lib"]),
+        ("noir", vec![]),
+    ] {
+        let (status, body) = get(&db, &format!("/api/search?q={}&exact=true", query)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(names(&body), expected, "{}", query);
+    }
+
+    let (_, page) = get(&db, "/api/search?q=noir_bignum&exact=true&offset=0").await;
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["items"][0]["name"], "noir-bignum");
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn missing_version_commit_is_backfilled_once() {
+    let db = TestDb::new().await;
+    package_storage::insert_package(&db.pool, &package("lib", "alice", 1))
+        .await
+        .unwrap();
+    let lib = package_storage::get_package_by_name(&db.pool, "lib")
+        .await
+        .unwrap()
+        .unwrap();
+    // Recorded before commits were pinned
+    sqlx::query("INSERT INTO package_versions (package_id, version) VALUES ($1, 'v1.0.0')")
+        .bind(lib.id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+    let sha = "a".repeat(40);
+    assert!(
+        package_storage::backfill_version_commit_sha(&db.pool, lib.id, "v1.0.0", &sha)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        package_storage::get_version_commit_sha(&db.pool, "lib", "v1.0.0")
+            .await
+            .unwrap(),
+        Some(sha.clone())
+    );
+
+    // A stored SHA is never replaced, and unpublished tags get no row
+    let other = "b".repeat(40);
+    for version in ["v1.0.0", "v2.0.0"] {
+        assert!(
+            !package_storage::backfill_version_commit_sha(&db.pool, lib.id, version, &other)
+                .await
+                .unwrap()
+        );
+    }
+    assert_eq!(
+        package_storage::get_version_commit_sha(&db.pool, "lib", "v1.0.0")
+            .await
+            .unwrap(),
+        Some(sha)
+    );
+    assert_eq!(
+        package_storage::get_version_commit_sha(&db.pool, "lib", "v2.0.0")
+            .await
+            .unwrap(),
+        None
+    );
+}
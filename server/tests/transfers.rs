@@ -0,0 +1,206 @@
+//! Package ownership transfers through the HTTP API, against a real Postgres
+//! (see `common` for how to run).
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use common::{TestDb, package, send, set_publisher, user};
+use noir_registry_server::package_storage;
+use serde_json::{Value, json};
+
+async fn post(db: &TestDb, uri: &str, token: &str, body: Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    send(db, request).await
+}
+
+async fn owner_of(db: &TestDb, name: &str) -> String {
+    package_storage::get_package_by_name(&db.pool, name)
+        .await
+        .unwrap()
+        .unwrap()
+        .owner_github_username
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn owner_offers_and_recipient_accepts() {
+    let db = TestDb::new().await;
+    let (alice, alice_token) = user(&db.pool, 1, "alice").await;
+    let (_bob, bob_token) = user(&db.pool, 2, "bob").await;
+    package_storage::insert_package(&db.pool, &package("lib", "alice", 1))
+        .await
+        .unwrap();
+    set_publisher(&db.pool, "lib", &alice).await;
+
+    let (status, transfer) = post(
+        &db,
+        "/api/packages/lib/transfer",
+        &alice_token,
+        json!({ "new_owner_github_username": "bob" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(transfer["status"], "pending");
+    assert_eq!(transfer["to_github_username"], "bob");
+    // Nothing moves until bob accepts
+    assert_eq!(owner_of(&db, "lib").await, "alice");
+
+    let (status, transfer) = post(
+        &db,
+        "/api/packages/lib/transfer/accept",
+        &bob_token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(transfer["status"], "accepted");
+    assert_eq!(owner_of(&db, "lib").await, "bob");
+
+    // Accepting again finds nothing pending
+    let (status, _) = post(
+        &db,
+        "/api/packages/lib/transfer/accept",
+        &bob_token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn non_owner_cannot_offer_a_package() {
+    let db = TestDb::new().await;
+    let (alice, _) = user(&db.pool, 1, "alice").await;
+    let (_mallory, mallory_token) = user(&db.pool, 2, "mallory").await;
+    user(&db.pool, 3, "bob").await;
+    package_storage::insert_package(&db.pool, &package("lib", "alice", 1))
+        .await
+        .unwrap();
+    set_publisher(&db.pool, "lib", &alice).await;
+
+    let (status, body) = post(
+        &db,
+        "/api/packages/lib/transfer",
+        &mallory_token,
+        json!({ "new_owner_github_username": "mallory" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(body["error"].as_str().unwrap().contains("current owner"));
+
+    let (status, _) = post(
+        &db,
+        "/api/packages/lib/transfer",
+        "not-a-token",
+        json!({ "new_owner_github_username": "bob" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(owner_of(&db, "lib").await, "alice");
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn offer_lapses_once_the_sender_no_longer_owns_the_package() {
+    let db = TestDb::new().await;
+    let (alice, alice_token) = user(&db.pool, 1, "alice").await;
+    let (_bob, bob_token) = user(&db.pool, 2, "bob").await;
+    let (carol, _) = user(&db.pool, 3, "carol").await;
+    package_storage::insert_package(&db.pool, &package("lib", "alice", 1))
+        .await
+        .unwrap();
+    set_publisher(&db.pool, "lib", &alice).await;
+
+    let (status, transfer) = post(
+        &db,
+        "/api/packages/lib/transfer",
+        &alice_token,
+        json!({ "new_owner_github_username": "bob" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    // The package changes hands some other way before bob accepts
+    set_publisher(&db.pool, "lib", &carol).await;
+
+    let (status, _) = post(
+        &db,
+        "/api/packages/lib/transfer/accept",
+        &bob_token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(owner_of(&db, "lib").await, "carol");
+    let lapsed =
+        package_storage::get_package_transfer(&db.pool, transfer["id"].as_i64().unwrap() as i32)
+            .await
+            .unwrap()
+            .unwrap();
+    assert_eq!(lapsed.status, "cancelled");
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn accepted_transfer_survives_republish_and_rescrape() {
+    let db = TestDb::new().await;
+    let (alice, alice_token) = user(&db.pool, 1, "alice").await;
+    let (_bob, bob_token) = user(&db.pool, 2, "bob").await;
+    package_storage::insert_package(&db.pool, &package("lib", "alice", 1))
+        .await
+        .unwrap();
+    set_publisher(&db.pool, "lib", &alice).await;
+
+    post(
+        &db,
+        "/api/packages/lib/transfer",
+        &alice_token,
+        json!({ "new_owner_github_username": "bob" }),
+    )
+    .await;
+    let (status, _) = post(
+        &db,
+        "/api/packages/lib/transfer/accept",
+        &bob_token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // alice still owns the GitHub repository, but can't publish over bob
+    let (status, body) = post(
+        &db,
+        "/api/packages/publish",
+        &alice_token,
+        json!({ "name": "lib", "github_repository_url": "https://github.com/alice/lib" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(
+        body["message"]
+            .as_str()
+            .unwrap()
+            .contains("another registry account")
+    );
+
+    // A scrape refreshes the metadata but leaves the owner alone
+    assert!(
+        package_storage::insert_package(&db.pool, &package("lib", "alice", 7))
+            .await
+            .unwrap()
+    );
+    let lib = package_storage::get_package_by_name(&db.pool, "lib")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(lib.owner_github_username, "bob");
+    assert_eq!(lib.github_stars, 7);
+}
@@ -0,0 +1,124 @@
+//! Configuring the publish webhook through the HTTP API, against a real
+//! Postgres (see `common` for how to run).
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use common::{TestDb, send, user};
+use noir_registry_server::auth;
+use serde_json::{Value, json};
+
+async fn webhook(
+    db: &TestDb,
+    method: Method,
+    token: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri("/api/webhook")
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Content-Type", "application/json")
+        .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+        .unwrap();
+    send(db, request).await
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn webhook_is_set_with_a_secret_shown_once_and_cleared() {
+    let db = TestDb::new().await;
+    let (alice, token) = user(&db.pool, 1, "alice").await;
+
+    let (status, body) = webhook(
+        &db,
+        Method::PUT,
+        &token,
+        Some(json!({ "url": "https://ci.example.com/hooks/noir" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["url"], "https://ci.example.com/hooks/noir");
+    let secret = body["secret"].as_str().expect("secret").to_string();
+
+    let stored = auth::get_webhook(&db.pool, alice.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.secret, secret);
+
+    // Reading it back never shows the secret again
+    let (status, body) = webhook(&db, Method::GET, &token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "url": "https://ci.example.com/hooks/noir" }));
+
+    let (status, _) = webhook(&db, Method::DELETE, &token, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(
+        auth::get_webhook(&db.pool, alice.id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn webhook_needs_https_and_auth() {
+    let db = TestDb::new().await;
+    let (_alice, token) = user(&db.pool, 1, "alice").await;
+
+    let (status, _) = webhook(
+        &db,
+        Method::PUT,
+        &token,
+        Some(json!({ "url": "http://ci.example.com/hooks/noir" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = webhook(
+        &db,
+        Method::PUT,
+        "not-a-token",
+        Some(json!({ "url": "https://ci.example.com/hooks/noir" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker or TEST_DATABASE_URL"]
+async fn webhook_rejects_internal_addresses() {
+    let db = TestDb::new().await;
+    let (alice, token) = user(&db.pool, 1, "alice").await;
+
+    for url in [
+        "https://localhost/hooks",
+        "https://127.0.0.1/hooks",
+        "https://[::1]/hooks",
+        "https://10.0.0.5/hooks",
+        "https://192.168.1.1/hooks",
+        "https://172.16.0.1/hooks",
+        "https://169.254.169.254/latest/meta-data",
+    ] {
+        let (status, body) = webhook(&db, Method::PUT, &token, Some(json!({ "url": url }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", url);
+        assert!(
+            body["error"]
+                .as_str()
+                .unwrap_or_default()
+                .contains("private network"),
+            "{}: {}",
+            url,
+            body
+        );
+    }
+    assert!(
+        auth::get_webhook(&db.pool, alice.id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}